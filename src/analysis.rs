@@ -0,0 +1,172 @@
+use std::fs;
+use std::io;
+
+use tracing::{error, warn};
+
+use super::board::Board;
+use super::engine::Engine;
+use super::game::{Game, Ptn};
+use super::provider::MoveProvider;
+use super::seek::{capstones_for_size, flatstones_for_size};
+use super::{err, ContinueCommand, ConvertCommand};
+
+// Has the engine search a position taken from a PTN file or a TPS string.
+pub async fn run_continue(command: &ContinueCommand) -> io::Result<()> {
+    let game = load_position(command).map_err(|error| {
+        error!(%error, "Could not load the position.");
+        error
+    })?;
+
+    let mut engine =
+        Engine::initialize(&command.engine_arguments, &command.engine_settings, &game).await?;
+    engine.warm_up(&command.engine_settings, game.size).await?;
+
+    print!("{}", game.position_string());
+
+    let result = engine
+        .analyze(&game, &format!("movetime {}", command.movetime), |line| {
+            if line.starts_with("info") {
+                println!("{line}");
+            }
+        })
+        .await;
+
+    if let Ok(best_move) = &result {
+        match engine.take_comment() {
+            Some(comment) => println!("Best move: {} {{{comment}}}", best_move.to_ptn()),
+            None => println!("Best move: {}", best_move.to_ptn()),
+        }
+    }
+
+    engine.quit().await?;
+
+    result.map(|_| ())
+}
+
+// Prints the TPS of a position in a PTN file, or a PTN that starts from a TPS.
+pub fn run_convert(command: &ConvertCommand) -> io::Result<()> {
+    let result = if let Some(tps) = &command.tps {
+        Board::from_tps(tps).map(|board| format!("[Size \"{}\"]\n[TPS \"{tps}\"]", board.size()))
+    } else if let Some(path) = &command.ptn {
+        ptn_to_tps(&fs::read_to_string(path)?, command.ply)
+    } else {
+        unreachable!();
+    };
+
+    match result {
+        Ok(output) => {
+            println!("{output}");
+            Ok(())
+        }
+        Err(error) => {
+            error!(%error, "Could not convert the position.");
+            Err(error)
+        }
+    }
+}
+
+fn ptn_to_tps(ptn: &str, ply: Option<usize>) -> io::Result<String> {
+    let ptn = Ptn::parse(ptn)?;
+
+    let mut board = match (ptn.tag("TPS"), ptn.tag("Size")) {
+        (Some(tps), _) => Board::from_tps(tps)?,
+        (None, Some(size)) => match size.parse::<u32>() {
+            Ok(size @ 3..=8) => Board::new(size),
+            _ => return Err(err!("invalid Size tag")),
+        },
+        (None, None) => return Err(err!("the PTN has no Size tag")),
+    };
+
+    let ply = ply.unwrap_or(ptn.moves.len());
+    if ply > ptn.moves.len() {
+        return Err(err!(format!("the PTN only has {} plies", ptn.moves.len())));
+    }
+
+    for game_move in &ptn.moves[..ply] {
+        board
+            .play(game_move)
+            .map_err(|error| err!(format!("{} is not legal: {error}", game_move.to_ptn())))?;
+    }
+
+    Ok(board.to_tps())
+}
+
+fn load_position(command: &ContinueCommand) -> io::Result<Game> {
+    let ptn = match &command.ptn {
+        Some(path) => Some(Ptn::parse(&fs::read_to_string(path)?)?),
+        None => None,
+    };
+
+    let tag = |name: &str| -> io::Result<Option<f32>> {
+        ptn.as_ref()
+            .and_then(|p| p.tag(name))
+            .map(|v| v.parse::<f32>())
+            .transpose()
+            .map_err(|_| err!(format!("invalid {name} tag")))
+    };
+
+    let size = match &command.tps {
+        Some(tps) => tps
+            .split_ascii_whitespace()
+            .next()
+            .map_or(0, |rows| rows.split('/').count() as u32),
+        None => tag("Size")?.ok_or_else(|| err!("the PTN has no Size tag"))? as u32,
+    };
+
+    if !(3..=8).contains(&size) {
+        return Err(err!("the board size must be between 3 and 8"));
+    }
+
+    let mut game = Game {
+        size,
+        half_komi: (tag("Komi")?.unwrap_or_default() * 2.0) as i32,
+        flatstones: tag("Flats")?.map_or(flatstones_for_size(size), |f| f as u32),
+        capstones: tag("Caps")?.map_or(capstones_for_size(size), |c| c as u32),
+        tps: command.tps.clone(),
+        ..Default::default()
+    };
+
+    if let Some(ptn) = ptn {
+        game.tps = ptn.tag("TPS").map(|t| t.to_owned());
+        game.moves = ptn.moves;
+
+        match command.ply {
+            Some(ply) => game.moves.truncate(ply),
+            None => {
+                if let Some(result) = &ptn.result {
+                    warn!(result, "The game in the PTN is already over.");
+                }
+            }
+        }
+    }
+
+    if let Some(half_komi) = command.half_komi {
+        game.half_komi = half_komi;
+    }
+
+    Ok(game)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_ptn_to_tps() {
+        let ptn = "[Size \"5\"]\n\n1. a1 e5\n2. Cc3 d3\n3. c3>";
+
+        assert_eq!(
+            ptn_to_tps(ptn, None).unwrap(),
+            "x4,1/x5/x3,21C,x/x5/2,x4 2 3"
+        );
+        assert_eq!(ptn_to_tps(ptn, Some(2)).unwrap(), "x4,1/x5/x5/x5/2,x4 1 2");
+        assert!(ptn_to_tps(ptn, Some(6)).is_err());
+        assert!(ptn_to_tps("1. a1 e5", None).is_err());
+
+        let ptn = "[Size \"5\"]\n[TPS \"x4,1/x5/x5/x5/2,x4 1 2\"]\n\n2. Cc3 d3";
+        assert_eq!(
+            ptn_to_tps(ptn, None).unwrap(),
+            "x4,1/x5/x2,1C,2,x/x5/2,x4 1 3"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::{Duration, Instant};
+
+use clap::Args;
+
+use super::seek::Seek;
+
+const DEFAULT_TEMPLATE: &str = "{name} is seeking {size}x{size} {time}+{increment}, komi {komi}";
+
+// Shouting the seek in the global chat, so that people browsing it can find the bot.
+#[derive(Args, Clone, Debug, Default)]
+pub struct Announce {
+    #[arg(long, action)]
+    pub announce: bool,
+    #[arg(long, value_name = "TEXT", requires = "announce")]
+    pub announce_template: Option<String>,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 1800,
+        requires = "announce"
+    )]
+    pub announce_interval: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Announcer {
+    settings: Announce,
+    // The scheduled window the last announcement was made in, and when.
+    last: Option<(Option<usize>, Instant)>,
+}
+
+impl Announcer {
+    pub fn new(settings: Announce) -> Self {
+        Self {
+            settings,
+            last: None,
+        }
+    }
+
+    // Whether posting the seek should be announced: the first time, when a different scheduled
+    // window has opened since the last announcement, or once the interval has passed.
+    pub fn is_due(&self, window: Option<usize>) -> bool {
+        let interval = Duration::from_secs(self.settings.announce_interval);
+
+        self.settings.announce
+            && self
+                .last
+                .is_none_or(|(last, time)| last != window || time.elapsed() >= interval)
+    }
+
+    pub fn announced(&mut self, window: Option<usize>) {
+        self.last = Some((window, Instant::now()));
+    }
+
+    pub fn text(&self, name: &str, seek: &Seek) -> String {
+        let template = self
+            .settings
+            .announce_template
+            .as_deref()
+            .unwrap_or(DEFAULT_TEMPLATE);
+
+        fill(template, name, seek)
+    }
+}
+
+// Fills in the template's placeholders: {name}, {size}, {time} in minutes, {increment} in
+// seconds, {komi}, {color}, and {rated}.
+fn fill(template: &str, name: &str, seek: &Seek) -> String {
+    let time = match seek.time % 60 {
+        0 => (seek.time / 60).to_string(),
+        seconds => format!("{}:{seconds:02}", seek.time / 60),
+    };
+    let komi = match seek.half_komi % 2 {
+        0 => (seek.half_komi / 2).to_string(),
+        _ => format!("{}", f64::from(seek.half_komi) / 2.0),
+    };
+    let color = format!("{:?}", seek.color).to_lowercase();
+    let rated = if seek.unrated { "unrated" } else { "rated" };
+
+    template
+        .replace("{name}", name)
+        .replace("{size}", &seek.size.to_string())
+        .replace("{time}", &time)
+        .replace("{increment}", &seek.increment.to_string())
+        .replace("{komi}", &komi)
+        .replace("{color}", &color)
+        .replace("{rated}", rated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fill_template() {
+        let mut seek = "Seek new 1 Bot 6 900 10 A 4 30 1 0 0 0 0"
+            .parse::<Seek>()
+            .unwrap();
+        assert_eq!(
+            fill(DEFAULT_TEMPLATE, "Bot", &seek),
+            "Bot is seeking 6x6 15+10, komi 2"
+        );
+
+        seek.time = 450;
+        seek.half_komi = 3;
+        seek.unrated = true;
+        assert_eq!(
+            fill("{time} {komi} {rated} {color}", "Bot", &seek),
+            "7:30 1.5 unrated random"
+        );
+
+        let mut announcer = Announcer::new(Announce {
+            announce: true,
+            announce_template: None,
+            announce_interval: 1800,
+        });
+        assert!(announcer.is_due(None));
+        announcer.announced(None);
+        assert!(!announcer.is_due(None));
+        assert!(announcer.is_due(Some(0)));
+    }
+}
@@ -0,0 +1,219 @@
+use std::io;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::game::{Game, GameMove};
+use super::seek::Seek;
+use super::{err, runtime};
+
+// How long a request to the PlayTak.com web API may take.  Lookups made at the start of a game
+// come out of the clock, so this is kept short.
+const API_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The player's rating, or None if they don't have one, like guests.
+pub async fn rating(api_url: &str, player: &str) -> io::Result<Option<u32>> {
+    let url = format!("{}/ratings/{player}", api_url.trim_end_matches('/'));
+
+    Ok(get_json(url)
+        .await?
+        .and_then(|player| player.get("rating")?.as_f64())
+        .map(|rating| rating.round() as u32))
+}
+
+// A seek as the API lists it, with the same fields as the protocol's.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiSeek {
+    pub id: u32,
+    pub creator: String,
+    pub opponent: Option<String>,
+    pub size: u32,
+    pub time: u32,
+    pub increment: u32,
+    // "W", "B", or "A".
+    pub color: String,
+    pub komi: i32,
+    pub pieces: u32,
+    pub capstones: u32,
+    #[serde(default)]
+    pub unrated: bool,
+    #[serde(default)]
+    pub tournament: bool,
+    #[serde(default)]
+    pub extra_time_move: u32,
+    #[serde(default)]
+    pub extra_time_amount: u32,
+}
+
+// A game in progress.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiGame {
+    pub id: u32,
+    pub white: String,
+    pub black: String,
+    pub size: u32,
+    pub time: u32,
+    pub increment: u32,
+    pub komi: i32,
+}
+
+// The seeks up on the server, which can be had without logging in.
+pub async fn seeks(api_url: &str) -> io::Result<Vec<Seek>> {
+    let url = format!("{}/seeks", api_url.trim_end_matches('/'));
+
+    list::<ApiSeek>(url)
+        .await?
+        .into_iter()
+        .map(|seek| Seek::try_from(seek).map_err(|error| err!(error)))
+        .collect()
+}
+
+pub async fn games(api_url: &str) -> io::Result<Vec<ApiGame>> {
+    list(format!("{}/games", api_url.trim_end_matches('/'))).await
+}
+
+async fn list<T: for<'de> Deserialize<'de>>(url: String) -> io::Result<Vec<T>> {
+    let Some(list) = get_json(url).await? else {
+        return Ok(Vec::new());
+    };
+
+    serde_json::from_value(list).map_err(|error| err!(error.to_string()))
+}
+
+// A finished game as the server recorded it, with the moves in the protocol's notation,
+// separated by commas.
+#[derive(Debug, Deserialize)]
+pub struct GameRecord {
+    pub result: String,
+    pub notation: String,
+}
+
+impl GameRecord {
+    // How the record differs from our own copy of the game, if it does.
+    pub fn mismatch(&self, game: &Game) -> Option<String> {
+        if game.result.as_deref() != Some(self.result.as_str()) {
+            return Some(format!(
+                "the result was {}, not {}",
+                self.result,
+                game.result.as_deref().unwrap_or("unknown")
+            ));
+        }
+
+        let moves = self
+            .notation
+            .split(',')
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+            .map(|m| GameMove::from_playtak(&format!("Game {m}")))
+            .collect::<io::Result<Vec<_>>>();
+        let moves = match moves {
+            Ok(moves) => moves,
+            Err(error) => return Some(format!("its moves could not be read: {error}")),
+        };
+
+        if let Some(ply) =
+            (0..moves.len().min(game.moves.len())).find(|&i| moves[i] != game.moves[i])
+        {
+            return Some(format!(
+                "ply {} was {}, not {}",
+                ply + 1,
+                moves[ply].to_ptn(),
+                game.moves[ply].to_ptn()
+            ));
+        }
+        if moves.len() != game.moves.len() {
+            return Some(format!(
+                "it has {} plies, not {}",
+                moves.len(),
+                game.moves.len()
+            ));
+        }
+
+        None
+    }
+}
+
+// None if the server has no record of the game, which it only makes once the game is over.
+pub async fn game(api_url: &str, id: u32) -> io::Result<Option<GameRecord>> {
+    let url = format!("{}/games-history/{id}", api_url.trim_end_matches('/'));
+
+    match get_json(url).await? {
+        Some(record) => serde_json::from_value(record)
+            .map(Some)
+            .map_err(|error| err!(error.to_string())),
+        None => Ok(None),
+    }
+}
+
+// None if there's nothing at the URL.
+async fn get_json(url: String) -> io::Result<Option<Value>> {
+    runtime::spawn_blocking(move || {
+        let agent = ureq::AgentBuilder::new().timeout(API_TIMEOUT).build();
+
+        match agent.get(&url).call() {
+            Ok(response) => response.into_json().map(Some),
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(error) => Err(err!(error.to_string())),
+        }
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::seek::SeekColor;
+
+    #[test]
+    fn parse_api_seeks() {
+        let seeks = serde_json::from_str::<Vec<ApiSeek>>(
+            r#"[{"id": 7, "creator": "Alice", "opponent": "", "size": 6, "time": 900,
+                 "increment": 10, "color": "B", "komi": 4, "pieces": 30, "capstones": 1,
+                 "unrated": true}]"#,
+        )
+        .unwrap();
+        let seek = Seek::try_from(seeks.into_iter().next().unwrap()).unwrap();
+
+        assert_eq!(seek.id, Some(7));
+        assert_eq!(seek.player.as_deref(), Some("Alice"));
+        assert!(matches!(seek.color, SeekColor::Black));
+        assert_eq!(seek.opponent, None);
+        assert!(seek.unrated && !seek.tournament);
+        assert_eq!(seek.extra_time_move, None);
+    }
+
+    #[test]
+    fn compare_game_records() {
+        let mut game = "Game Start 7 5 Alice vs Bob white 600 0 21 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+        game.moves = ["a1", "e5", "Cc3"]
+            .into_iter()
+            .map(|m| GameMove::from_ptn(m).unwrap())
+            .collect();
+        game.result = Some("R-0".to_owned());
+
+        let record = |result: &str, notation: &str| GameRecord {
+            result: result.to_owned(),
+            notation: notation.to_owned(),
+        };
+
+        assert_eq!(record("R-0", "P A1,P E5,P C3 C").mismatch(&game), None);
+        assert_eq!(
+            record("0-R", "P A1,P E5,P C3 C").mismatch(&game).unwrap(),
+            "the result was 0-R, not R-0"
+        );
+        assert_eq!(
+            record("R-0", "P A1,P E5,P C3").mismatch(&game).unwrap(),
+            "ply 3 was c3, not Cc3"
+        );
+        assert_eq!(
+            record("R-0", "P A1,P E5").mismatch(&game).unwrap(),
+            "it has 2 plies, not 3"
+        );
+    }
+}
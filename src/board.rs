@@ -0,0 +1,752 @@
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::str::FromStr;
+
+use super::err;
+use super::game::{Direction, GameMove, PieceType};
+use super::seek::{capstones_for_size, flatstones_for_size};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Color {
+    #[default]
+    White,
+    Black,
+}
+
+impl Color {
+    pub fn opposite(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "white" => Ok(Color::White),
+            "black" => Ok(Color::Black),
+            _ => Err("invalid color"),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Color::White => "white",
+            Color::Black => "black",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Piece {
+    pub color: Color,
+    pub piece_type: PieceType,
+}
+
+// Just enough of the rules of Tak to follow a game's moves and see what they do.
+#[derive(Clone, Debug)]
+pub struct Board {
+    size: u32,
+    // Each stack is listed from the bottom up.
+    stacks: Vec<Vec<Piece>>,
+    ply: usize,
+    // The stones each player starts with.
+    flatstones: u32,
+    capstones: u32,
+}
+
+impl Board {
+    pub fn new(size: u32) -> Self {
+        Self {
+            size,
+            stacks: vec![Vec::new(); (size * size) as usize],
+            ply: 0,
+            flatstones: flatstones_for_size(size),
+            capstones: capstones_for_size(size),
+        }
+    }
+
+    pub fn set_stones(&mut self, flatstones: u32, capstones: u32) {
+        self.flatstones = flatstones;
+        self.capstones = capstones;
+    }
+
+    pub fn from_tps(tps: &str) -> io::Result<Self> {
+        let parts = tps.split_ascii_whitespace().collect::<Vec<_>>();
+        let [rows, player, number] = parts[..] else {
+            return Err(err!("tps must have a board, a player, and a move number"));
+        };
+
+        let rows = rows.split('/').collect::<Vec<_>>();
+        let size = rows.len() as u32;
+
+        if !(3..=8).contains(&size) {
+            return Err(err!("tps board size must be between 3 and 8"));
+        }
+
+        let mut board = Self::new(size);
+
+        // Rows are listed from the top of the board down.
+        for (y, row) in (0..size).rev().zip(rows) {
+            let mut x = 0u32;
+
+            for square in row.split(',') {
+                if let Some(count) = square.strip_prefix('x') {
+                    let count = match count {
+                        "" => 1,
+                        count => count
+                            .parse::<u32>()
+                            .map_err(|_| err!("invalid tps empty square count"))?,
+                    };
+                    x = x
+                        .checked_add(count)
+                        .filter(|&x| x <= size)
+                        .ok_or_else(|| err!("tps row is too long"))?;
+                    continue;
+                }
+
+                if x >= size {
+                    return Err(err!("tps row is too long"));
+                }
+
+                let (colors, top) = match square.strip_suffix(['S', 'C']) {
+                    Some(colors) if square.ends_with('S') => (colors, PieceType::StandingStone),
+                    Some(colors) => (colors, PieceType::Capstone),
+                    None => (square, PieceType::Flatstone),
+                };
+
+                if colors.is_empty() {
+                    return Err(err!("tps stack is empty"));
+                }
+
+                let index = board.index(x, y);
+                for (i, c) in colors.chars().enumerate() {
+                    let color = match c {
+                        '1' => Color::White,
+                        '2' => Color::Black,
+                        _ => return Err(err!("invalid tps stone color")),
+                    };
+                    let piece_type = if i == colors.len() - 1 {
+                        top
+                    } else {
+                        PieceType::Flatstone
+                    };
+
+                    board.stacks[index].push(Piece { color, piece_type });
+                }
+
+                x += 1;
+            }
+
+            if x != size {
+                return Err(err!("tps row has the wrong number of squares"));
+            }
+        }
+
+        let player = match player {
+            "1" => 0,
+            "2" => 1,
+            _ => return Err(err!("invalid tps player to move")),
+        };
+        let number = number
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n >= 1)
+            .ok_or_else(|| err!("invalid tps move number"))?;
+
+        board.ply = (number - 1) * 2 + player;
+
+        Ok(board)
+    }
+
+    pub fn to_tps(&self) -> String {
+        let rows = (0..self.size)
+            .rev()
+            .map(|y| {
+                let mut squares = Vec::new();
+                let mut empty = 0;
+
+                for x in 0..self.size {
+                    let stack = self.stack(x, y);
+
+                    if stack.is_empty() {
+                        empty += 1;
+                        continue;
+                    }
+
+                    if empty > 0 {
+                        squares.push(empty_squares(empty));
+                        empty = 0;
+                    }
+
+                    squares.push(stack_to_tps(stack));
+                }
+
+                if empty > 0 {
+                    squares.push(empty_squares(empty));
+                }
+
+                squares.join(",")
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        let player = match self.to_move() {
+            Color::White => 1,
+            Color::Black => 2,
+        };
+
+        format!("{rows} {player} {}", self.ply / 2 + 1)
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn stack(&self, x: u32, y: u32) -> &[Piece] {
+        &self.stacks[self.index(x, y)]
+    }
+
+    pub fn to_move(&self) -> Color {
+        if self.ply.is_multiple_of(2) {
+            Color::White
+        } else {
+            Color::Black
+        }
+    }
+
+    // Returns true if the move is a capstone flattening a standing stone.
+    pub fn is_smash(&self, game_move: &GameMove) -> bool {
+        let GameMove::Spread {
+            x,
+            y,
+            direction,
+            drops,
+        } = game_move
+        else {
+            return false;
+        };
+
+        if *x >= self.size || *y >= self.size {
+            return false;
+        }
+
+        let carries_capstone = self
+            .stack(*x, *y)
+            .last()
+            .is_some_and(|p| p.piece_type == PieceType::Capstone);
+
+        let target = self.step(*x, *y, direction, drops.len() as u32);
+
+        carries_capstone
+            && drops.last() == Some(&1)
+            && target.is_some_and(|(tx, ty)| {
+                self.stack(tx, ty)
+                    .last()
+                    .is_some_and(|p| p.piece_type == PieceType::StandingStone)
+            })
+    }
+
+    pub fn play(&mut self, game_move: &GameMove) -> io::Result<()> {
+        match game_move {
+            GameMove::Place { x, y, piece_type } => {
+                game_move.check_size(self.size)?;
+
+                if !self.stack(*x, *y).is_empty() {
+                    return Err(err!("placement is on an occupied square"));
+                }
+
+                if self.ply < 2 && *piece_type != PieceType::Flatstone {
+                    return Err(err!("only flatstones can be placed on the first turn"));
+                }
+
+                // Each player places one of their opponent's flatstones on their first turn.
+                let color = match (self.ply < 2, self.to_move()) {
+                    (true, Color::White) | (false, Color::Black) => Color::Black,
+                    (true, Color::Black) | (false, Color::White) => Color::White,
+                };
+
+                if self.stones_left(color, *piece_type) == 0 {
+                    return Err(err!("the player has no stones of that type left"));
+                }
+
+                let index = self.index(*x, *y);
+                self.stacks[index].push(Piece {
+                    color,
+                    piece_type: *piece_type,
+                });
+            }
+            GameMove::Spread {
+                x,
+                y,
+                direction,
+                drops,
+            } => {
+                if self.ply < 2 {
+                    return Err(err!("stones can't be moved on the first turn"));
+                }
+
+                game_move.check_size(self.size)?;
+                let smash = self.is_smash(game_move);
+
+                let count = drops.iter().sum::<u32>() as usize;
+                let source = self.index(*x, *y);
+
+                if count > self.stacks[source].len() {
+                    return Err(err!(format!(
+                        "the spread picks up {count} stones from a stack of {}",
+                        self.stacks[source].len()
+                    )));
+                }
+
+                if self.stacks[source].last().map(|p| p.color) != Some(self.to_move()) {
+                    return Err(err!("spread of a stack the player doesn't control"));
+                }
+
+                // Check every square first so that an illegal spread leaves the board untouched.
+                for distance in 0..drops.len() {
+                    let (tx, ty) = self.step(*x, *y, direction, distance as u32 + 1).unwrap();
+
+                    let last = distance == drops.len() - 1;
+
+                    match self.stack(tx, ty).last().map(|p| p.piece_type) {
+                        None | Some(PieceType::Flatstone) => (),
+                        Some(PieceType::StandingStone) if smash && last => (),
+                        _ => return Err(err!("spread is blocked")),
+                    }
+                }
+
+                let split = self.stacks[source].len() - count;
+                let mut carried = self.stacks[source].split_off(split).into_iter();
+
+                for (distance, &drop) in drops.iter().enumerate() {
+                    let (tx, ty) = self.step(*x, *y, direction, distance as u32 + 1).unwrap();
+                    let target = self.index(tx, ty);
+
+                    if let Some(top) = self.stacks[target].last_mut() {
+                        top.piece_type = PieceType::Flatstone;
+                    }
+
+                    self.stacks[target].extend(carried.by_ref().take(drop as usize));
+                }
+            }
+        }
+
+        self.ply += 1;
+
+        Ok(())
+    }
+
+    pub fn legal_moves(&self) -> Vec<GameMove> {
+        let mut moves = Vec::new();
+
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if self.stack(x, y).is_empty() {
+                    for piece_type in [
+                        PieceType::Flatstone,
+                        PieceType::StandingStone,
+                        PieceType::Capstone,
+                    ] {
+                        moves.push(GameMove::Place { x, y, piece_type });
+                    }
+                    continue;
+                }
+
+                let carry = self.size.min(self.stack(x, y).len() as u32);
+
+                for direction in [
+                    Direction::North,
+                    Direction::South,
+                    Direction::East,
+                    Direction::West,
+                ] {
+                    let reach = (1..=carry)
+                        .take_while(|&d| self.step(x, y, &direction, d).is_some())
+                        .count() as u32;
+
+                    for count in 1..=carry {
+                        for drops in partitions(count, reach) {
+                            moves.push(GameMove::Spread {
+                                x,
+                                y,
+                                direction,
+                                drops,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        moves.retain(|game_move| self.clone().play(game_move).is_ok());
+        moves
+    }
+
+    // Whether the player has a road: their flatstones and capstones on top of stacks, in an
+    // unbroken line from one edge of the board to the opposite one.
+    pub fn has_road(&self, color: Color) -> bool {
+        let last = self.size - 1;
+        self.connects(color, |x, _| x == 0, |x, _| x == last)
+            || self.connects(color, |_, y| y == 0, |_, y| y == last)
+    }
+
+    // Whether the player could finish a road with one move, if it were their turn.
+    pub fn has_road_threat(&self, color: Color) -> bool {
+        let mut board = self.clone();
+        if board.to_move() != color {
+            board.ply += 1;
+        }
+
+        // The first stones placed are the opponent's, and it's far too soon for a road anyway.
+        board.ply >= 2
+            && board.legal_moves().iter().any(|game_move| {
+                let mut after = board.clone();
+                after.play(game_move).is_ok() && after.has_road(color)
+            })
+    }
+
+    // Whether the player's road squares join a square on one edge to a square on the other.
+    fn connects(
+        &self,
+        color: Color,
+        start: impl Fn(u32, u32) -> bool,
+        end: impl Fn(u32, u32) -> bool,
+    ) -> bool {
+        let is_road = |x: u32, y: u32| {
+            self.stack(x, y)
+                .last()
+                .is_some_and(|p| p.color == color && p.piece_type != PieceType::StandingStone)
+        };
+
+        let mut seen = vec![false; self.stacks.len()];
+        let mut frontier = Vec::new();
+        for y in 0..self.size {
+            for x in 0..self.size {
+                if start(x, y) && is_road(x, y) {
+                    seen[self.index(x, y)] = true;
+                    frontier.push((x, y));
+                }
+            }
+        }
+
+        while let Some((x, y)) = frontier.pop() {
+            if end(x, y) {
+                return true;
+            }
+
+            for direction in [
+                Direction::North,
+                Direction::South,
+                Direction::East,
+                Direction::West,
+            ] {
+                if let Some((nx, ny)) = self.step(x, y, &direction, 1) {
+                    if is_road(nx, ny) && !seen[self.index(nx, ny)] {
+                        seen[self.index(nx, ny)] = true;
+                        frontier.push((nx, ny));
+                    }
+                }
+            }
+        }
+
+        false
+    }
+
+    // A random flatstone placement if there's one to make, and otherwise any legal move.
+    pub fn fallback_move(&self) -> Option<GameMove> {
+        let moves = self.legal_moves();
+        let placements = moves
+            .iter()
+            .filter(|m| {
+                matches!(
+                    m,
+                    GameMove::Place {
+                        piece_type: PieceType::Flatstone,
+                        ..
+                    }
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let choices = if placements.is_empty() {
+            moves.iter().collect()
+        } else {
+            placements
+        };
+
+        if choices.is_empty() {
+            return None;
+        }
+
+        let random = RandomState::new().build_hasher().finish() as usize;
+        Some(choices[random % choices.len()].clone())
+    }
+
+    // Counts what's on the board rather than keeping a tally, so positions from TPS work too.
+    fn stones_left(&self, color: Color, piece_type: PieceType) -> u32 {
+        let capstone = piece_type == PieceType::Capstone;
+        let used = self
+            .stacks
+            .iter()
+            .flatten()
+            .filter(|p| p.color == color && (p.piece_type == PieceType::Capstone) == capstone)
+            .count() as u32;
+
+        let total = if capstone {
+            self.capstones
+        } else {
+            self.flatstones
+        };
+
+        total.saturating_sub(used)
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        (y * self.size + x) as usize
+    }
+
+    // The square some distance away in a direction, if it's on the board.
+    fn step(&self, x: u32, y: u32, direction: &Direction, distance: u32) -> Option<(u32, u32)> {
+        let (x, y) = match direction {
+            Direction::North => (Some(x), y.checked_add(distance)),
+            Direction::South => (Some(x), y.checked_sub(distance)),
+            Direction::East => (x.checked_add(distance), Some(y)),
+            Direction::West => (x.checked_sub(distance), Some(y)),
+        };
+
+        x.zip(y).filter(|&(x, y)| x < self.size && y < self.size)
+    }
+}
+
+// The board as text, with each stack written the way TPS writes it:
+//
+// 3  .   .   .
+// 2  .   21C .
+// 1  2   .   1
+//    a   b   c
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self
+            .stacks
+            .iter()
+            .map(|stack| stack.len() + 1)
+            .max()
+            .unwrap_or_default()
+            .max(4);
+
+        for y in (0..self.size).rev() {
+            let squares = (0..self.size)
+                .map(|x| match self.stack(x, y) {
+                    [] => format!("{:width$}", "."),
+                    stack => format!("{:width$}", stack_to_tps(stack)),
+                })
+                .collect::<String>();
+
+            writeln!(f, "{}  {}", y + 1, squares.trim_end())?;
+        }
+
+        let files = (0..self.size)
+            .map(|x| format!("{:width$}", (b'a' + x as u8) as char))
+            .collect::<String>();
+
+        write!(f, "   {}", files.trim_end())
+    }
+}
+
+// Every way of dropping a number of stones over at most some number of squares, one or more
+// stones per square.
+fn partitions(count: u32, squares: u32) -> Vec<Vec<u32>> {
+    if count == 0 {
+        return vec![Vec::new()];
+    }
+
+    if squares == 0 {
+        return Vec::new();
+    }
+
+    (1..=count)
+        .flat_map(|first| {
+            partitions(count - first, squares - 1)
+                .into_iter()
+                .map(move |mut rest| {
+                    rest.insert(0, first);
+                    rest
+                })
+        })
+        .collect()
+}
+
+// A stack the way TPS writes it, from the bottom up and with the top stone's type.
+fn stack_to_tps(stack: &[Piece]) -> String {
+    let mut square = stack
+        .iter()
+        .map(|p| match p.color {
+            Color::White => '1',
+            Color::Black => '2',
+        })
+        .collect::<String>();
+
+    match stack.last().map(|p| p.piece_type) {
+        None | Some(PieceType::Flatstone) => (),
+        Some(PieceType::StandingStone) => square.push('S'),
+        Some(PieceType::Capstone) => square.push('C'),
+    }
+
+    square
+}
+
+fn empty_squares(count: u32) -> String {
+    match count {
+        1 => "x".to_owned(),
+        n => format!("x{n}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(board: &mut Board, moves: &[&str]) {
+        for ptn in moves {
+            board.play(&GameMove::from_ptn(ptn).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn roads_and_threats() {
+        let mut board = Board::from_tps("1,1,1,1,x/x5/x5/x5/2,2,2,x2 1 5").unwrap();
+        assert!(!board.has_road(Color::White));
+        assert!(board.has_road_threat(Color::White));
+        assert!(!board.has_road_threat(Color::Black));
+
+        play(&mut board, &["e5"]);
+        assert!(board.has_road(Color::White));
+        assert!(!board.has_road(Color::Black));
+
+        // A wall doesn't count toward a road.
+        let board = Board::from_tps("1,1,1,1S,x/x5/x5/x5/2,2,2,x2 1 5").unwrap();
+        assert!(!board.has_road_threat(Color::White));
+        assert!(!Board::new(5).has_road_threat(Color::White));
+    }
+
+    #[test]
+    fn spreads_and_smashes() {
+        let mut board = Board::new(5);
+        play(&mut board, &["a1", "e5", "c3", "d3", "c3>"]);
+
+        assert_eq!(board.stack(0, 0)[0].color, Color::Black);
+        assert_eq!(board.stack(4, 4)[0].color, Color::White);
+        assert!(board.stack(2, 2).is_empty());
+        assert_eq!(
+            board
+                .stack(3, 2)
+                .iter()
+                .map(|p| p.color)
+                .collect::<Vec<_>>(),
+            [Color::Black, Color::White]
+        );
+
+        let mut board = Board::new(5);
+        play(&mut board, &["a1", "e5", "Cc3", "Sd3"]);
+
+        let smash = GameMove::from_ptn("c3>").unwrap();
+        assert!(board.is_smash(&smash));
+        board.play(&smash).unwrap();
+        assert_eq!(board.stack(3, 2)[0].piece_type, PieceType::Flatstone);
+        assert_eq!(board.stack(3, 2)[1].piece_type, PieceType::Capstone);
+
+        assert!(!board.is_smash(&GameMove::from_ptn("e5-").unwrap()));
+        assert!(board.play(&GameMove::from_ptn("a1<").unwrap()).is_err());
+        assert!(board.play(&GameMove::from_ptn("Sd3").unwrap()).is_err());
+
+        // Spreads off the board are errors, not panics.
+        let off_board = GameMove::from_ptn("h8<").unwrap();
+        assert!(!board.is_smash(&off_board));
+        assert!(board.play(&off_board).is_err());
+        assert!(Board::new(5)
+            .play(&GameMove::from_ptn("b6+").unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn illegal_placements() {
+        let mut board = Board::new(5);
+        assert!(board.play(&GameMove::from_ptn("Ca1").unwrap()).is_err());
+        assert!(board.play(&GameMove::from_ptn("Sa1").unwrap()).is_err());
+        play(&mut board, &["a1", "e5", "Cc3", "Cc4"]);
+        assert!(board.play(&GameMove::from_ptn("Cd3").unwrap()).is_err());
+        assert!(board.play(&GameMove::from_ptn("c3+").unwrap()).is_err());
+
+        let mut board = Board::from_tps("1,x2/x3/x2,2 1 2").unwrap();
+        board.set_stones(2, 0);
+        play(&mut board, &["b1", "b2"]);
+        assert!(board.play(&GameMove::from_ptn("c3").unwrap()).is_err());
+        assert!(board.play(&GameMove::from_ptn("Cc3").unwrap()).is_err());
+    }
+
+    #[test]
+    fn legal_moves() {
+        let mut board = Board::new(3);
+        assert_eq!(board.legal_moves().len(), 9);
+
+        play(&mut board, &["a1", "c3"]);
+        // 7 empty squares with a flatstone or a wall each, since 3x3 has no capstones, and c3
+        // can move down or left.
+        assert_eq!(board.legal_moves().len(), 7 * 2 + 2);
+
+        play(&mut board, &["b1", "b2", "b1<"]);
+        let moves = board.legal_moves();
+        assert!(moves.contains(&GameMove::from_ptn("b2-").unwrap()));
+        assert!(!moves.contains(&GameMove::from_ptn("a1>").unwrap()));
+
+        let fallback = board.fallback_move().unwrap();
+        assert!(matches!(
+            fallback,
+            GameMove::Place {
+                piece_type: PieceType::Flatstone,
+                ..
+            }
+        ));
+        assert!(board.clone().play(&fallback).is_ok());
+    }
+
+    #[test]
+    fn tps() {
+        let mut board = Board::new(5);
+        play(&mut board, &["a1", "e5", "Cc3", "d3", "c3>"]);
+
+        let tps = board.to_tps();
+        assert_eq!(tps, "x4,1/x5/x3,21C,x/x5/2,x4 2 3");
+
+        let board = Board::from_tps(&tps).unwrap();
+        assert_eq!(board.to_tps(), tps);
+        assert_eq!(board.to_move(), Color::Black);
+        assert_eq!(board.stack(3, 2)[1].piece_type, PieceType::Capstone);
+
+        assert!(Board::from_tps("x5/x5/x5/x5/x5 1").is_err());
+        assert!(Board::from_tps("x5/x5/x5/x5/x4 1 1").is_err());
+        assert!(Board::from_tps("x5/x5/x5/x5/x4,3 1 1").is_err());
+        assert!(Board::from_tps("x5/x5/x5/x5/x5 3 1").is_err());
+        assert!(Board::from_tps("x4294967295,x1/x5/x5/x5/x5 1 1").is_err());
+    }
+
+    #[test]
+    fn display() {
+        let mut board = Board::new(3);
+        play(&mut board, &["a1", "c3", "Sb2"]);
+
+        assert_eq!(
+            board.to_string(),
+            "3  .   .   1\n2  .   1S  .\n1  2   .   .\n   a   b   c"
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use tracing::info;
+
+// How far back messages are counted when checking a player for flooding.
+const FLOOD_WINDOW: Duration = Duration::from_secs(30);
+
+// However many players are asking, the bot doesn't say more than this in a minute.
+const MAX_REPLIES_PER_MINUTE: usize = 6;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ChatKind {
+    // A private message to us.
+    Tell,
+    // The server's echo of a private message we sent.
+    Told,
+    Shout,
+    ShoutRoom(String),
+}
+
+// A line of chat, with who it's from, or who it's to for one of our own tells.
+#[derive(Clone, Debug)]
+pub struct ChatMessage {
+    pub kind: ChatKind,
+    pub player: String,
+    pub text: String,
+    // The whole line, to be shown as it is.
+    pub line: String,
+}
+
+impl FromStr for ChatMessage {
+    type Err = &'static str;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (first, rest) = line.split_once(' ').ok_or("not a chat message")?;
+
+        let (kind, rest) = match first {
+            "Tell" => (ChatKind::Tell, rest),
+            "Told" => (ChatKind::Told, rest),
+            "Shout" => (ChatKind::Shout, rest),
+            "ShoutRoom" => {
+                let (room, rest) = rest.split_once(' ').ok_or("missing chat room")?;
+                (ChatKind::ShoutRoom(room.to_owned()), rest)
+            }
+            _ => return Err("not a chat message"),
+        };
+
+        let (player, text) = rest
+            .strip_prefix('<')
+            .and_then(|rest| rest.split_once('>'))
+            .ok_or("missing player name")?;
+
+        Ok(Self {
+            kind,
+            player: player.to_owned(),
+            text: text.trim().to_owned(),
+            line: line.to_owned(),
+        })
+    }
+}
+
+// Keeps other players from using the chat to make the bot do things or talk too much.
+#[derive(Args, Clone, Debug)]
+pub struct ChatModeration {
+    #[arg(long = "ignore", value_name = "PLAYER", value_delimiter = ',')]
+    pub ignore: Vec<String>,
+    // Players whose tells are taken as commands, like the control API's.
+    #[arg(long = "owner", value_name = "PLAYER", value_delimiter = ',')]
+    pub owners: Vec<String>,
+    #[arg(long, value_name = "MESSAGES", default_value_t = 5)]
+    pub chat_flood_limit: usize,
+    #[arg(long, value_name = "SECONDS", default_value_t = 600)]
+    pub chat_mute: u64,
+    #[arg(long, value_name = "SECONDS", default_value_t = 10)]
+    pub reply_interval: u64,
+}
+
+impl Default for ChatModeration {
+    fn default() -> Self {
+        Self {
+            ignore: Vec::new(),
+            owners: Vec::new(),
+            chat_flood_limit: 5,
+            chat_mute: 600,
+            reply_interval: 10,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Moderation {
+    settings: ChatModeration,
+    // When each player's recent messages came in, and the players muted for flooding.
+    recent: HashMap<String, VecDeque<Instant>>,
+    muted: HashMap<String, Instant>,
+    // When each player was last replied to, and the replies of the last minute.
+    replied: HashMap<String, Instant>,
+    replies: VecDeque<Instant>,
+}
+
+impl Moderation {
+    pub fn new(settings: ChatModeration) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    fn is_ignored(&self, player: &str) -> bool {
+        self.settings
+            .ignore
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(player))
+    }
+
+    pub fn is_owner(&self, player: &str) -> bool {
+        self.settings
+            .owners
+            .iter()
+            .any(|p| p.eq_ignore_ascii_case(player))
+    }
+
+    pub fn has_owners(&self) -> bool {
+        !self.settings.owners.is_empty()
+    }
+
+    // Whether a message should be paid any attention.  Players sending too many messages too
+    // quickly are muted for a while.
+    pub fn accepts(&mut self, chat: &ChatMessage) -> bool {
+        if chat.kind == ChatKind::Told || self.is_ignored(&chat.player) {
+            return false;
+        }
+
+        let player = chat.player.to_lowercase();
+        let mute = Duration::from_secs(self.settings.chat_mute);
+        if let Some(since) = self.muted.get(&player) {
+            if since.elapsed() < mute {
+                return false;
+            }
+            self.muted.remove(&player);
+        }
+
+        let now = Instant::now();
+        let recent = self.recent.entry(player.clone()).or_default();
+        recent.retain(|time| now.duration_since(*time) < FLOOD_WINDOW);
+        recent.push_back(now);
+
+        if recent.len() > self.settings.chat_flood_limit {
+            info!(
+                player = chat.player,
+                seconds = self.settings.chat_mute,
+                "Muting a player for flooding the chat."
+            );
+            self.recent.remove(&player);
+            self.muted.insert(player, now);
+            return false;
+        }
+
+        true
+    }
+
+    // Whether the bot may answer the player, counting the reply if so.  Owners can be answered
+    // as often as they like, within the overall limit.
+    pub fn may_reply(&mut self, player: &str) -> bool {
+        let player = player.to_lowercase();
+        let interval = Duration::from_secs(self.settings.reply_interval);
+        let now = Instant::now();
+
+        self.replies
+            .retain(|time| now.duration_since(*time) < Duration::from_secs(60));
+
+        let too_soon = self
+            .replied
+            .get(&player)
+            .is_some_and(|last| last.elapsed() < interval)
+            && !self.is_owner(&player);
+        if self.is_ignored(&player) || too_soon || self.replies.len() >= MAX_REPLIES_PER_MINUTE {
+            return false;
+        }
+
+        self.replied.insert(player, now);
+        self.replies.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moderate_chat() {
+        let chat = "ShoutRoom en <Alice> hello there"
+            .parse::<ChatMessage>()
+            .unwrap();
+        assert_eq!(chat.kind, ChatKind::ShoutRoom("en".to_owned()));
+        assert_eq!(
+            (chat.player.as_str(), chat.text.as_str()),
+            ("Alice", "hello there")
+        );
+        assert!("Tell Alice hi".parse::<ChatMessage>().is_err());
+
+        let mut moderation = Moderation::new(ChatModeration {
+            ignore: vec!["spammer".to_owned()],
+            owners: vec!["boss".to_owned()],
+            chat_flood_limit: 2,
+            ..Default::default()
+        });
+
+        let tell = |player: &str| format!("Tell <{player}> !status").parse().unwrap();
+        assert!(!moderation.accepts(&tell("Spammer")));
+        assert!(!moderation.may_reply("Spammer"));
+
+        assert!(moderation.accepts(&tell("Bob")));
+        assert!(moderation.accepts(&tell("Bob")));
+        assert!(!moderation.accepts(&tell("Bob")));
+        assert!(!moderation.accepts(&tell("bob")));
+        assert!(moderation.accepts(&tell("Carol")));
+
+        assert!(moderation.may_reply("Carol"));
+        assert!(!moderation.may_reply("carol"));
+        assert!(moderation.may_reply("Boss"));
+        assert!(moderation.may_reply("Boss"));
+        for player in ["A", "B", "C"] {
+            assert!(moderation.may_reply(player));
+        }
+        assert!(!moderation.may_reply("F"));
+    }
+}
@@ -0,0 +1,222 @@
+use super::board::Color;
+use super::game::Game;
+
+// Never think for less than this, in milliseconds.
+const MINIMUM_MOVE_TIME: u64 = 50;
+// Below this many seconds, or this many increments, moves are played as fast as is reasonable.
+const PANIC_SECONDS: u64 = 10;
+const PANIC_INCREMENTS: u64 = 5;
+// How much each new reading moves the estimate of the clock overhead.
+const DRIFT_SMOOTHING: f64 = 0.2;
+// More overhead than this, in milliseconds, is more likely a misread clock than a slow network.
+const MAX_OVERHEAD: f64 = 5000.0;
+
+// Decides how many milliseconds to spend on our next move, for engines that don't manage their
+// own time well.  The time left is spread over the moves the game is expected to still last,
+// with less spent in the opening and more in the middlegame.
+pub fn plan_move_time(game: &Game, buffer: u64) -> u64 {
+    let time_left = (u64::from(game.our_time()) * 1000).saturating_sub(buffer);
+    let increment = u64::from(game.increment.unwrap_or_default()) * 1000;
+
+    // A game tends to last about as many moves per player as there are squares.
+    let size = u64::from(game.size);
+    let move_number = game.moves.len() as u64 / 2 + 1;
+    let moves_left = (size * size).saturating_sub(move_number).max(size * 2);
+
+    let budget = if time_left < PANIC_SECONDS * 1000 || time_left < PANIC_INCREMENTS * increment {
+        time_left / 30 + increment / 2
+    } else {
+        let base = time_left / moves_left + increment * 3 / 4;
+
+        if move_number <= size {
+            base / 2
+        } else if move_number <= size * 3 {
+            base * 3 / 2
+        } else {
+            base
+        }
+    };
+
+    budget.min(time_left / 2).max(MINIMUM_MOVE_TIME)
+}
+
+// How much more time the server takes off our clock for each move than we see ourselves
+// spending on it, mostly the trip our move makes to the server.  The server's clocks are only
+// good to the second, so single readings are rough and the estimate is smoothed over many.
+#[derive(Debug, Default)]
+pub struct ClockDrift {
+    estimate: Option<f64>,
+}
+
+impl ClockDrift {
+    // Takes a reading of the drift in milliseconds, positive when the server's clock is behind
+    // ours, and returns the new estimate of the overhead.
+    pub fn observe(&mut self, drift: i64) -> u64 {
+        let drift = drift as f64;
+        let estimate = self.estimate.map_or(drift, |estimate| {
+            estimate + DRIFT_SMOOTHING * (drift - estimate)
+        });
+        self.estimate = Some(estimate);
+        self.overhead()
+    }
+
+    // Our clock is never taken to be ahead of the server's.
+    pub fn overhead(&self) -> u64 {
+        self.estimate
+            .unwrap_or_default()
+            .clamp(0.0, MAX_OVERHEAD)
+            .round() as u64
+    }
+}
+
+// The clocks as the server reported them after each ply, for working out afterwards how long
+// each move took.
+#[derive(Debug, Default)]
+pub struct ClockLog {
+    clocks: Vec<(usize, (u32, u32))>,
+}
+
+// How one side used its time, in seconds.
+#[derive(Debug, PartialEq)]
+pub struct TimeUsage {
+    pub color: Color,
+    // The time each timed move took, by move number.
+    pub moves: Vec<(usize, u32)>,
+    pub remaining: u32,
+}
+
+impl ClockLog {
+    pub fn new(game: &Game) -> Self {
+        Self {
+            clocks: vec![(game.moves.len(), game.time)],
+        }
+    }
+
+    pub fn record(&mut self, game: &Game) {
+        let ply = game.moves.len();
+        // Clocks from moves that were taken back don't count.
+        self.clocks.retain(|&(p, _)| p < ply);
+        self.clocks.push((ply, game.time));
+    }
+
+    // A move's time is whatever the mover's clock lost, plus the increment it got back.  Only
+    // moves with a clock reading from just before them are counted.
+    pub fn usage(&self, game: &Game) -> [TimeUsage; 2] {
+        let increment = game.increment.unwrap_or_default();
+        let mut usage = [Color::White, Color::Black].map(|color| TimeUsage {
+            color,
+            moves: Vec::new(),
+            remaining: match color {
+                Color::White => game.time.0,
+                Color::Black => game.time.1,
+            },
+        });
+
+        for pair in self.clocks.windows(2) {
+            let ((before, (white, black)), (after, clocks)) = (pair[0], pair[1]);
+            if after != before + 1 {
+                continue;
+            }
+
+            let mover = game.to_move_at(before);
+            let (had, has) = match mover {
+                Color::White => (white, clocks.0),
+                Color::Black => (black, clocks.1),
+            };
+            let side = &mut usage[usize::from(mover == Color::Black)];
+            side.moves
+                .push((after.div_ceil(2), (had + increment).saturating_sub(has)));
+        }
+
+        usage
+    }
+}
+
+impl TimeUsage {
+    pub fn total(&self) -> u32 {
+        self.moves.iter().map(|&(_, time)| time).sum()
+    }
+
+    pub fn average(&self) -> f64 {
+        match self.moves.len() {
+            0 => 0.0,
+            count => f64::from(self.total()) / count as f64,
+        }
+    }
+
+    // The longest think, and the move it was for.
+    pub fn longest(&self) -> Option<(usize, u32)> {
+        self.moves.iter().copied().max_by_key(|&(_, time)| time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Color;
+    use crate::game::GameMove;
+
+    fn game(seconds: u32, increment: u32, plies: usize) -> Game {
+        Game {
+            size: 6,
+            color: Color::White,
+            time: (seconds, seconds),
+            increment: Some(increment),
+            moves: vec![GameMove::from_ptn("a1").unwrap(); plies],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn move_time() {
+        let opening = plan_move_time(&game(600, 10, 2), 1000);
+        let middlegame = plan_move_time(&game(600, 10, 20), 1000);
+        let endgame = plan_move_time(&game(200, 10, 60), 1000);
+
+        assert!(opening < middlegame);
+        assert!(endgame < middlegame);
+        assert!(middlegame < 600_000 / 10);
+
+        // With little time left, moves come quickly, but the increment is still used.
+        assert_eq!(plan_move_time(&game(8, 0, 20), 1000), 233);
+        assert_eq!(plan_move_time(&game(8, 10, 20), 1000), 3500);
+
+        assert_eq!(plan_move_time(&game(0, 0, 20), 1000), MINIMUM_MOVE_TIME);
+    }
+
+    #[test]
+    fn time_usage() {
+        let mut game = game(600, 5, 0);
+        let mut log = ClockLog::new(&game);
+        for (white, black) in [(590, 600), (590, 580), (595, 580), (595, 560)] {
+            game.moves.push(GameMove::from_ptn("a1").unwrap());
+            game.time = (white, black);
+            log.record(&game);
+        }
+
+        // The last move is taken back and replayed more quickly.
+        game.moves.pop();
+        game.time = (595, 580);
+        log.record(&game);
+        game.moves.push(GameMove::from_ptn("a1").unwrap());
+        game.time = (595, 583);
+        log.record(&game);
+
+        let [white, black] = log.usage(&game);
+        assert_eq!(white.moves, vec![(1, 15), (2, 0)]);
+        assert_eq!(white.remaining, 595);
+        assert_eq!(black.moves, vec![(1, 25), (2, 2)]);
+        assert_eq!(black.longest(), Some((1, 25)));
+        assert_eq!(black.average(), 13.5);
+    }
+
+    #[test]
+    fn clock_drift() {
+        let mut drift = ClockDrift::default();
+        assert_eq!(drift.overhead(), 0);
+        assert_eq!(drift.observe(1000), 1000);
+        assert_eq!(drift.observe(0), 800);
+        assert_eq!(drift.observe(-4000), 0);
+        assert_eq!(drift.observe(60_000), 5000);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::err;
+use super::filter::OpponentFilter;
+use super::limits::SessionLimits;
+use super::notify::NotifierConfig;
+use super::schedule::Window;
+use super::seek::{Seek, SeekColor};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub seek: SeekConfig,
+    #[serde(rename = "account")]
+    pub accounts: Vec<AccountConfig>,
+    #[serde(rename = "preset")]
+    pub presets: BTreeMap<String, SeekConfig>,
+    #[serde(rename = "notify")]
+    pub notifiers: Vec<NotifierConfig>,
+    pub schedule: Vec<Window>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AccountConfig {
+    pub username: String,
+    pub password: String,
+    pub engine: Vec<String>,
+    #[serde(default)]
+    pub status_file: Option<PathBuf>,
+    // Layered on top of the top-level seek settings.
+    #[serde(default)]
+    pub seek: SeekConfig,
+}
+
+// Every setting is optional; anything left out keeps its command-line value.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SeekConfig {
+    pub size: Option<u32>,
+    pub time: Option<u32>,
+    pub increment: Option<u32>,
+    pub color: Option<SeekColor>,
+    pub half_komi: Option<i32>,
+    pub flatstones: Option<u32>,
+    pub capstones: Option<u32>,
+    pub unrated: Option<bool>,
+    pub tournament: Option<bool>,
+    pub extra_time_move: Option<u32>,
+    pub extra_time_amount: Option<u32>,
+    pub opponent: Option<String>,
+    pub only: Option<Vec<String>>,
+    pub block: Option<Vec<String>>,
+    pub min_rating: Option<u32>,
+    pub max_rating: Option<u32>,
+    pub rating_message: Option<String>,
+    pub max_games: Option<u32>,
+    pub max_games_per_opponent: Option<u32>,
+    pub opponent_cooldown: Option<u64>,
+    pub max_daily_games_per_opponent: Option<u32>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let config = toml::from_str::<Self>(&fs::read_to_string(path)?)
+            .map_err(|error| err!(error.message().to_owned()))?;
+
+        let seeks = std::iter::once(&config.seek)
+            .chain(config.accounts.iter().map(|a| &a.seek))
+            .chain(config.presets.values())
+            .chain(config.schedule.iter().map(|w| &w.seek));
+        for seek in seeks {
+            if seek.size.is_some_and(|s| !(3..=8).contains(&s)) {
+                return Err(err!("seek size must be between 3 and 8"));
+            }
+        }
+
+        for (name, preset) in &config.presets {
+            if preset.size.is_none() {
+                return Err(err!(format!("no size given for preset {name}")));
+            }
+        }
+
+        for account in &config.accounts {
+            if account.engine.is_empty() {
+                return Err(err!(format!(
+                    "no engine given for account {}",
+                    account.username
+                )));
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn preset(&self, name: &str) -> io::Result<&SeekConfig> {
+        self.presets
+            .get(name)
+            .ok_or_else(|| err!(format!("no preset named {name}")))
+    }
+
+    pub fn account(&self, username: &str) -> Option<&AccountConfig> {
+        self.accounts
+            .iter()
+            .find(|a| a.username.eq_ignore_ascii_case(username))
+    }
+}
+
+impl SeekConfig {
+    pub fn apply(&self, seek: &mut Seek, filter: &mut OpponentFilter, limits: &mut SessionLimits) {
+        seek.configure(self);
+
+        if let Some(only) = &self.only {
+            filter.only = only.clone();
+        }
+        if let Some(block) = &self.block {
+            filter.block = block.clone();
+        }
+        filter.min_rating = self.min_rating.or(filter.min_rating);
+        filter.max_rating = self.max_rating.or(filter.max_rating);
+        if let Some(message) = &self.rating_message {
+            filter.rating_message = Some(message.clone());
+        }
+
+        limits.max_games = self.max_games.or(limits.max_games);
+        limits.max_games_per_opponent = self
+            .max_games_per_opponent
+            .or(limits.max_games_per_opponent);
+        limits.opponent_cooldown = self.opponent_cooldown.or(limits.opponent_cooldown);
+        limits.max_daily_games_per_opponent = self
+            .max_daily_games_per_opponent
+            .or(limits.max_daily_games_per_opponent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_config() {
+        let config = toml::from_str::<Config>(
+            r#"
+            [seek]
+            size = 6
+            time = 900
+            color = "white"
+            block = ["Alice"]
+            max_games = 10
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.seek.size, Some(6));
+        assert_eq!(config.seek.time, Some(900));
+        assert!(matches!(config.seek.color, Some(SeekColor::White)));
+        assert_eq!(config.seek.block, Some(vec!["Alice".to_owned()]));
+        assert_eq!(config.seek.max_games, Some(10));
+        assert_eq!(config.seek.increment, None);
+
+        assert!(toml::from_str::<Config>("[seek]\nsiz = 6").is_err());
+    }
+
+    #[test]
+    fn parse_accounts() {
+        let config = toml::from_str::<Config>(
+            r#"
+            [seek]
+            time = 900
+
+            [[account]]
+            username = "BotOne"
+            password = "secret"
+            engine = ["path/to/engine", "--flag"]
+            seek = { size = 6 }
+
+            [[account]]
+            username = "BotTwo"
+            password = "secret"
+            engine = ["path/to/other/engine"]
+
+            [account.seek]
+            size = 5
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.accounts.len(), 2);
+        assert_eq!(config.accounts[0].engine, ["path/to/engine", "--flag"]);
+        assert_eq!(config.accounts[0].seek.size, Some(6));
+        assert_eq!(config.account("bottwo").unwrap().seek.size, Some(5));
+        assert!(config.account("BotThree").is_none());
+    }
+
+    #[test]
+    fn parse_presets() {
+        let config = toml::from_str::<Config>(
+            r#"
+            [preset.blitz6]
+            size = 6
+            time = 180
+            increment = 5
+
+            [preset.casual5]
+            size = 5
+            unrated = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.preset("blitz6").unwrap().time, Some(180));
+        assert_eq!(config.preset("casual5").unwrap().unrated, Some(true));
+        assert!(config.preset("bullet").is_err());
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::game::{Game, ListedGame};
+use super::seek::{Seek, SeekColor};
+
+// Output for people watching the terminal, kept apart from the log, which only shows
+// warnings and errors while this is enabled.  Nothing is printed until it's enabled.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+const BOLD: &str = "1";
+const RED: &str = "31";
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+const CYAN: &str = "36";
+const DIM: &str = "2";
+
+pub fn enable(color: bool) {
+    ENABLED.store(true, Ordering::Relaxed);
+    COLOR.store(color, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if COLOR.load(Ordering::Relaxed) {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_owned()
+    }
+}
+
+fn clock(seconds: u32) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+pub fn game_start(game: &Game) {
+    if !is_enabled() {
+        return;
+    }
+
+    let (white, black) = game.white_and_black();
+    let increment = game
+        .increment
+        .map(|increment| format!("+{increment}"))
+        .unwrap_or_default();
+
+    println!(
+        "{} {white} (white) vs {black} (black), {size}x{size}, {}{increment}, komi {}",
+        paint(BOLD, &format!("Game {}:", game.id)),
+        clock(game.initial_time),
+        f64::from(game.half_komi) / 2.0,
+        size = game.size,
+    );
+    println!("{}", paint(DIM, &game.url()));
+}
+
+// Announces the game's last move, with both clocks.
+pub fn game_move(game: &Game) {
+    let Some(last_move) = game.moves.last().filter(|_| is_enabled()) else {
+        return;
+    };
+
+    let ply = game.moves.len();
+    let mover = game.to_move().opposite();
+    let (name, code) = if mover == game.color {
+        (&game.player, GREEN)
+    } else {
+        (&game.opponent, CYAN)
+    };
+    let (white, black) = game.white_and_black();
+
+    println!(
+        "{:>4}. {:<12} {:<8} {}",
+        ply.div_ceil(2),
+        name,
+        paint(code, &last_move.to_ptn()),
+        paint(
+            DIM,
+            &format!(
+                "{white} {}  {black} {}",
+                clock(game.time.0),
+                clock(game.time.1)
+            )
+        ),
+    );
+}
+
+pub fn game_over(game: &Game) {
+    if !is_enabled() {
+        return;
+    }
+
+    let Some(result) = &game.result else {
+        println!(
+            "{} {}",
+            paint(YELLOW, &format!("Game {} abandoned.", game.id)),
+            paint(DIM, &game.url())
+        );
+        return;
+    };
+
+    let (outcome, code) = match game.winner() {
+        Some(winner) if winner == game.color => ("won", GREEN),
+        Some(_) => ("lost", RED),
+        None => ("drawn", YELLOW),
+    };
+
+    println!(
+        "{} {}",
+        paint(code, &format!("Game {} {outcome}.", game.id)),
+        paint(DIM, &format!("({result}) {}", game.url())),
+    );
+}
+
+pub fn status(line: &str) {
+    if is_enabled() {
+        println!("{}", paint(DIM, line));
+    }
+}
+
+// A table of seeks, with the ones directed at us highlighted.
+pub fn seek_table<'a>(
+    seeks: impl IntoIterator<Item = &'a Seek>,
+    name: &str,
+    ratings: &HashMap<String, u32>,
+) {
+    println!(
+        "{}",
+        paint(
+            BOLD,
+            &format!(
+                "{:>6}  {:<14} {:>6}  {:>4}  {:>9}  {:>4}  {:<6}  {}",
+                "ID", "Player", "Rating", "Size", "Time", "Komi", "Color", "Rated"
+            )
+        )
+    );
+
+    for seek in seeks {
+        let line = format!(
+            "{:>6}  {:<14} {:>6}  {:>4}  {:>9}  {:>4}  {:<6}  {}",
+            seek.id.map(|id| id.to_string()).unwrap_or_default(),
+            seek.player.as_deref().unwrap_or_default(),
+            seek.player
+                .as_ref()
+                .and_then(|player| ratings.get(player))
+                .map(|rating| rating.to_string())
+                .unwrap_or_default(),
+            format!("{0}x{0}", seek.size),
+            format!("{}+{}", clock(seek.time), seek.increment),
+            f64::from(seek.half_komi) / 2.0,
+            match seek.color {
+                SeekColor::White => "white",
+                SeekColor::Black => "black",
+                SeekColor::Random => "random",
+            },
+            if seek.unrated { "no" } else { "yes" },
+        );
+
+        if seek.is_directed_at(name) {
+            println!("{}", paint(YELLOW, &format!("{line}  for you")));
+        } else {
+            println!("{line}");
+        }
+    }
+}
+
+pub fn game_table(games: &[ListedGame]) {
+    println!(
+        "{}",
+        paint(
+            BOLD,
+            &format!(
+                "{:>6}  {:<14} {:<14} {:>4}  {:>9}  {:>4}  {:>5}",
+                "ID", "White", "Black", "Size", "Time", "Komi", "Plies"
+            )
+        )
+    );
+
+    for game in games {
+        println!(
+            "{:>6}  {:<14} {:<14} {:>4}  {:>9}  {:>4}  {:>5}",
+            game.id,
+            game.white,
+            game.black,
+            format!("{0}x{0}", game.size),
+            format!("{}+{}", clock(game.time), game.increment),
+            game.half_komi
+                .map(|half_komi| (f64::from(half_komi) / 2.0).to_string())
+                .unwrap_or_default(),
+            game.plies
+                .map(|plies| plies.to_string())
+                .unwrap_or_default(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_clock() {
+        assert_eq!(clock(600), "10:00");
+        assert_eq!(clock(65), "1:05");
+        assert_eq!(paint(RED, "x"), "x");
+    }
+}
@@ -0,0 +1,89 @@
+use std::process::{self, Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use clap::Args;
+use tracing::{debug, warn};
+
+use super::resources::ResourceSettings;
+
+// Running the engine in a Docker or Podman container, for engines that aren't trusted with the
+// machine.
+#[derive(Args, Clone, Debug, Default)]
+pub struct ContainerSettings {
+    #[arg(long, value_name = "IMAGE")]
+    pub engine_docker: Option<String>,
+    #[arg(long, value_name = "PROGRAM", requires = "engine_docker")]
+    pub container_runtime: Option<String>,
+}
+
+// Containers started by this process, for giving each a unique name.
+static CONTAINERS: AtomicU32 = AtomicU32::new(0);
+
+// An engine's container, which is removed when this is dropped.
+#[derive(Debug)]
+pub struct Container {
+    runtime: String,
+    name: String,
+}
+
+impl ContainerSettings {
+    fn runtime(&self) -> &str {
+        self.container_runtime.as_deref().unwrap_or("docker")
+    }
+
+    // The command that runs the image with the engine's stdio attached.  The engine arguments
+    // are passed on to the image's entrypoint, and the image is pulled if it isn't there yet.
+    pub fn command(
+        &self,
+        image: &str,
+        engine_arguments: &[String],
+        resources: &ResourceSettings,
+    ) -> (Command, Container) {
+        let name = format!(
+            "playtak-tei-{}-{}",
+            process::id(),
+            CONTAINERS.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut command = Command::new(self.runtime());
+        command.args(["run", "--rm", "--interactive", "--init"]);
+        command.args(["--pull", "missing", "--name", &name]);
+
+        if let Some(cpus) = &resources.cpus {
+            command.arg(format!("--cpuset-cpus={cpus}"));
+        }
+        if let Some(megabytes) = resources.memory_limit {
+            command.arg(format!("--memory={megabytes}m"));
+        }
+        if resources.nice.is_some() {
+            warn!("--nice has no effect on an engine in a container.");
+        }
+
+        command.arg(image).args(engine_arguments);
+        debug!(?command, "Running the engine in a container.");
+
+        let container = Container {
+            runtime: self.runtime().to_owned(),
+            name,
+        };
+
+        (command, container)
+    }
+}
+
+impl Drop for Container {
+    fn drop(&mut self) {
+        // Killing the runtime's client doesn't always stop the container, so it's removed
+        // separately.  It's gone already if the engine quit on its own, which makes this quick.
+        let removal = Command::new(&self.runtime)
+            .args(["rm", "--force", &self.name])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+        if let Err(error) = removal {
+            warn!(%error, container = self.name, "Could not remove the engine's container.");
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::channel::{mpsc, oneshot};
+use futures::{future, StreamExt};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::info;
+
+use super::http::{self, Request, Response};
+use super::offers::Offer;
+use super::runtime::{self, TcpListener};
+
+// How long a request waits for the session to get to it before giving up.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+pub enum Command {
+    Seek,
+    Unseek,
+    Resign,
+    // Offers a draw or requests an undo, or takes that back.
+    Offer(Offer),
+    Withdraw(Offer),
+    SetOption(String, i32),
+    Shutdown,
+}
+
+// Commands as the bot's owners write them in chat, like "option Threads 4".
+impl FromStr for Command {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let words = s.split_ascii_whitespace().collect::<Vec<_>>();
+
+        let command = match words[..] {
+            ["seek"] => Self::Seek,
+            ["unseek"] => Self::Unseek,
+            ["resign"] => Self::Resign,
+            ["draw"] => Self::Offer(Offer::Draw),
+            ["undo"] => Self::Offer(Offer::Undo),
+            ["cancel", "draw"] => Self::Withdraw(Offer::Draw),
+            ["cancel", "undo"] => Self::Withdraw(Offer::Undo),
+            ["shutdown"] => Self::Shutdown,
+            ["option", ref name @ .., value] if !name.is_empty() => Self::SetOption(
+                name.join(" "),
+                value
+                    .parse()
+                    .map_err(|_| "option values must be integers")?,
+            ),
+            _ => return Err("unknown command"),
+        };
+
+        Ok(command)
+    }
+}
+
+// Whether the command was carried out, or why not.
+pub type Reply = oneshot::Sender<Result<(), &'static str>>;
+
+// Takes commands from other programs over HTTP, so that a long-running session can be managed
+// without restarting it.  Commands are carried out by the session when it gets to them.
+#[derive(Debug)]
+pub struct Control {
+    commands: mpsc::UnboundedReceiver<(Command, Reply)>,
+    status: Arc<Mutex<Value>>,
+    // Engine options set through the control API or chat, applied before each game.
+    pub options: BTreeMap<String, i32>,
+    // Set once a shutdown is requested, after which no more games are started.
+    pub shutdown: bool,
+}
+
+impl Control {
+    // Without a token, anything that can reach the endpoint can control the session, so it's
+    // only served on a loopback address.
+    pub async fn listen(address: &str, token: Option<String>) -> io::Result<Self> {
+        let listener = runtime::listen(address).await?;
+        if token.is_none() && !listener.local_addr()?.ip().is_loopback() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "an address that isn't loopback needs a control token",
+            ));
+        }
+
+        let (sender, commands) = mpsc::unbounded();
+        let status = Arc::new(Mutex::new(json!({ "state": "connecting" })));
+
+        runtime::spawn(serve(listener, sender, status.clone(), token));
+
+        Ok(Self {
+            commands,
+            status,
+            options: BTreeMap::new(),
+            shutdown: false,
+        })
+    }
+
+    // Control without an endpoint, for sessions that only take commands from chat.
+    pub fn local() -> Self {
+        let (_, commands) = mpsc::unbounded();
+
+        Self {
+            commands,
+            status: Arc::new(Mutex::new(Value::Null)),
+            options: BTreeMap::new(),
+            shutdown: false,
+        }
+    }
+
+    pub fn set_status(&self, status: Value) {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = status;
+    }
+}
+
+// The next command, or never if there's no control endpoint.
+pub async fn next_command(control: &mut Option<Control>) -> (Command, Reply) {
+    match control {
+        Some(control) => match control.commands.next().await {
+            Some(command) => command,
+            None => future::pending().await,
+        },
+        None => future::pending().await,
+    }
+}
+
+#[derive(Deserialize)]
+struct SetOption {
+    name: String,
+    value: i32,
+}
+
+async fn serve(
+    listener: TcpListener,
+    sender: mpsc::UnboundedSender<(Command, Reply)>,
+    status: Arc<Mutex<Value>>,
+    token: Option<String>,
+) {
+    let token = Arc::new(token);
+    http::serve(listener, move |request| {
+        handle(request, sender.clone(), status.clone(), token.clone())
+    })
+    .await
+}
+
+async fn handle(
+    request: Request,
+    sender: mpsc::UnboundedSender<(Command, Reply)>,
+    status: Arc<Mutex<Value>>,
+    token: Arc<Option<String>>,
+) -> Response {
+    if let Some((status, reason)) = rejection(&request, token.as_deref()) {
+        return Response::error(status, reason);
+    }
+
+    let command = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => {
+            let status = status.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            return Response::json(status);
+        }
+        ("POST", "/seek") => Command::Seek,
+        ("DELETE", "/seek") => Command::Unseek,
+        ("POST", "/resign") => Command::Resign,
+        ("POST", "/draw") => Command::Offer(Offer::Draw),
+        ("DELETE", "/draw") => Command::Withdraw(Offer::Draw),
+        ("POST", "/undo") => Command::Offer(Offer::Undo),
+        ("DELETE", "/undo") => Command::Withdraw(Offer::Undo),
+        ("POST", "/option") => match serde_json::from_str::<SetOption>(&request.body) {
+            Ok(option) => Command::SetOption(option.name, option.value),
+            Err(error) => return Response::error(400, &error.to_string()),
+        },
+        ("POST", "/shutdown") => Command::Shutdown,
+        (_, "/status" | "/seek" | "/resign" | "/draw" | "/undo" | "/option" | "/shutdown") => {
+            return Response::error(405, "method not allowed")
+        }
+        _ => return Response::error(404, "not found"),
+    };
+
+    info!(?command, "Received a control command.");
+
+    let (reply, result) = oneshot::channel();
+    if sender.unbounded_send((command, reply)).is_err() {
+        return Response::error(503, "the session has ended");
+    }
+
+    match runtime::timeout(REPLY_TIMEOUT, result).await {
+        Some(Ok(Ok(()))) => Response::json(json!({ "ok": true })),
+        Some(Ok(Err(reason))) => Response::error(409, reason),
+        Some(Err(_)) => Response::error(503, "the session has ended"),
+        None => Response::error(503, "the session is busy"),
+    }
+}
+
+// Why a request isn't accepted, if it isn't.  Browsers say which page a request comes from,
+// and other programs have no reason to, so a web page can't use the endpoint on its visitor's
+// behalf.  Bodies have to be JSON, which a page can't send elsewhere without asking first.
+fn rejection(request: &Request, token: Option<&str>) -> Option<(u16, &'static str)> {
+    if request.header("origin").is_some() {
+        return Some((403, "requests from web pages aren't accepted"));
+    }
+
+    if let Some(token) = token {
+        let given = request
+            .header("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if !given.is_some_and(|given| same_token(given, token)) {
+            return Some((401, "the control token is missing or wrong"));
+        }
+    }
+
+    let content_type = request
+        .header("content-type")
+        .map(|value| value.split(';').next().unwrap_or_default().trim());
+    match content_type {
+        Some(content_type) if !content_type.eq_ignore_ascii_case("application/json") => {
+            Some((415, "request bodies must be JSON"))
+        }
+        None if !request.body.is_empty() => Some((415, "request bodies must be JSON")),
+        _ => None,
+    }
+}
+
+// Compares every byte whatever the first difference, so the time taken says nothing about it.
+fn same_token(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_chat_commands() {
+        assert!(matches!("unseek".parse(), Ok(Command::Unseek)));
+        assert!(matches!(
+            "cancel undo".parse(),
+            Ok(Command::Withdraw(Offer::Undo))
+        ));
+        assert!(matches!(
+            "option Clear Hash 1".parse(),
+            Ok(Command::SetOption(name, 1)) if name == "Clear Hash"
+        ));
+        assert!("option Threads many".parse::<Command>().is_err());
+        assert!("option 4".parse::<Command>().is_err());
+        assert!("hello".parse::<Command>().is_err());
+    }
+
+    #[test]
+    fn reject_requests() {
+        let request = |headers: &[(&str, &str)], body: &str| Request {
+            method: "POST".to_owned(),
+            path: "/option".to_owned(),
+            query: None,
+            headers: headers
+                .iter()
+                .map(|&(name, value)| (name.to_owned(), value.to_owned()))
+                .collect(),
+            body: body.to_owned(),
+        };
+        let json = ("content-type", "application/json; charset=utf-8");
+        let body = r#"{"name": "Threads", "value": 4}"#;
+
+        assert_eq!(rejection(&request(&[json], body), None), None);
+        assert_eq!(rejection(&request(&[], ""), None), None);
+        assert_eq!(
+            rejection(
+                &request(&[json, ("origin", "https://example.com")], body),
+                None
+            ),
+            Some((403, "requests from web pages aren't accepted"))
+        );
+        assert_eq!(
+            rejection(&request(&[("content-type", "text/plain")], body), None),
+            Some((415, "request bodies must be JSON"))
+        );
+        assert_eq!(
+            rejection(&request(&[], body), None),
+            Some((415, "request bodies must be JSON"))
+        );
+
+        let authorized = ("authorization", "Bearer secret");
+        assert_eq!(
+            rejection(&request(&[authorized, json], body), Some("secret")),
+            None
+        );
+        assert_eq!(
+            rejection(&request(&[json], body), Some("secret")),
+            Some((401, "the control token is missing or wrong"))
+        );
+        assert_eq!(
+            rejection(&request(&[authorized], ""), Some("secreT")),
+            Some((401, "the control token is missing or wrong"))
+        );
+    }
+}
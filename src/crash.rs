@@ -0,0 +1,192 @@
+use std::collections::VecDeque;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use super::game::{utc_date_time, Game};
+use super::option::SpinOption;
+use super::trace;
+
+// How many lines exchanged with PlayTak.com and the engine are kept for a report.
+const HISTORY: usize = 100;
+
+// What's known about the session when something goes fatally wrong: the last lines exchanged
+// with either party, the game in progress, and the engine.  It's always kept, since there's no
+// knowing in advance whether it will be needed.
+#[derive(Debug, Default)]
+struct Context {
+    lines: VecDeque<String>,
+    game: Option<Game>,
+    engine: Option<String>,
+}
+
+static CONTEXT: Mutex<Context> = Mutex::new(Context {
+    lines: VecDeque::new(),
+    game: None,
+    engine: None,
+});
+
+fn update(f: impl FnOnce(&mut Context)) {
+    f(&mut CONTEXT.lock().unwrap_or_else(|e| e.into_inner()));
+}
+
+pub fn record(party: &str, marker: char, line: &str) {
+    update(|context| {
+        if context.lines.len() == HISTORY {
+            context.lines.pop_front();
+        }
+        context.lines.push_back(format!(
+            "{party:<7} {marker} {}",
+            trace::redact(marker, line)
+        ));
+    });
+}
+
+pub fn game(game: &Game) {
+    update(|context| context.game = Some(game.clone()));
+}
+
+pub fn engine(name: &str, options: &[SpinOption], buttons: &[String]) {
+    let mut description = name.to_owned();
+    for option in options {
+        write!(
+            description,
+            "\n  option {} (default {}, range {}..={})",
+            option.name,
+            option.default,
+            option.range.start(),
+            option.range.end()
+        )
+        .unwrap();
+    }
+    for button in buttons {
+        write!(description, "\n  button {button}").unwrap();
+    }
+
+    update(|context| context.engine = Some(description));
+}
+
+// Writes a report of the error and everything known about what led up to it to a file in the
+// temporary directory, returning its path.
+pub fn write_report(error: &io::Error) -> io::Result<PathBuf> {
+    let report = report(error, env::args());
+
+    let (date, time) = utc_date_time(SystemTime::now());
+    let path = env::temp_dir().join(format!(
+        "playtak-tei-crash-{date}-{}-{}.txt",
+        time.replace(':', ""),
+        std::process::id()
+    ));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+fn report(error: &io::Error, args: impl IntoIterator<Item = String>) -> String {
+    let (date, time) = utc_date_time(SystemTime::now());
+    let context = CONTEXT.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut report = format!(
+        "playtak-tei {} crash report, {date} {time} UTC\n\nError: {error}\n\nArguments: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        redact_args(args).join(" ")
+    );
+
+    match &context.engine {
+        Some(engine) => write!(report, "\nEngine: {engine}\n").unwrap(),
+        None => report += "\nEngine: none started\n",
+    }
+
+    match &context.game {
+        Some(game) => write!(report, "\nGame:\n{}\n", game.to_ptn().trim_end()).unwrap(),
+        None => report += "\nGame: none started\n",
+    }
+
+    write!(
+        report,
+        "\nThe last {} lines exchanged:\n",
+        context.lines.len()
+    )
+    .unwrap();
+    for line in &context.lines {
+        writeln!(report, "{line}").unwrap();
+    }
+
+    report
+}
+
+// The report is meant to be shared, so passwords and guest tokens are left out of it.
+fn redact_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    const SECRETS: [&str; 5] = ["-p", "--password", "-t", "--token", "--control-token"];
+
+    let mut redacted = Vec::new();
+    let mut secret_next = false;
+    for arg in args {
+        if std::mem::take(&mut secret_next) {
+            redacted.push("********".to_owned());
+        } else if SECRETS.contains(&arg.as_str()) {
+            secret_next = true;
+            redacted.push(arg);
+        } else if let Some((flag, _)) = arg
+            .split_once('=')
+            .filter(|(flag, _)| SECRETS.contains(flag))
+        {
+            redacted.push(format!("{flag}=********"));
+        } else {
+            redacted.push(arg);
+        }
+    }
+    redacted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_secrets() {
+        let args = [
+            "playtak-tei",
+            "seek",
+            "-u",
+            "Alice",
+            "-p",
+            "hunter2",
+            "--token=abc",
+            "--",
+            "engine",
+        ];
+        assert_eq!(
+            redact_args(args.map(str::to_owned)),
+            [
+                "playtak-tei",
+                "seek",
+                "-u",
+                "Alice",
+                "-p",
+                "********",
+                "--token=********",
+                "--",
+                "engine"
+            ]
+        );
+    }
+
+    #[test]
+    fn report_context() {
+        record("playtak", '>', "Login Alice hunter2");
+        record("tei", '<', "bestmove a1");
+
+        let report = report(
+            &io::Error::other("the engine crashed"),
+            ["playtak-tei".to_owned()],
+        );
+        assert!(report.contains("Error: the engine crashed"));
+        assert!(report.contains("playtak > Login Alice ********"));
+        assert!(report.contains("tei     < bestmove a1"));
+        assert!(!report.contains("hunter2"));
+    }
+}
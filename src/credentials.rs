@@ -0,0 +1,35 @@
+use std::io;
+
+#[cfg(feature = "keyring")]
+use super::err;
+
+// Passwords in the OS keyring are stored under this service, by username.
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "playtak-tei";
+
+// Asks for the password on the terminal, without echoing what's typed.
+pub fn prompt_password(username: &str) -> io::Result<String> {
+    rpassword::prompt_password(format!("Password for {username}: "))
+}
+
+#[cfg(feature = "keyring")]
+pub fn load_password(username: &str) -> io::Result<String> {
+    keyring::Entry::new(KEYRING_SERVICE, username)
+        .and_then(|entry| entry.get_password())
+        .map_err(|error| {
+            err!(format!(
+                "could not read the password from the keyring: {error}"
+            ))
+        })
+}
+
+#[cfg(feature = "keyring")]
+pub fn save_password(username: &str, password: &str) -> io::Result<()> {
+    keyring::Entry::new(KEYRING_SERVICE, username)
+        .and_then(|entry| entry.set_password(password))
+        .map_err(|error| {
+            err!(format!(
+                "could not save the password to the keyring: {error}"
+            ))
+        })
+}
@@ -0,0 +1,166 @@
+use std::fs;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::path::Path;
+use std::time::Duration;
+
+use futures::AsyncWriteExt;
+
+use super::engine::Engine;
+use super::lock::AccountLock;
+use super::session::Session;
+use super::{err, runtime, write, DoctorCommand};
+
+const SERVER: &str = "playtak.com:10000";
+// How long the server has to accept a connection.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Runs through everything the tool needs to play, printing what worked and what didn't, for
+// working out why it doesn't.  Later checks are made even if earlier ones fail.
+pub async fn run_doctor(command: &DoctorCommand) -> io::Result<()> {
+    let mut failures = 0;
+    let mut report = |check: &str, result: Result<String, String>| match result {
+        Ok(detail) => println!("[ ok ] {check}: {detail}"),
+        Err(detail) => {
+            println!("[FAIL] {check}: {detail}");
+            failures += 1;
+        }
+    };
+
+    let addresses = runtime::spawn_blocking(|| SERVER.to_socket_addrs().map(Vec::from_iter)).await;
+    report(
+        "DNS",
+        match addresses {
+            Ok(addresses) => Ok(format!(
+                "{SERVER} resolves to {}",
+                addresses
+                    .iter()
+                    .map(|a| a.ip().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            Err(error) => Err(format!("could not resolve {SERVER}: {error}")),
+        },
+    );
+
+    report(
+        "Connection",
+        match runtime::timeout(CONNECT_TIMEOUT, runtime::connect(SERVER)).await {
+            Some(Ok(_)) => Ok(format!("{SERVER} accepts connections")),
+            Some(Err(error)) => Err(format!("could not connect to {SERVER}: {error}")),
+            None => Err(format!(
+                "{SERVER} did not answer within {CONNECT_TIMEOUT:?}"
+            )),
+        },
+    );
+
+    report("Login", check_login(command).await);
+
+    if command.engine_arguments.is_empty()
+        && command.engine_settings.engine.is_none()
+        && command.engine_settings.container.engine_docker.is_none()
+    {
+        println!("[skip] Engine: no engine given");
+    } else {
+        report("Engine", check_engine(command).await);
+    }
+
+    let directories = [
+        ("State directory", &command.state_dir),
+        ("PTN directory", &command.ptn_dir),
+        ("Journal directory", &command.journal_dir),
+        ("Transcript directory", &command.transcript),
+    ];
+    for (check, directory) in directories {
+        if let Some(directory) = directory {
+            report(check, check_directory(directory));
+        }
+    }
+
+    match failures {
+        0 => {
+            println!("\nEverything checked out.");
+            Ok(())
+        }
+        failures => {
+            println!("\n{failures} check(s) failed.");
+            Err(err!())
+        }
+    }
+}
+
+// Logs in and straight back out again, unless that would disconnect another instance.
+async fn check_login(command: &DoctorCommand) -> Result<String, String> {
+    let _lock = match &command.login.username {
+        Some(username) => Some(AccountLock::acquire(username).map_err(|error| {
+            if error.kind() == io::ErrorKind::AddrInUse {
+                format!("another instance is already logged in as {username}")
+            } else {
+                format!("could not lock the account: {error}")
+            }
+        })?),
+        None => None,
+    };
+
+    let session = Session::connect(&command.login)
+        .await
+        .map_err(|error| format!("could not log in: {error}"))?;
+
+    let mut writer = session.writer;
+    write(&mut writer, "quit\n").await.ok();
+    writer.flush().await.ok();
+
+    Ok(format!("logged in as {}", session.name))
+}
+
+async fn check_engine(command: &DoctorCommand) -> Result<String, String> {
+    let engine = Engine::spawn(&command.engine_arguments, &command.engine_settings)
+        .await
+        .map_err(|error| format!("the engine did not complete the handshake: {error}"))?;
+
+    let mut detail = format!("\"{}\" completed the handshake", engine.name);
+    for option in &engine.options {
+        detail += &format!(
+            "\n         option {} (default {}, range {}..={})",
+            option.name,
+            option.default,
+            option.range.start(),
+            option.range.end()
+        );
+    }
+    for button in &engine.buttons {
+        detail += &format!("\n         button {button}");
+    }
+
+    engine.quit().await.ok();
+    Ok(detail)
+}
+
+// A directory passes if a file can be written to it, creating it if need be.
+fn check_directory(directory: &Path) -> Result<String, String> {
+    let probe = directory.join(".playtak-tei-doctor");
+    fs::create_dir_all(directory)
+        .and_then(|()| fs::write(&probe, "playtak-tei"))
+        .and_then(|()| fs::remove_file(&probe))
+        .map(|()| format!("{} is writable", directory.display()))
+        .map_err(|error| format!("could not write to {}: {error}", directory.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_directories() {
+        let directory =
+            std::env::temp_dir().join(format!("playtak-tei-doctor-{}", std::process::id()));
+        assert!(check_directory(&directory.join("nested")).is_ok());
+        assert!(!directory.join("nested/.playtak-tei-doctor").exists());
+
+        let file = directory.join("file");
+        fs::write(&file, "").unwrap();
+        assert!(check_directory(&file).is_err());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+use std::f64::consts::LN_10;
+
+use serde_json::{json, Value};
+
+use super::game::Game;
+
+// The z-score for a 95% confidence interval.
+const Z_95: f64 = 1.959964;
+
+// Wins, losses, and draws from our side of the board.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+// An Elo difference with the margin of its 95% confidence interval, and the likelihood of
+// superiority as a percentage.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub elo: f64,
+    pub margin: f64,
+    pub los: f64,
+}
+
+impl Record {
+    // Counts a finished game.  Abandoned games and games without a winner or a draw don't
+    // count.
+    pub fn add(&mut self, game: &Game) {
+        match (game.winner(), game.result.as_deref()) {
+            (Some(winner), _) if winner == game.color => self.wins += 1,
+            (Some(_), _) => self.losses += 1,
+            (None, Some("1/2-1/2")) => self.draws += 1,
+            _ => (),
+        }
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+
+    // There's no estimate until there are games, or while every game was won or every game
+    // was lost, since the difference could be anything.
+    pub fn estimate(&self) -> Option<Estimate> {
+        let games = f64::from(self.games());
+        let (wins, losses, draws) = (
+            f64::from(self.wins),
+            f64::from(self.losses),
+            f64::from(self.draws),
+        );
+
+        let score = (wins + draws / 2.0) / games;
+        if !(score > 0.0 && score < 1.0) {
+            return None;
+        }
+
+        let deviation = ((wins * (1.0 - score).powi(2)
+            + losses * score.powi(2)
+            + draws * (0.5 - score).powi(2))
+            / games)
+            .sqrt()
+            / games.sqrt();
+
+        let low = elo((score - Z_95 * deviation).max(f64::EPSILON));
+        let high = elo((score + Z_95 * deviation).min(1.0 - f64::EPSILON));
+
+        // Draws say nothing about which side is stronger.
+        let los = match wins + losses {
+            0.0 => 50.0,
+            decisive => 50.0 * (1.0 + erf((wins - losses) / (2.0 * decisive).sqrt())),
+        };
+
+        Some(Estimate {
+            elo: elo(score),
+            margin: (high - low) / 2.0,
+            los,
+        })
+    }
+
+    pub fn to_json(self) -> Value {
+        let estimate = self.estimate();
+
+        json!({
+            "wins": self.wins,
+            "losses": self.losses,
+            "draws": self.draws,
+            "elo": estimate.map(|e| round(e.elo)),
+            "elo_margin": estimate.map(|e| round(e.margin)),
+            "los": estimate.map(|e| round(e.los)),
+        })
+    }
+}
+
+// The Elo difference that gives the expected score.
+fn elo(score: f64) -> f64 {
+    -400.0 * (1.0 / score - 1.0).ln() / LN_10
+}
+
+// The error function, to within 1.5e-7.  (Abramowitz and Stegun, 7.1.26)
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+
+    (1.0 - polynomial * (-x * x).exp()).copysign(x)
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimate_elo() {
+        let record = Record {
+            wins: 60,
+            losses: 30,
+            draws: 10,
+        };
+        let estimate = record.estimate().unwrap();
+        assert!((estimate.elo - 107.5).abs() < 0.1);
+        assert!((estimate.margin - 68.4).abs() < 0.1);
+        assert!((estimate.los - 99.9).abs() < 0.1);
+
+        let even = Record {
+            wins: 5,
+            losses: 5,
+            draws: 2,
+        };
+        let estimate = even.estimate().unwrap();
+        assert!(estimate.elo.abs() < 1e-9);
+        assert!((estimate.los - 50.0).abs() < 1e-6);
+
+        let drawn = Record {
+            draws: 4,
+            ..Default::default()
+        };
+        assert_eq!(drawn.estimate().unwrap().los, 50.0);
+
+        assert_eq!(Record::default().estimate(), None);
+        assert_eq!(
+            Record {
+                wins: 3,
+                ..Default::default()
+            }
+            .estimate(),
+            None
+        );
+
+        let mut record = Record::default();
+        let mut game = "Game Start 7 5 Alice vs Bob white 600 0 21 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+        game.result = Some("0-R".to_owned());
+        record.add(&game);
+        game.result = None;
+        record.add(&game);
+        assert_eq!(
+            record,
+            Record {
+                losses: 1,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,1122 @@
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use tracing::{debug, error, info, warn};
+
+use super::board::Board;
+use super::clock::plan_move_time;
+use super::container::{Container, ContainerSettings};
+use super::crash;
+use super::engines::{EngineDefinition, EnginesFile, Quirks, DEFAULT_ENGINES_FILE};
+use super::game::{Game, GameMove};
+use super::option::{check_option, validate_and_set_option, SpinOption};
+use super::provider::MoveProvider;
+use super::resources::ResourceSettings;
+use super::runtime::{self, spawn_process, Child, ProcessReader, ProcessWriter};
+use super::seek::{capstones_for_size, flatstones_for_size, Seek};
+use super::settle;
+use super::transcript::{Transcribed, Transcript};
+use super::{err, live, read, write};
+
+// How long an engine is given to exit on its own after being told to quit.
+const QUIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+// How long a search is given to end after being told to stop.
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+
+// How long an engine has to answer the isready sent after the handshake, before it's taken
+// not to support it.
+const ISREADY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How long an engine has to answer stop when a move is being forced.
+const FORCE_TIMEOUT: Duration = Duration::from_millis(250);
+
+// Words that mark an engine's info string as a warning, like a missing weights file.
+const WARNING_WORDS: [&str; 8] = [
+    "warn",
+    "error",
+    "fail",
+    "not found",
+    "missing",
+    "fallback",
+    "cannot",
+    "could not",
+];
+
+// Set when the terminal is in use for something else, which anything an engine writes to
+// stderr would get mixed into.
+static DISCARD_STDERR: AtomicBool = AtomicBool::new(false);
+
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn discard_stderr() {
+    DISCARD_STDERR.store(true, Ordering::Relaxed);
+}
+
+// Options that control how the engine is run, independent of the game being played.
+#[derive(Args, Clone, Debug, Default)]
+pub struct EngineSettings {
+    #[arg(long, value_name = "NAME")]
+    pub engine: Option<String>,
+    #[arg(long, value_name = "PATH", requires = "engine")]
+    pub engines_file: Option<PathBuf>,
+    #[arg(long, value_name = "MS")]
+    pub warmup: Option<u64>,
+    #[arg(long, action)]
+    pub clear_hash: bool,
+    #[arg(long, value_name = "MB")]
+    pub hash: Option<u32>,
+    #[arg(long, value_name = "N")]
+    pub threads: Option<u32>,
+    #[arg(long, value_name = "MS", default_value_t = 1000)]
+    pub time_buffer: u64,
+    #[arg(long, action)]
+    pub manage_time: bool,
+    #[arg(long, value_name = "PLIES", value_parser = clap::value_parser!(u32).range(1..))]
+    pub snapshot_interval: Option<u32>,
+    #[arg(long, value_name = "D", value_parser = clap::value_parser!(u32).range(1..))]
+    pub limit_depth: Option<u32>,
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    pub limit_nodes: Option<u64>,
+    #[arg(long, action)]
+    pub ponder: bool,
+    #[command(flatten)]
+    pub resources: ResourceSettings,
+    #[command(flatten)]
+    pub container: ContainerSettings,
+}
+
+impl EngineSettings {
+    // The engine chosen with --engine, if any.
+    fn definition(&self) -> io::Result<Option<EngineDefinition>> {
+        let Some(name) = &self.engine else {
+            return Ok(None);
+        };
+
+        let path = self
+            .engines_file
+            .as_deref()
+            .unwrap_or(Path::new(DEFAULT_ENGINES_FILE));
+
+        EnginesFile::definition(path, name).map(Some)
+    }
+}
+
+// The engine process is killed if this is dropped without quitting, so error paths and panics
+// don't leave it running.
+pub struct Engine {
+    child: Child,
+    writer: Transcribed<ProcessWriter>,
+    reader: Transcribed<ProcessReader>,
+    searching: bool,
+    // Searches that were stopped but whose bestmove hasn't arrived yet.
+    stale_searches: u32,
+    // Whether the engine has answered the isready sent before the current search.  Anything
+    // it sends before that belongs to an earlier search.
+    synced: bool,
+    // A move is forced if the engine is still searching this close to running out of time.
+    time_buffer: Duration,
+    // The time the server takes for each move on top of the engine's, as last estimated.
+    overhead: Duration,
+    // Whether the client decides how long each search takes, instead of the engine.
+    manage_time: bool,
+    // If set, positions are sent as a TPS snapshot taken every this many plies, followed by
+    // only the moves since.
+    snapshot_interval: Option<usize>,
+    // Limits added to every search in a game, like " depth 4", to weaken the engine.
+    search_limits: String,
+    // Whether the engine searches on the opponent's time, and whether the current search is
+    // one of those, whose move is thrown away.
+    ponder: bool,
+    pondering: bool,
+    // The reply the engine expected to the move it chose last, from its bestmove line.
+    ponder_move: Option<GameMove>,
+    // When the current search has to be cut off, if it's being timed.
+    deadline: Option<Instant>,
+    // The position being searched, for finding a move if the engine doesn't.
+    board: Option<Board>,
+    // Whether the engine has already been asked to search again after an illegal move.
+    retried: bool,
+    // The principal variation from the engine's last info line.
+    pv: Option<String>,
+    // What the engine has said about its current search, and the comment for the move it
+    // chose last.
+    search_info: SearchInfo,
+    comment: Option<String>,
+    // Whether the engine resigned instead of choosing a move.
+    resigned: bool,
+    // Whether the engine has been readied for its first game.
+    prepared: bool,
+    // The size, half komi, flatstones, and capstones the engine was last configured with.
+    configured: Option<(u32, i32, u32, u32)>,
+    quirks: Quirks,
+    // Declared after the child so that the container is removed once the child is killed.
+    _container: Option<Container>,
+    // Lets a panic tell the engine to quit.
+    _settled: settle::Registration,
+    pub name: String,
+    pub options: Vec<SpinOption>,
+    pub buttons: Vec<String>,
+}
+
+impl Engine {
+    // Launches the engine and performs the TEI handshake.
+    pub async fn spawn(engine_arguments: &[String], settings: &EngineSettings) -> io::Result<Self> {
+        let definition = settings.definition().map_err(|error| {
+            error!(%error, "Could not find the engine.");
+            error
+        })?;
+
+        // A defined engine's own command comes first, and anything given on the command line
+        // is added to it.
+        let engine_arguments = match &definition {
+            Some(definition) => [definition.command.as_slice(), engine_arguments].concat(),
+            None => engine_arguments.to_vec(),
+        };
+
+        let (command, container) = match &settings.container.engine_docker {
+            Some(image) => {
+                let (command, container) =
+                    settings
+                        .container
+                        .command(image, &engine_arguments, &settings.resources);
+
+                if definition
+                    .as_ref()
+                    .is_some_and(|d| d.directory.is_some() || !d.env.is_empty())
+                {
+                    warn!("The engine's directory and environment don't apply in a container.");
+                }
+
+                (command, Some(container))
+            }
+            None => {
+                let (engine, arguments) = (engine_arguments[0].as_str(), &engine_arguments[1..]);
+
+                let mut command = Command::new(engine);
+                command.args(arguments);
+                settings.resources.apply(&mut command);
+
+                if let Some(definition) = &definition {
+                    if let Some(directory) = &definition.directory {
+                        command.current_dir(directory);
+                    }
+                    command.envs(&definition.env);
+                }
+
+                (command, None)
+            }
+        };
+
+        let stderr = match DISCARD_STDERR.load(Ordering::Relaxed) {
+            true => Stdio::null(),
+            false => Stdio::inherit(),
+        };
+
+        let (child, engine_writer, engine_reader) = spawn_process(command, stderr)?;
+        let settled = settle::engine(&engine_writer);
+        let mut engine_writer = Transcribed::new(engine_writer, None).traced("tei");
+        let mut engine_reader = Transcribed::new(engine_reader, None).traced("tei");
+
+        write(&mut engine_writer, "tei\n").await?;
+
+        let mut engine_name = "TEI engine".to_owned();
+        let mut engine_options = Vec::new();
+        let mut engine_buttons = Vec::new();
+        loop {
+            let line = read(&mut engine_reader).await?;
+
+            if log_info_string(&engine_name, &line) {
+                continue;
+            }
+
+            if line.starts_with("id name") {
+                engine_name = line.strip_prefix("id name ").unwrap().to_owned();
+            } else if line.starts_with("option") && line.contains("type spin") {
+                // An option that can't be made sense of is left out rather than the engine.
+                match line.parse::<SpinOption>() {
+                    Ok(option) => engine_options.push(option),
+                    Err(error) => {
+                        warn!(error, line, "Skipping an option {engine_name} advertised.")
+                    }
+                }
+            } else if let Some(name) = line
+                .strip_prefix("option name ")
+                .and_then(|l| l.strip_suffix(" type button"))
+            {
+                engine_buttons.push(name.to_owned());
+            } else if line == "teiok" {
+                break;
+            }
+        }
+
+        let mut quirks = match definition {
+            Some(definition) => {
+                for option in definition.option_strings()? {
+                    write(&mut engine_writer, option).await?;
+                }
+                definition.quirks
+            }
+            None => Quirks::default(),
+        };
+
+        if quirks.no_half_komi {
+            engine_options.retain(|o| o.name != "HalfKomi");
+        }
+        engine_options.retain(|o| !quirks.ignore_options.contains(&o.name));
+        engine_buttons.retain(|b| !quirks.ignore_options.contains(b));
+        crash::engine(&engine_name, &engine_options, &engine_buttons);
+
+        // Every search starts with isready, so an engine that doesn't answer it would never be
+        // heard from.
+        if !quirks.no_isready {
+            write(&mut engine_writer, "isready\n").await?;
+            let ready = runtime::timeout(ISREADY_TIMEOUT, async {
+                loop {
+                    let line = read(&mut engine_reader).await?;
+                    if line == "readyok" {
+                        return Ok::<_, io::Error>(());
+                    }
+                    log_info_string(&engine_name, &line);
+                }
+            })
+            .await;
+
+            match ready {
+                Some(result) => result?,
+                None => {
+                    warn!("{engine_name} did not answer isready. Searching without it.");
+                    quirks.no_isready = true;
+                }
+            }
+        }
+
+        Ok(Self {
+            child,
+            writer: engine_writer,
+            reader: engine_reader,
+            searching: false,
+            stale_searches: 0,
+            synced: true,
+            time_buffer: Duration::ZERO,
+            overhead: Duration::ZERO,
+            manage_time: false,
+            snapshot_interval: None,
+            search_limits: String::new(),
+            ponder: false,
+            pondering: false,
+            ponder_move: None,
+            deadline: None,
+            board: None,
+            retried: false,
+            pv: None,
+            search_info: SearchInfo::default(),
+            comment: None,
+            resigned: false,
+            prepared: false,
+            configured: None,
+            quirks,
+            _container: container,
+            _settled: settled,
+            name: engine_name,
+            options: engine_options,
+            buttons: engine_buttons,
+        })
+    }
+
+    // Sets the engine options given on the command line, which the engine has to support.
+    pub async fn apply_settings(&mut self, settings: &EngineSettings) -> io::Result<()> {
+        self.time_buffer = Duration::from_millis(settings.time_buffer);
+        self.manage_time = settings.manage_time;
+        self.snapshot_interval = settings.snapshot_interval.map(|i| i as usize);
+
+        let limits = [
+            ("depth", settings.limit_depth.map(u64::from)),
+            ("nodes", settings.limit_nodes),
+        ];
+        self.search_limits = limits
+            .iter()
+            .filter_map(|(name, limit)| limit.map(|limit| format!(" {name} {limit}")))
+            .collect();
+        // A search with no end can't be used if it can't be stopped.
+        self.ponder = settings.ponder && !self.quirks.no_stop;
+        if settings.ponder && !self.ponder {
+            warn!("{} can't be stopped, so it won't ponder.", self.name);
+        }
+
+        if !self.search_limits.is_empty() {
+            info!(
+                limits = self.search_limits.trim_start(),
+                "Limiting {}'s searches.", self.name
+            );
+        }
+
+        for (name, value) in [("Hash", settings.hash), ("Threads", settings.threads)] {
+            let Some(value) = value else {
+                continue;
+            };
+
+            if !self.options.iter().any(|o| o.name == name) {
+                error!("The engine doesn't support the \"{name}\" option.");
+                return Err(err!());
+            }
+
+            validate_and_set_option(
+                &mut self.writer,
+                &self.options,
+                name,
+                value as i32,
+                value as i32,
+                true,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    // Sets one of the engine's options while it isn't searching.
+    pub async fn set_option(&mut self, name: &str, value: i32) -> io::Result<()> {
+        let Some(option) = self.options.iter().find(|o| o.name == name) else {
+            return Err(err!(format!("{} has no \"{name}\" option", self.name)));
+        };
+
+        if !option.valid_value(value) {
+            return Err(err!(format!(
+                "{value} is outside the range {}..={}",
+                option.range.start(),
+                option.range.end()
+            )));
+        }
+
+        write(&mut self.writer, option.to_tei_string(value)).await
+    }
+
+    // Whether the engine has what it takes to play a seek's games, checked before the seek is
+    // posted or accepted so that no opponent is kept waiting on a game that can't start.
+    pub fn check_seek(&self, seek: &Seek) -> Result<(), String> {
+        check_option(&self.options, "HalfKomi", seek.half_komi, 0)?;
+        check_option(
+            &self.options,
+            "Flatstones",
+            seek.flatstones() as i32,
+            flatstones_for_size(seek.size) as i32,
+        )?;
+        check_option(
+            &self.options,
+            "Capstones",
+            seek.capstones() as i32,
+            capstones_for_size(seek.size) as i32,
+        )
+    }
+
+    pub fn is_prepared(&self) -> bool {
+        self.prepared
+    }
+
+    // Validates the game options with the available engine options and sets them.  Nothing is
+    // sent if the engine is already configured for the same options.
+    pub async fn configure(&mut self, game: &Game) -> io::Result<()> {
+        let configuration = (game.size, game.half_komi, game.flatstones, game.capstones);
+        if self.configured == Some(configuration) {
+            return Ok(());
+        }
+
+        // An engine that was configured for an earlier game may have options away from their
+        // defaults, so every option has to be set explicitly.
+        let force = self.configured.is_some();
+        self.configured = None;
+
+        validate_and_set_option(
+            &mut self.writer,
+            &self.options,
+            "HalfKomi",
+            game.half_komi,
+            0,
+            force,
+        )
+        .await?;
+        validate_and_set_option(
+            &mut self.writer,
+            &self.options,
+            "Flatstones",
+            game.flatstones as i32,
+            flatstones_for_size(game.size) as i32,
+            force,
+        )
+        .await?;
+        validate_and_set_option(
+            &mut self.writer,
+            &self.options,
+            "Capstones",
+            game.capstones as i32,
+            capstones_for_size(game.size) as i32,
+            force,
+        )
+        .await?;
+
+        self.configured = Some(configuration);
+        Ok(())
+    }
+
+    // Readies an engine that has already played a game for the next one.
+    pub async fn reset(&mut self, settings: &EngineSettings, game: &Game) -> io::Result<()> {
+        if settings.clear_hash {
+            if self.buttons.iter().any(|b| b == "Clear Hash") {
+                write(&mut self.writer, "setoption name Clear Hash\n").await?;
+            } else {
+                warn!("{} has no Clear Hash option.", self.name);
+            }
+        }
+
+        self.configure(game).await
+    }
+
+    pub async fn initialize(
+        engine_arguments: &[String],
+        settings: &EngineSettings,
+        game: &Game,
+    ) -> io::Result<Self> {
+        let mut engine = Self::spawn(engine_arguments, settings).await?;
+        engine.prepare(settings, game).await?;
+        Ok(engine)
+    }
+
+    // Readies a newly spawned engine for its first game.
+    pub async fn prepare(&mut self, settings: &EngineSettings, game: &Game) -> io::Result<()> {
+        self.apply_settings(settings).await?;
+        self.configure(game).await?;
+
+        info!("{} initialized.", self.name);
+        self.prepared = true;
+
+        Ok(())
+    }
+
+    // Copies everything exchanged with the engine to the transcript, until it's replaced.
+    pub fn transcribe(&mut self, transcript: Option<Transcript>) {
+        self.writer.set_transcript(transcript.clone());
+        self.reader.set_transcript(transcript);
+    }
+
+    // Runs a throwaway search on an empty board, if one was asked for, so that the engine has
+    // its caches and weights loaded before the clock starts on its first move.  It has to come
+    // before a game is started, or it takes its time from our clock.
+    pub async fn warm_up(&mut self, settings: &EngineSettings, size: u32) -> io::Result<()> {
+        let Some(movetime) = settings.warmup else {
+            return Ok(());
+        };
+        info!(movetime, "Warming up {}.", self.name);
+
+        self.new_game(size).await?;
+        write(&mut self.writer, "position startpos\n").await?;
+        write(&mut self.writer, format!("go movetime {movetime}\n")).await?;
+
+        self.searching = true;
+        while self.searching {
+            self.next_move().await?;
+        }
+
+        Ok(())
+    }
+
+    // Searches the game's position within a limit like "movetime 5000", passing along the
+    // engine's output as it arrives, and returns the best move.
+    pub async fn analyze(
+        &mut self,
+        game: &Game,
+        limit: &str,
+        mut output: impl FnMut(&str),
+    ) -> io::Result<GameMove> {
+        self.new_game(game.size).await?;
+        write(&mut self.writer, game.position_string()).await?;
+        write(&mut self.writer, format!("go {limit}\n")).await?;
+
+        self.searching = true;
+        self.search_info = SearchInfo::started();
+        loop {
+            let line = read(&mut self.reader).await?;
+            output(&line);
+
+            if log_info_string(&self.name, &line) {
+                continue;
+            }
+
+            if let Some(info) = line.strip_prefix("info ") {
+                self.search_info
+                    .update(&info.split_ascii_whitespace().collect::<Vec<_>>());
+            } else if let Some(best_move) = line.strip_prefix("bestmove ") {
+                self.searching = false;
+                let best_move = best_move
+                    .split_ascii_whitespace()
+                    .next()
+                    .unwrap_or_default();
+                if self.is_resignation(best_move) {
+                    return Err(err!("the engine resigned"));
+                }
+                self.comment = self.search_info.annotation();
+                return GameMove::from_ptn(best_move);
+            }
+        }
+    }
+
+    async fn new_game(&mut self, size: u32) -> io::Result<()> {
+        if self.quirks.isready_before_newgame {
+            write(&mut self.writer, "isready\n").await?;
+
+            // Whatever comes before readyok is left over from earlier searches.
+            loop {
+                let line = read(&mut self.reader).await?;
+                if line == "readyok" {
+                    break;
+                } else if line.starts_with("bestmove") {
+                    self.stale_searches = self.stale_searches.saturating_sub(1);
+                } else {
+                    log_info_string(&self.name, &line);
+                }
+            }
+        }
+
+        write(&mut self.writer, format!("teinewgame {size}\n")).await
+    }
+
+    async fn search(&mut self, game: &Game) -> io::Result<()> {
+        if self.pondering {
+            self.stop().await?;
+        }
+        self.send_position(game).await?;
+
+        let margin = self.time_buffer + self.overhead;
+        let go = if self.manage_time {
+            let movetime = plan_move_time(game, margin.as_millis() as u64);
+            debug!(movetime, "Planned the search time.");
+            format!("go movetime {movetime}")
+        } else {
+            let overhead = self.overhead.as_millis() as u64;
+            game.search_string(overhead).trim_end().to_owned()
+        };
+        // The limits go alongside the clock, so the engine stops at whichever comes first.
+        write(&mut self.writer, format!("{go}{}\n", self.search_limits)).await?;
+        self.searching = true;
+        self.pondering = false;
+        self.ponder_move = None;
+        self.pv = None;
+        self.search_info = SearchInfo::started();
+        self.comment = None;
+        self.board = game.board().ok();
+
+        let time = Duration::from_secs(game.our_time().into());
+        self.deadline = Some(Instant::now() + time.saturating_sub(margin));
+
+        Ok(())
+    }
+
+    async fn send_position(&mut self, game: &Game) -> io::Result<()> {
+        if !self.quirks.no_isready {
+            write(&mut self.writer, "isready\n").await?;
+            self.synced = false;
+        }
+
+        // If the game can't be followed, the engine is left to make sense of the whole history.
+        let position = match self.snapshot_interval {
+            Some(interval) => game
+                .snapshot_position_string(interval)
+                .unwrap_or_else(|_| game.position_string()),
+            None => game.position_string(),
+        };
+        write(&mut self.writer, position).await
+    }
+
+    // Tells the engine to stop searching when its time is nearly up, and plays the first move
+    // of its principal variation if it doesn't answer right away.
+    async fn force_move(&mut self) -> io::Result<Option<GameMove>> {
+        warn!("{} is nearly out of time. Forcing a move.", self.name);
+        self.deadline = None;
+        if !self.quirks.no_stop {
+            write(&mut self.writer, "stop\n").await?;
+        }
+
+        let answer = runtime::timeout(FORCE_TIMEOUT, async {
+            loop {
+                let line = read(&mut self.reader).await?;
+                if let Some(game_move) = self.handle_line(&line)? {
+                    return Ok::<_, io::Error>(game_move);
+                }
+            }
+        })
+        .await;
+
+        if let Some(answer) = answer {
+            return answer.map(Some);
+        }
+
+        // Its bestmove will be thrown away whenever it does turn up.
+        self.searching = false;
+        self.stale_searches += 1;
+
+        let pv_move = self
+            .pv
+            .as_deref()
+            .and_then(|pv| pv.split_ascii_whitespace().next());
+
+        match pv_move {
+            Some(pv_move) => {
+                warn!(
+                    pv_move,
+                    "{} did not stop in time. Playing its best move so far.", self.name
+                );
+                GameMove::from_ptn(pv_move).map(Some)
+            }
+            None => {
+                let fallback = self.board.as_ref().and_then(Board::fallback_move);
+                warn!(
+                    fallback = fallback.as_ref().map(GameMove::to_ptn),
+                    "{} did not stop in time and has no move. Playing a fallback move.", self.name
+                );
+                Ok(fallback)
+            }
+        }
+    }
+
+    fn is_resignation(&self, best_move: &str) -> bool {
+        best_move == "resign" || self.quirks.resign_move.as_deref() == Some(best_move)
+    }
+
+    fn handle_line(&mut self, line: &str) -> io::Result<Option<GameMove>> {
+        let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
+
+        match parts.as_slice() {
+            ["readyok"] => {
+                self.synced = true;
+                Ok(None)
+            }
+            ["bestmove", ..] if self.stale_searches > 0 => {
+                self.stale_searches -= 1;
+                debug!("Discarding the result of a stopped search.");
+                Ok(None)
+            }
+            ["bestmove", ..] if !self.searching => {
+                warn!("{} sent a move without being asked for one.", self.name);
+                Ok(None)
+            }
+            ["bestmove", ..] if !self.synced => {
+                debug!("Discarding a move from before the current search.");
+                Ok(None)
+            }
+            ["bestmove", ..] if self.pondering => {
+                self.searching = false;
+                self.pondering = false;
+                debug!("Discarding the result of a search on the opponent's time.");
+                Ok(None)
+            }
+            ["bestmove", best_move, ..] if self.is_resignation(best_move) => {
+                self.searching = false;
+                self.deadline = None;
+                self.resigned = true;
+                Ok(None)
+            }
+            ["bestmove", best_move, rest @ ..] => {
+                self.searching = false;
+                self.deadline = None;
+                self.comment = self.search_info.annotation();
+                // A reply that can't be read only costs the prediction, not the move.
+                self.ponder_move = match rest {
+                    ["ponder", reply, ..] => GameMove::from_ptn(reply).ok(),
+                    _ => None,
+                };
+                Ok(Some(GameMove::from_ptn(best_move)?))
+            }
+            ["bestmove"] => Err(err!("the engine sent bestmove without a move")),
+            ["info", "string", ..] => {
+                log_info_string(&self.name, line);
+                Ok(None)
+            }
+            ["info", info @ ..] => {
+                live::engine_info(info);
+                self.search_info.update(info);
+                if let Some(i) = info.iter().position(|&p| p == "pv") {
+                    self.pv = Some(info[i + 1..].join(" "));
+                }
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn is_running(&mut self) -> bool {
+        !self.child.has_exited()
+    }
+
+    // Asks the engine to exit, and kills it if it doesn't in time.
+    pub async fn quit(mut self) -> io::Result<()> {
+        write(&mut self.writer, "quit\n").await.ok();
+
+        if runtime::timeout(QUIT_TIMEOUT, self.child.wait())
+            .await
+            .is_none()
+        {
+            warn!("{} did not quit. Killing it.", self.name);
+            self.child.kill()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl MoveProvider for Engine {
+    async fn start(&mut self, game: &Game) -> io::Result<()> {
+        self.new_game(game.size).await
+    }
+
+    async fn request_move(&mut self, game: &Game) -> io::Result<()> {
+        self.retried = false;
+        self.search(game).await
+    }
+
+    async fn next_move(&mut self) -> io::Result<Option<GameMove>> {
+        let line = match self.deadline.filter(|_| self.searching) {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                match runtime::timeout(remaining, read(&mut self.reader)).await {
+                    Some(line) => line?,
+                    None => return self.force_move().await,
+                }
+            }
+            None => read(&mut self.reader).await?,
+        };
+
+        self.handle_line(&line)
+    }
+
+    // Searches once more, in case the illegal move came from a bug that doesn't repeat.
+    async fn reject_move(
+        &mut self,
+        game: &Game,
+        game_move: &GameMove,
+        error: &io::Error,
+    ) -> io::Result<bool> {
+        error!(
+            %error,
+            game_move = game_move.to_ptn(),
+            pv = self.pv.as_deref().unwrap_or_default(),
+            "{} chose an illegal move.",
+            self.name
+        );
+
+        if self.retried {
+            return Ok(false);
+        }
+
+        self.retried = true;
+        self.search(game).await?;
+        Ok(true)
+    }
+
+    // Stops a search that's still running and throws away its move, so that it can't be
+    // mistaken for the answer to a later position.
+    async fn stop(&mut self) -> io::Result<()> {
+        if !self.searching {
+            return Ok(());
+        }
+
+        // The search's move is thrown away whenever it turns up.
+        if self.quirks.no_stop {
+            info!("Leaving {}'s search to finish on its own.", self.name);
+            self.searching = false;
+            self.stale_searches += 1;
+            return Ok(());
+        }
+
+        if self.pondering {
+            debug!("Stopping {}'s search on the opponent's time.", self.name);
+        } else {
+            info!("Stopping {}'s search.", self.name);
+        }
+        write(&mut self.writer, "stop\n").await?;
+
+        let stopped = runtime::timeout(STOP_TIMEOUT, async {
+            while self.searching {
+                self.next_move().await?;
+            }
+            Ok::<_, io::Error>(())
+        })
+        .await;
+
+        match stopped {
+            Some(result) => result,
+            None => {
+                // If the move turns up later, it's thrown away then.
+                warn!("{} did not stop searching.", self.name);
+                self.searching = false;
+                self.stale_searches += 1;
+                Ok(())
+            }
+        }
+    }
+
+    // Searches with no limit until the opponent moves, so that the engine's tables are warm by
+    // the time it's asked for a move.  If it said what reply it expects, it searches the
+    // position after that reply, and otherwise the position as it is.
+    async fn ponder(&mut self, game: &Game) -> io::Result<()> {
+        if !self.ponder {
+            return Ok(());
+        }
+        if self.pondering {
+            self.stop().await?;
+        }
+
+        match &game.ponder {
+            Some(reply) => {
+                debug!(reply = reply.to_ptn(), "Pondering the expected reply.");
+                let mut predicted = game.clone();
+                predicted.moves.push(reply.clone());
+                self.send_position(&predicted).await?;
+            }
+            None => self.send_position(game).await?,
+        }
+        write(&mut self.writer, "go infinite\n").await?;
+        self.searching = true;
+        self.pondering = true;
+        self.deadline = None;
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> io::Result<()> {
+        self.stop().await
+    }
+
+    async fn set_option(&mut self, name: &str, value: i32) -> io::Result<()> {
+        if self.pondering {
+            self.stop().await?;
+        }
+        Engine::set_option(self, name, value).await
+    }
+
+    fn take_comment(&mut self) -> Option<String> {
+        self.comment.take()
+    }
+
+    fn take_ponder_move(&mut self) -> Option<GameMove> {
+        self.ponder_move.take()
+    }
+
+    fn take_resignation(&mut self) -> bool {
+        mem::take(&mut self.resigned)
+    }
+
+    fn set_overhead(&mut self, overhead: u64) {
+        self.overhead = Duration::from_millis(overhead);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Score {
+    Centipawns(i32),
+    Mate(i32),
+}
+
+// The deepest depth and latest score an engine has reported for a search, and when it began.
+#[derive(Debug, Default)]
+struct SearchInfo {
+    started: Option<Instant>,
+    depth: Option<u32>,
+    score: Option<Score>,
+}
+
+impl SearchInfo {
+    fn started() -> Self {
+        Self {
+            started: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    fn update(&mut self, info: &[&str]) {
+        // Anything can follow "string", and nothing but moves can follow "pv".
+        let mut fields = info
+            .iter()
+            .copied()
+            .take_while(|&f| f != "string" && f != "pv");
+
+        while let Some(field) = fields.next() {
+            match field {
+                "depth" => self.depth = fields.next().and_then(|d| d.parse().ok()),
+                "score" => {
+                    self.score = match (fields.next(), fields.next().and_then(|v| v.parse().ok())) {
+                        (Some("cp"), Some(value)) => Some(Score::Centipawns(value)),
+                        (Some("mate"), Some(value)) => Some(Score::Mate(value)),
+                        _ => self.score,
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    fn annotation(&self) -> Option<String> {
+        Some(annotation(self.score?, self.depth, self.started?.elapsed()))
+    }
+}
+
+// Logs what an engine says in an info string, as a warning if it sounds like one.  Returns
+// false if the line isn't an info string.
+fn log_info_string(name: &str, line: &str) -> bool {
+    let Some(message) = line.strip_prefix("info string ") else {
+        return false;
+    };
+
+    if is_warning(message) {
+        warn!(message, "{name} reported a problem.");
+    } else {
+        info!(message, "{name} says:");
+    }
+    true
+}
+
+fn is_warning(message: &str) -> bool {
+    let message = message.to_lowercase();
+    WARNING_WORDS.iter().any(|word| message.contains(word))
+}
+
+// A move's comment in the form ptn.ninja shows, like "+0.85/17 3.2s": the score in flats from
+// the engine's side, the depth, and the time taken.
+fn annotation(score: Score, depth: Option<u32>, time: Duration) -> String {
+    let score = match score {
+        Score::Centipawns(centipawns) => format!("{:+.2}", f64::from(centipawns) / 100.0),
+        Score::Mate(moves) if moves < 0 => format!("-M{}", -moves),
+        Score::Mate(moves) => format!("+M{moves}"),
+    };
+    let depth = depth.map(|d| format!("/{d}")).unwrap_or_default();
+
+    format!("{score}{depth} {:.1}s", time.as_secs_f64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotate_moves() {
+        let mut info = SearchInfo::default();
+        info.update(
+            &"depth 12 score cp 85 nodes 1000 pv c3 d3"
+                .split(' ')
+                .collect::<Vec<_>>(),
+        );
+        info.update(&"depth 13 pv c3".split(' ').collect::<Vec<_>>());
+        info.update(&"string depth 99".split(' ').collect::<Vec<_>>());
+        assert_eq!(
+            (info.depth, info.score),
+            (Some(13), Some(Score::Centipawns(85)))
+        );
+        assert_eq!(
+            annotation(info.score.unwrap(), info.depth, Duration::from_millis(3240)),
+            "+0.85/13 3.2s"
+        );
+
+        assert_eq!(
+            annotation(Score::Mate(-3), None, Duration::ZERO),
+            "-M3 0.0s"
+        );
+        assert_eq!(
+            annotation(Score::Centipawns(-4), Some(1), Duration::ZERO),
+            "-0.04/1 0.0s"
+        );
+    }
+
+    #[test]
+    fn classify_info_strings() {
+        assert!(is_warning("Weights file not found, using defaults"));
+        assert!(is_warning("WARNING: hash too large"));
+        assert!(!is_warning("Loaded network nn-6x6.bin"));
+        assert!(log_info_string("Engine", "info string hello"));
+        assert!(!log_info_string("Engine", "info depth 3"));
+    }
+
+    // An engine that writes down everything it's sent, advertising the options given.
+    #[cfg(unix)]
+    fn fake_engine(name: &str, options: &[&str]) -> (Vec<String>, PathBuf) {
+        let log =
+            std::env::temp_dir().join(format!("playtak-tei-{name}-{}.log", std::process::id()));
+        let script = format!(
+            r#"while read -r line; do
+                echo "$line" >> "$1"
+                case "$line" in
+                    tei) printf 'id name Fake\n{}teiok\n' ;;
+                    isready) echo readyok ;;
+                    go*) echo "bestmove a1" ;;
+                    quit) exit ;;
+                esac
+            done"#,
+            options
+                .iter()
+                .map(|o| format!("{o}\\n"))
+                .collect::<String>()
+        );
+        let arguments = ["sh", "-c", &script, "sh", &log.to_string_lossy()];
+
+        (arguments.map(str::to_owned).to_vec(), log)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn warm_up_before_the_game() {
+        let (arguments, log) = fake_engine("warmup", &[]);
+        let settings = EngineSettings {
+            warmup: Some(10),
+            ..Default::default()
+        };
+        let game = "Game Start 1 5 Bot vs Opp white 600 0 21 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+
+        runtime::block_on(async {
+            let mut engine = Engine::spawn(&arguments, &settings).await.unwrap();
+            engine.prepare(&settings, &game).await.unwrap();
+            engine.quit().await.unwrap();
+        });
+        let sent = std::fs::read_to_string(&log).unwrap();
+        assert!(!sent.contains("go"));
+
+        runtime::block_on(async {
+            let mut engine = Engine::spawn(&arguments, &settings).await.unwrap();
+            engine.warm_up(&settings, 5).await.unwrap();
+            engine.quit().await.unwrap();
+        });
+        let sent = std::fs::read_to_string(&log).unwrap();
+        assert!(sent.contains("teinewgame 5\nposition startpos\ngo movetime 10\n"));
+        std::fs::remove_file(log).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn skip_bad_options() {
+        let (arguments, log) = fake_engine(
+            "options",
+            &[
+                "option name Threads type spin default 1 min 1 max 8",
+                "option name Hash type spin default lots min 1 max 1024",
+            ],
+        );
+
+        let names = runtime::block_on(async {
+            let engine = Engine::spawn(&arguments, &EngineSettings::default())
+                .await
+                .unwrap();
+            let names = engine
+                .options
+                .iter()
+                .map(|option| option.name.clone())
+                .collect::<Vec<_>>();
+            engine.quit().await.unwrap();
+            names
+        });
+        assert_eq!(names, ["Threads"]);
+        std::fs::remove_file(log).ok();
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::err;
+
+// Where engine definitions are looked for when no other file is given.
+pub const DEFAULT_ENGINES_FILE: &str = "engines.toml";
+
+// Engines described once, so that they can be chosen with --engine NAME.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnginesFile {
+    #[serde(rename = "engine")]
+    pub engines: BTreeMap<String, EngineDefinition>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EngineDefinition {
+    pub command: Vec<String>,
+    #[serde(default)]
+    pub directory: Option<PathBuf>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    // Set right after the handshake, so options given on the command line take precedence.
+    #[serde(default)]
+    pub options: BTreeMap<String, toml::Value>,
+    #[serde(default)]
+    pub quirks: Quirks,
+}
+
+// Ways in which an engine strays from the protocol, which the client works around.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Quirks {
+    // The engine has to answer isready before it accepts teinewgame.
+    pub isready_before_newgame: bool,
+    // The engine lists HalfKomi but doesn't handle it, so it's treated as not having it.
+    pub no_half_komi: bool,
+    // What the engine sends as its best move when it resigns, if not "resign".
+    pub resign_move: Option<String>,
+    // The engine ignores stop, so searches are left to finish and their moves thrown away.
+    pub no_stop: bool,
+    // The engine never answers isready.  This is also detected during the handshake.
+    pub no_isready: bool,
+    // Options the engine lists but doesn't handle, which are treated as missing.
+    pub ignore_options: Vec<String>,
+}
+
+impl EnginesFile {
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file = toml::from_str::<Self>(&fs::read_to_string(path)?)
+            .map_err(|error| err!(error.message().to_owned()))?;
+
+        for (name, definition) in &file.engines {
+            if definition.command.is_empty() {
+                return Err(err!(format!("no command given for engine {name}")));
+            }
+            definition.option_strings()?;
+        }
+
+        Ok(file)
+    }
+
+    pub fn definition(path: &Path, name: &str) -> io::Result<EngineDefinition> {
+        let mut file = Self::load(path)
+            .map_err(|error| err!(format!("could not load {}: {error}", path.display())))?;
+
+        file.engines.remove(name).ok_or_else(|| {
+            err!(format!(
+                "engine {name} is not defined in {}",
+                path.display()
+            ))
+        })
+    }
+}
+
+impl EngineDefinition {
+    // The setoption commands for the definition's options.
+    pub fn option_strings(&self) -> io::Result<Vec<String>> {
+        self.options
+            .iter()
+            .map(|(name, value)| {
+                let value = match value {
+                    toml::Value::String(value) => value.clone(),
+                    toml::Value::Integer(value) => value.to_string(),
+                    toml::Value::Float(value) => value.to_string(),
+                    toml::Value::Boolean(value) => value.to_string(),
+                    _ => return Err(err!(format!("invalid value for option {name}"))),
+                };
+
+                Ok(format!("setoption name {name} value {value}\n"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_engines_file() {
+        let file = toml::from_str::<EnginesFile>(
+            r#"
+            [engine.tiltak]
+            command = ["tiltak", "tei"]
+            directory = "/opt/tiltak"
+            env = { RUST_LOG = "warn" }
+            options = { Hash = 256, "Book File" = "book.txt", Ponder = false }
+
+            [engine.old]
+            command = ["old-engine"]
+            quirks = { isready_before_newgame = true, no_half_komi = true, resign_move = "0000" }
+
+            [engine.older]
+            command = ["older-engine"]
+            quirks = { no_stop = true, no_isready = true, ignore_options = ["Threads"] }
+            "#,
+        )
+        .unwrap();
+
+        let tiltak = &file.engines["tiltak"];
+        assert_eq!(tiltak.command, ["tiltak", "tei"]);
+        assert_eq!(tiltak.env["RUST_LOG"], "warn");
+        assert!(!tiltak.quirks.isready_before_newgame);
+        assert_eq!(
+            tiltak.option_strings().unwrap(),
+            [
+                "setoption name Book File value book.txt\n",
+                "setoption name Hash value 256\n",
+                "setoption name Ponder value false\n",
+            ]
+        );
+
+        let old = &file.engines["old"];
+        assert!(old.quirks.isready_before_newgame && old.quirks.no_half_komi);
+        assert_eq!(old.quirks.resign_move.as_deref(), Some("0000"));
+
+        let older = &file.engines["older"];
+        assert!(older.quirks.no_stop && older.quirks.no_isready && !old.quirks.no_stop);
+        assert_eq!(older.quirks.ignore_options, ["Threads"]);
+
+        assert!(toml::from_str::<EnginesFile>("[engine.x]\ncommand = []\nquirk = {}").is_err());
+    }
+}
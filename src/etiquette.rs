@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use tracing::info;
+
+use super::seek::Seek;
+
+// Games being played by every session in the process, for the limit on concurrent games.
+static GAMES_IN_PROGRESS: AtomicUsize = AtomicUsize::new(0);
+
+// Limits that keep a bot within what's considered polite on PlayTak.com.  None of them apply
+// without --bot-mode.
+#[derive(Args, Clone, Debug, Default)]
+pub struct BotMode {
+    #[arg(long, action)]
+    pub bot_mode: bool,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 30,
+        requires = "bot_mode"
+    )]
+    pub min_seek_interval: u64,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 300,
+        requires = "bot_mode"
+    )]
+    pub challenge_timeout: u64,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 3600,
+        requires = "bot_mode"
+    )]
+    pub decline_cooldown: u64,
+    #[arg(long, value_name = "N", default_value_t = 1, requires = "bot_mode")]
+    pub max_concurrent_games: usize,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 60,
+        requires = "bot_mode"
+    )]
+    pub min_chat_interval: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Etiquette {
+    settings: BotMode,
+    last_seek: Option<Instant>,
+    // The player a directed seek is waiting on, and since when.
+    challenge: Option<(String, Instant)>,
+    // Players who let a directed seek go unanswered, and when.
+    declined: HashMap<String, Instant>,
+    last_chat: Option<Instant>,
+}
+
+impl Etiquette {
+    pub fn new(settings: BotMode) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    // How long to wait before the seek can be posted again.
+    pub fn seek_delay(&self) -> Option<Duration> {
+        let interval = Duration::from_secs(self.settings.min_seek_interval);
+        let elapsed = self.last_seek.filter(|_| self.settings.bot_mode)?.elapsed();
+
+        (elapsed < interval).then(|| interval - elapsed)
+    }
+
+    pub fn seek_posted(&mut self, seek: &Seek) {
+        self.last_seek = Some(Instant::now());
+
+        // A reposted challenge keeps waiting from when it was first made.
+        self.challenge = match (self.challenge.take(), &seek.opponent) {
+            (Some((player, since)), Some(opponent)) if player == opponent.to_lowercase() => {
+                Some((player, since))
+            }
+            (_, Some(opponent)) => Some((opponent.to_lowercase(), Instant::now())),
+            (_, None) => None,
+        };
+    }
+
+    pub fn seek_withdrawn(&mut self) {
+        self.challenge = None;
+    }
+
+    // Checks whether a directed seek has gone unanswered for too long.  If it has, the player
+    // isn't challenged again until the cooldown is over.
+    pub fn challenge_expired(&mut self) -> bool {
+        let timeout = Duration::from_secs(self.settings.challenge_timeout);
+
+        match self.challenge.take() {
+            Some((player, since)) if self.settings.bot_mode && since.elapsed() >= timeout => {
+                info!(
+                    player,
+                    cooldown = self.settings.decline_cooldown,
+                    "The challenge went unanswered. Holding off on challenging them again."
+                );
+                self.declined.insert(player, Instant::now());
+                true
+            }
+            challenge => {
+                self.challenge = challenge;
+                false
+            }
+        }
+    }
+
+    // Whether the seek may be posted at all right now.
+    pub fn allows_seek(&self, seek: &Seek) -> Result<(), &'static str> {
+        if !self.settings.bot_mode {
+            return Ok(());
+        }
+
+        self.allows_game()?;
+
+        let cooldown = Duration::from_secs(self.settings.decline_cooldown);
+        let declined = seek
+            .opponent
+            .as_ref()
+            .and_then(|opponent| self.declined.get(&opponent.to_lowercase()))
+            .is_some_and(|declined| declined.elapsed() < cooldown);
+        if declined {
+            return Err("the player didn't answer the last challenge");
+        }
+
+        Ok(())
+    }
+
+    // Whether another game can be started, given the games other sessions are playing.
+    pub fn allows_game(&self) -> Result<(), &'static str> {
+        match self.settings.bot_mode
+            && GAMES_IN_PROGRESS.load(Ordering::SeqCst) >= self.settings.max_concurrent_games
+        {
+            true => Err("too many games are in progress"),
+            false => Ok(()),
+        }
+    }
+
+    // Whether the bot may say something in chat unprompted, counting it if so.
+    pub fn allow_chat(&mut self) -> bool {
+        let interval = Duration::from_secs(self.settings.min_chat_interval);
+        if self.settings.bot_mode && self.last_chat.is_some_and(|l| l.elapsed() < interval) {
+            return false;
+        }
+
+        self.last_chat = Some(Instant::now());
+        true
+    }
+}
+
+// Counts a game as in progress for as long as it's held.
+pub struct GameSlot;
+
+impl GameSlot {
+    pub fn take() -> Self {
+        GAMES_IN_PROGRESS.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for GameSlot {
+    fn drop(&mut self) {
+        GAMES_IN_PROGRESS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bot_mode_limits() {
+        let settings = BotMode {
+            bot_mode: true,
+            min_seek_interval: 30,
+            challenge_timeout: 0,
+            decline_cooldown: 3600,
+            max_concurrent_games: 2,
+            min_chat_interval: 60,
+        };
+        let mut etiquette = Etiquette::new(settings);
+
+        let mut seek = "Seek new 1 Bot 6 900 10 A 0 30 1 0 0 0 0 Alice"
+            .parse::<Seek>()
+            .unwrap();
+        seek.opponent = Some("Alice".to_owned());
+
+        assert!(etiquette.seek_delay().is_none());
+        assert!(etiquette.allows_seek(&seek).is_ok());
+        etiquette.seek_posted(&seek);
+        assert!(etiquette.seek_delay().is_some());
+
+        assert!(etiquette.challenge_expired());
+        assert!(etiquette.allows_seek(&seek).is_err());
+        seek.opponent = Some("Bob".to_owned());
+        assert!(etiquette.allows_seek(&seek).is_ok());
+
+        assert!(etiquette.allow_chat());
+        assert!(!etiquette.allow_chat());
+
+        // Without bot mode, nothing is limited.
+        let mut etiquette = Etiquette::default();
+        etiquette.seek_posted(&seek);
+        assert!(etiquette.seek_delay().is_none());
+        assert!(!etiquette.challenge_expired());
+        assert!(etiquette.allow_chat());
+        assert!(etiquette.allow_chat());
+    }
+}
@@ -0,0 +1,172 @@
+use clap::Args;
+use tracing::{info, warn};
+
+use super::api;
+use super::seek::Seek;
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct OpponentFilter {
+    #[arg(
+        long = "only",
+        value_name = "PLAYER",
+        value_delimiter = ',',
+        conflicts_with = "block"
+    )]
+    pub only: Vec<String>,
+    #[arg(long = "block", value_name = "PLAYER", value_delimiter = ',')]
+    pub block: Vec<String>,
+    #[arg(long, value_name = "RATING")]
+    pub min_rating: Option<u32>,
+    #[arg(long, value_name = "RATING")]
+    pub max_rating: Option<u32>,
+    #[arg(long, value_name = "MESSAGE")]
+    pub rating_message: Option<String>,
+}
+
+impl OpponentFilter {
+    pub fn allows(&self, player: &str) -> bool {
+        let listed = |names: &[String]| names.iter().any(|n| n.eq_ignore_ascii_case(player));
+
+        if !self.only.is_empty() {
+            listed(&self.only)
+        } else {
+            !listed(&self.block)
+        }
+    }
+
+    // Looks up the player's rating if there's a range it has to be in.  A game isn't turned
+    // down just because the rating couldn't be looked up.
+    pub async fn check_rating(&self, api_url: &str, player: &str) -> Result<(), &'static str> {
+        if self.min_rating.is_none() && self.max_rating.is_none() {
+            return Ok(());
+        }
+
+        match api::rating(api_url, player).await {
+            Ok(rating) => {
+                info!(rating, "Looked up {player}'s rating.");
+                self.allows_rating(rating)
+            }
+            Err(error) => {
+                warn!(%error, "Could not look up {player}'s rating.");
+                Ok(())
+            }
+        }
+    }
+
+    // Players without a rating, like guests, only get in if there's no minimum.
+    fn allows_rating(&self, rating: Option<u32>) -> Result<(), &'static str> {
+        match rating {
+            None if self.min_rating.is_some() => Err("opponent has no rating"),
+            Some(rating) if self.min_rating.is_some_and(|min| rating < min) => {
+                Err("opponent's rating is too low")
+            }
+            Some(rating) if self.max_rating.is_some_and(|max| rating > max) => {
+                Err("opponent's rating is too high")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// The games accept --auto takes, going by the seek.
+#[derive(Args, Clone, Debug, Default)]
+pub struct SeekFilter {
+    #[arg(
+        long = "size",
+        value_name = "SIZE",
+        value_delimiter = ',',
+        requires = "continuous"
+    )]
+    pub sizes: Vec<u32>,
+    #[arg(long, value_name = "SECONDS", requires = "continuous")]
+    pub min_time: Option<u32>,
+    #[arg(long, value_name = "SECONDS", requires = "continuous")]
+    pub max_time: Option<u32>,
+    #[arg(long, action, requires = "continuous")]
+    pub rated_only: bool,
+}
+
+impl SeekFilter {
+    pub fn allows(&self, seek: &Seek) -> bool {
+        (self.sizes.is_empty() || self.sizes.contains(&seek.size))
+            && self.min_time.is_none_or(|min| seek.time >= min)
+            && self.max_time.is_none_or(|max| seek.time <= max)
+            && !(self.rated_only && seek.unrated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn allow_and_block() {
+        let filter = OpponentFilter::default();
+        assert!(filter.allows("anyone"));
+
+        let filter = OpponentFilter {
+            only: vec!["Alice".to_owned(), "Bob".to_owned()],
+            ..Default::default()
+        };
+        assert!(filter.allows("alice"));
+        assert!(filter.allows("Bob"));
+        assert!(!filter.allows("Carol"));
+
+        let filter = OpponentFilter {
+            block: vec!["Carol".to_owned()],
+            ..Default::default()
+        };
+        assert!(filter.allows("Alice"));
+        assert!(!filter.allows("carol"));
+    }
+
+    #[test]
+    fn rating_range() {
+        let filter = OpponentFilter {
+            min_rating: Some(1200),
+            max_rating: Some(1600),
+            ..Default::default()
+        };
+        assert!(filter.allows_rating(Some(1400)).is_ok());
+        assert!(filter.allows_rating(Some(1100)).is_err());
+        assert!(filter.allows_rating(Some(1700)).is_err());
+        assert!(filter.allows_rating(None).is_err());
+
+        let filter = OpponentFilter {
+            max_rating: Some(1600),
+            ..Default::default()
+        };
+        assert!(filter.allows_rating(None).is_ok());
+    }
+
+    #[test]
+    fn filter_seeks() {
+        let seek = "Seek new 1 Alice 6 900 10 A 4 30 1 1 0 0 0"
+            .parse::<Seek>()
+            .unwrap();
+        assert!(SeekFilter::default().allows(&seek));
+
+        let filter = SeekFilter {
+            sizes: vec![5, 6],
+            min_time: Some(600),
+            max_time: Some(900),
+            rated_only: false,
+        };
+        assert!(filter.allows(&seek));
+        assert!(!SeekFilter {
+            sizes: vec![7],
+            ..filter.clone()
+        }
+        .allows(&seek));
+        assert!(!SeekFilter {
+            max_time: Some(600),
+            ..filter.clone()
+        }
+        .allows(&seek));
+        assert!(!SeekFilter {
+            rated_only: true,
+            ..filter
+        }
+        .allows(&seek));
+    }
+}
@@ -1,20 +1,42 @@
-use std::fmt::Write;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::{self, Write};
 use std::io;
 use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
+use super::api::ApiGame;
+use super::board::{Board, Color};
 use super::err;
 
-#[derive(Debug, Default)]
+// The carry limit on the largest board there is.
+const MAX_CARRY: u32 = 8;
+
+#[derive(Clone, Debug, Default)]
 pub struct Game {
     pub id: u32,
     pub size: u32,
+    pub player: String,
     pub opponent: String,
-    pub color: String,
+    pub color: Color,
     pub time: (u32, u32),
-    pub half_komi: u32,
+    pub initial_time: u32,
+    // Only known if the seek the game came from was seen.
+    pub increment: Option<u32>,
+    pub half_komi: i32,
     pub flatstones: u32,
     pub capstones: u32,
+    pub unrated: bool,
+    pub tournament: bool,
+    // The starting position, if it isn't the empty board.
+    pub tps: Option<String>,
     pub moves: Vec<GameMove>,
+    // Comments written after moves in PTN, by the moves' indices, like an engine's evaluation.
+    pub comments: BTreeMap<usize, String>,
+    // The reply the engine expected to our last move, if it said, until the opponent moves.
+    pub ponder: Option<GameMove>,
+    pub started: Option<SystemTime>,
+    pub result: Option<String>,
 }
 
 impl FromStr for Game {
@@ -23,17 +45,18 @@ impl FromStr for Game {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let parts = s.split_ascii_whitespace().collect::<Vec<_>>();
 
-        let color = match parts[7] {
-            value if value == "white" || value == "black" => value.to_owned(),
-            _ => return Err("could not parse player color"),
-        };
-
-        let opponent = match color.as_str() {
-            "white" => parts[6],
-            "black" => parts[4],
-            _ => unreachable!(),
+        if parts.len() < 12 {
+            return Err("game start is missing fields");
         }
-        .to_owned();
+
+        let color = parts[7]
+            .parse::<Color>()
+            .map_err(|_| "could not parse player color")?;
+
+        let (player, opponent) = match color {
+            Color::White => (parts[4], parts[6]),
+            Color::Black => (parts[6], parts[4]),
+        };
 
         let time = parts[8]
             .parse::<u32>()
@@ -46,11 +69,13 @@ impl FromStr for Game {
             size: parts[3]
                 .parse::<u32>()
                 .map_err(|_| "could not parse board size")?,
-            opponent,
+            player: player.to_owned(),
+            opponent: opponent.to_owned(),
             color,
             time: (time, time),
+            initial_time: time,
             half_komi: parts[9]
-                .parse::<u32>()
+                .parse::<i32>()
                 .map_err(|_| "could not parse komi")?,
             flatstones: parts[10]
                 .parse::<u32>()
@@ -58,26 +83,180 @@ impl FromStr for Game {
             capstones: parts[11]
                 .parse::<u32>()
                 .map_err(|_| "could not parse capstones")?,
+            // Older versions of the server don't send these.
+            unrated: parts.get(12).is_some_and(|&p| p == "1"),
+            tournament: parts.get(13).is_some_and(|&p| p == "1"),
+            started: Some(SystemTime::now()),
             ..Default::default()
         })
     }
 }
 
+// A game in progress on the server, as listed to everyone who's logged in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListedGame {
+    pub id: u32,
+    pub white: String,
+    pub black: String,
+    pub size: u32,
+    pub time: u32,
+    pub increment: u32,
+    // Only newer servers list the komi, and only older ones how many plies have been played.
+    pub half_komi: Option<i32>,
+    pub plies: Option<u32>,
+}
+
+impl FromStr for ListedGame {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let listing = s
+            .strip_prefix("GameList Add ")
+            .ok_or("not a game listing")?;
+        let number = |value: Option<&str>| value.and_then(|v| v.trim().parse().ok());
+
+        // Older servers list "Game#1 Alice vs Bob, 5x5, 600, 10, 12 half-moves played, Alice
+        // to move".
+        if let Some(listing) = listing.strip_prefix("Game#") {
+            let fields = listing.split(',').collect::<Vec<_>>();
+            let players = fields[0].split_ascii_whitespace().collect::<Vec<_>>();
+            let [id, white, "vs", black] = players[..] else {
+                return Err("invalid players in game listing");
+            };
+
+            return Ok(Self {
+                id: number(Some(id)).ok_or("invalid game id")?,
+                white: white.to_owned(),
+                black: black.to_owned(),
+                size: number(
+                    fields
+                        .get(1)
+                        .and_then(|s| s.trim().split_once('x'))
+                        .map(|s| s.0),
+                )
+                .ok_or("invalid board size")?,
+                time: number(fields.get(2).copied()).ok_or("invalid game time")?,
+                increment: number(fields.get(3).copied()).ok_or("invalid increment")?,
+                half_komi: None,
+                plies: number(
+                    fields
+                        .get(4)
+                        .and_then(|s| s.split_ascii_whitespace().next()),
+                ),
+            });
+        }
+
+        let parts = listing.split_ascii_whitespace().collect::<Vec<_>>();
+        if parts.len() < 7 {
+            return Err("game listing is missing fields");
+        }
+
+        Ok(Self {
+            id: number(Some(parts[0])).ok_or("invalid game id")?,
+            white: parts[1].to_owned(),
+            black: parts[2].to_owned(),
+            size: number(Some(parts[3])).ok_or("invalid board size")?,
+            time: number(Some(parts[4])).ok_or("invalid game time")?,
+            increment: number(Some(parts[5])).ok_or("invalid increment")?,
+            half_komi: Some(parts[6].parse().map_err(|_| "invalid komi")?),
+            plies: None,
+        })
+    }
+}
+
+impl From<ApiGame> for ListedGame {
+    fn from(game: ApiGame) -> Self {
+        Self {
+            id: game.id,
+            white: game.white,
+            black: game.black,
+            size: game.size,
+            time: game.time,
+            increment: game.increment,
+            half_komi: Some(game.komi),
+            plies: None,
+        }
+    }
+}
+
 impl Game {
-    pub fn new_game_string(&self) -> String {
-        format!("teinewgame {}\n", self.size)
+    pub fn to_move(&self) -> Color {
+        self.to_move_at(self.moves.len())
     }
 
-    pub fn search_string(&self) -> String {
-        format!(
-            "go wtime {} btime {}\n",
-            self.time.0 * 1000,
-            self.time.1 * 1000
-        )
+    // Who was to move after the given number of plies.
+    pub fn to_move_at(&self, ply: usize) -> Color {
+        // A game from a TPS can start with either player to move.
+        let first = match self
+            .tps
+            .as_deref()
+            .and_then(|tps| tps.split_ascii_whitespace().nth(1))
+        {
+            Some("2") => Color::Black,
+            _ => Color::White,
+        };
+
+        if ply.is_multiple_of(2) {
+            first
+        } else {
+            first.opposite()
+        }
+    }
+
+    pub fn is_our_turn(&self) -> bool {
+        self.to_move() == self.color
+    }
+
+    // The winner of a finished game, if it wasn't a draw.
+    pub fn winner(&self) -> Option<Color> {
+        match self.result.as_deref()? {
+            "R-0" | "F-0" | "1-0" => Some(Color::White),
+            "0-R" | "0-F" | "0-1" => Some(Color::Black),
+            _ => None,
+        }
+    }
+
+    // Whether the game ended without a result before any move was made.
+    pub fn was_aborted(&self) -> bool {
+        self.result.is_none() && self.moves.is_empty()
+    }
+
+    // Our time remaining, in seconds.
+    pub fn our_time(&self) -> u32 {
+        match self.color {
+            Color::White => self.time.0,
+            Color::Black => self.time.1,
+        }
+    }
+
+    // Where the game can be watched while it's played, and reviewed afterwards.
+    pub fn url(&self) -> String {
+        format!("https://playtak.com/games/{}/view", self.id)
+    }
+
+    pub fn white_and_black(&self) -> (&str, &str) {
+        match self.color {
+            Color::White => (&self.player, &self.opponent),
+            Color::Black => (&self.opponent, &self.player),
+        }
+    }
+
+    // Our own clock is shown less the overhead the server charges us for each move.
+    pub fn search_string(&self, overhead: u64) -> String {
+        let (mut white, mut black) = (u64::from(self.time.0) * 1000, u64::from(self.time.1) * 1000);
+        match self.color {
+            Color::White => white = white.saturating_sub(overhead),
+            Color::Black => black = black.saturating_sub(overhead),
+        }
+
+        format!("go wtime {white} btime {black}\n")
     }
 
     pub fn position_string(&self) -> String {
-        let mut buffer = "position startpos moves".to_string();
+        let mut buffer = match &self.tps {
+            Some(tps) => format!("position tps {tps} moves"),
+            None => "position startpos moves".to_string(),
+        };
 
         for game_move in &self.moves {
             write!(buffer, " {}", game_move.to_ptn()).unwrap();
@@ -87,9 +266,232 @@ impl Game {
 
         buffer
     }
+
+    // Like position_string, but starting from the TPS of the position every `interval` plies,
+    // so that the list of moves the engine has to replay stays short.
+    pub fn snapshot_position_string(&self, interval: usize) -> io::Result<String> {
+        let start = self.moves.len() / interval * interval;
+
+        let mut board = match &self.tps {
+            Some(tps) => Board::from_tps(tps)?,
+            None => Board::new(self.size),
+        };
+        board.set_stones(self.flatstones, self.capstones);
+
+        for game_move in &self.moves[..start] {
+            board.play(game_move)?;
+        }
+
+        let mut buffer = format!("position tps {} moves", board.to_tps());
+
+        for game_move in &self.moves[start..] {
+            write!(buffer, " {}", game_move.to_ptn()).unwrap();
+        }
+
+        writeln!(buffer).unwrap();
+
+        Ok(buffer)
+    }
+
+    // The game as a PTN document, tagged with everything that's known about it.
+    pub fn to_ptn(&self) -> String {
+        let (white, black) = self.white_and_black();
+
+        let mut clock = format!("{}:{:02}", self.initial_time / 60, self.initial_time % 60);
+        if let Some(increment) = self.increment {
+            write!(clock, " +{increment}").unwrap();
+        }
+
+        let mut tags = vec![
+            ("Site", "PlayTak.com".to_owned()),
+            (
+                "Event",
+                match self.tournament {
+                    false => "Online Play".to_owned(),
+                    true => "Tournament".to_owned(),
+                },
+            ),
+        ];
+        if let Some(started) = self.started {
+            let (date, time) = utc_date_time(started);
+            tags.push(("Date", date));
+            tags.push(("Time", time));
+        }
+        tags.extend([
+            ("Player1", white.to_owned()),
+            ("Player2", black.to_owned()),
+            ("Clock", clock),
+        ]);
+        if let Some(result) = &self.result {
+            tags.push(("Result", result.clone()));
+        }
+        tags.extend([
+            ("Size", self.size.to_string()),
+            ("Komi", (self.half_komi as f32 / 2.0).to_string()),
+            ("Flats", self.flatstones.to_string()),
+            ("Caps", self.capstones.to_string()),
+            ("Rated", (!self.unrated).to_string()),
+            ("PlayTakId", self.id.to_string()),
+        ]);
+        if let Some(tps) = &self.tps {
+            tags.push(("TPS", tps.clone()));
+        }
+
+        let mut buffer = String::new();
+
+        for (name, value) in tags {
+            writeln!(buffer, "[{name} \"{value}\"]").unwrap();
+        }
+        writeln!(buffer).unwrap();
+
+        let moves = self
+            .ptn_moves()
+            .into_iter()
+            .enumerate()
+            .map(|(index, ptn)| match self.comments.get(&index) {
+                Some(comment) => format!("{ptn} {{{comment}}}"),
+                None => ptn,
+            })
+            .collect::<Vec<_>>();
+
+        for (number, pair) in moves.chunks(2).enumerate() {
+            writeln!(buffer, "{}. {}", number + 1, pair.join(" ")).unwrap();
+        }
+
+        if let Some(result) = &self.result {
+            writeln!(buffer, "{result}").unwrap();
+        }
+
+        buffer
+    }
+
+    // The position after the game's moves.
+    pub fn board(&self) -> io::Result<Board> {
+        let mut board = match &self.tps {
+            Some(tps) => Board::from_tps(tps)?,
+            None => Board::new(self.size),
+        };
+        board.set_stones(self.flatstones, self.capstones);
+
+        for game_move in &self.moves {
+            board.play(game_move)?;
+        }
+
+        Ok(board)
+    }
+
+    // A legal move to play when the move provider can't come up with one.
+    pub fn fallback_move(&self) -> Option<GameMove> {
+        self.board().ok()?.fallback_move()
+    }
+
+    // Checks that a move can be played next.  If the game so far can't be followed, the move
+    // is given the benefit of the doubt, as long as it fits on the board.
+    pub fn check_move(&self, game_move: &GameMove) -> io::Result<()> {
+        match self.board() {
+            Ok(mut board) => board.play(game_move),
+            Err(_) => game_move.check_size(self.size),
+        }
+    }
+
+    // The game's moves in PTN, with smashes marked.
+    pub fn ptn_moves(&self) -> Vec<String> {
+        let (mut board, mut legal) = match self.tps.as_deref().map(Board::from_tps) {
+            Some(Ok(board)) => (board, true),
+            Some(Err(_)) => (Board::new(self.size), false),
+            None => (Board::new(self.size), true),
+        };
+
+        self.moves
+            .iter()
+            .map(|game_move| {
+                let mut ptn = game_move.to_ptn();
+
+                // Once a move can't be followed, the board no longer says anything useful.
+                if legal && board.is_smash(game_move) {
+                    ptn.push('*');
+                }
+                legal = legal && board.play(game_move).is_ok();
+
+                ptn
+            })
+            .collect()
+    }
+}
+
+// A complete PTN document: header tags, then the moves, possibly with move numbers,
+// comments, and a result.
+#[derive(Debug, Default)]
+pub struct Ptn {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<GameMove>,
+    pub result: Option<String>,
+}
+
+const RESULTS: [&str; 8] = ["R-0", "0-R", "F-0", "0-F", "1-0", "0-1", "1/2-1/2", "0-0"];
+
+impl Ptn {
+    pub fn parse(value: &str) -> io::Result<Self> {
+        let mut ptn = Self::default();
+
+        // Comments can appear anywhere, so they're removed before anything else.
+        let mut text = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| err!("unterminated comment"))?;
+
+            text.push_str(&rest[..start]);
+            text.push(' ');
+            rest = &rest[start + end + 1..];
+        }
+        text.push_str(rest);
+
+        let mut rest = text.trim_start();
+        while let Some(tag) = rest.strip_prefix('[') {
+            let end = tag.find(']').ok_or_else(|| err!("unterminated tag"))?;
+            let (name, value) = tag[..end]
+                .trim()
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| err!("tag has no value"))?;
+            let value = value
+                .trim()
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .ok_or_else(|| err!("tag value is not quoted"))?;
+
+            ptn.tags.push((name.to_owned(), value.to_owned()));
+            rest = tag[end + 1..].trim_start();
+        }
+
+        for token in rest.split_ascii_whitespace() {
+            if token.chars().all(|c| c.is_ascii_digit() || c == '.') {
+                continue;
+            } else if RESULTS.contains(&token) {
+                ptn.result = Some(token.to_owned());
+            } else {
+                ptn.moves.push(GameMove::from_ptn(token)?);
+            }
+        }
+
+        if ptn.result.is_none() {
+            ptn.result = ptn.tag("Result").map(|r| r.to_owned());
+        }
+
+        Ok(ptn)
+    }
+
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+            .filter(|v| !v.is_empty())
+    }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum GameMove {
     Place {
         x: u32,
@@ -104,14 +506,14 @@ pub enum GameMove {
     },
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum PieceType {
     Flatstone,
     StandingStone,
     Capstone,
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Direction {
     North,
     South,
@@ -123,6 +525,10 @@ impl GameMove {
     pub fn from_playtak(value: &str) -> io::Result<Self> {
         let parts = value.split_ascii_whitespace().collect::<Vec<_>>();
 
+        if parts.len() < 3 {
+            return Err(err!("move is missing fields"));
+        }
+
         match parts[1] {
             "P" => {
                 let (x, y) = coords_from_square(parts[2])?;
@@ -138,7 +544,7 @@ impl GameMove {
             }
             "M" => {
                 let (x, y) = coords_from_square(parts[2])?;
-                let (tx, ty) = coords_from_square(parts[3])?;
+                let (tx, ty) = coords_from_square(parts.get(3).copied().unwrap_or_default())?;
 
                 let direction = if ty > y {
                     Direction::North
@@ -158,6 +564,23 @@ impl GameMove {
                     );
                 }
 
+                let distance = tx.abs_diff(x) + ty.abs_diff(y);
+                if (tx != x && ty != y) || distance == 0 {
+                    return Err(err!(format!(
+                        "the spread from {} to {} isn't along a row or column",
+                        parts[2], parts[3]
+                    )));
+                }
+                if drops.len() as u32 != distance {
+                    return Err(err!(format!(
+                        "the spread from {} to {} has {} drops for {distance} squares",
+                        parts[2],
+                        parts[3],
+                        drops.len()
+                    )));
+                }
+                check_drops(&drops)?;
+
                 Ok(Self::Spread {
                     x,
                     y,
@@ -169,7 +592,7 @@ impl GameMove {
         }
     }
 
-    pub fn to_playtak(&self, game_id: u32) -> String {
+    pub fn to_playtak(&self, game_id: u32) -> io::Result<String> {
         match self {
             Self::Place { x, y, piece_type } => {
                 let square = square_from_coords(*x, *y).to_uppercase();
@@ -183,7 +606,7 @@ impl GameMove {
 
                 writeln!(buffer).unwrap();
 
-                buffer
+                Ok(buffer)
             }
             Self::Spread {
                 x,
@@ -193,10 +616,13 @@ impl GameMove {
             } => {
                 let count = drops.len() as u32;
                 let (tx, ty) = match direction {
-                    Direction::North => (*x, *y + count),
-                    Direction::South => (*x, *y - count),
-                    Direction::East => (*x + count, *y),
-                    Direction::West => (*x - count, *y),
+                    Direction::North => (Some(*x), y.checked_add(count)),
+                    Direction::South => (Some(*x), y.checked_sub(count)),
+                    Direction::East => (x.checked_add(count), Some(*y)),
+                    Direction::West => (x.checked_sub(count), Some(*y)),
+                };
+                let (Some(tx), Some(ty)) = (tx, ty) else {
+                    return Err(err!("the spread runs off the board"));
                 };
 
                 let square = square_from_coords(*x, *y).to_uppercase();
@@ -210,26 +636,37 @@ impl GameMove {
 
                 writeln!(buffer).unwrap();
 
-                buffer
+                Ok(buffer)
             }
         }
     }
 
     pub fn from_ptn(value: &str) -> io::Result<Self> {
+        // Smash markers and annotations don't change the move.
+        let value = value.trim_end_matches(['*', '\'', '"', '!', '?']);
+
         let mut chars = value.chars().collect::<Vec<_>>();
 
+        if chars.is_empty() {
+            return Err(err!("ptn move is too short"));
+        }
+
         let piece_type = match chars[0] {
             'S' => PieceType::StandingStone,
             'C' => PieceType::Capstone,
             _ => PieceType::Flatstone,
         };
 
-        let pickup = chars[0].to_digit(10).unwrap_or(1);
+        let pickup = chars[0].to_digit(10);
 
         if ['F', 'S', 'C'].contains(&chars[0]) || chars[0].is_ascii_digit() {
             chars.remove(0);
         }
 
+        if chars.len() < 2 {
+            return Err(err!("ptn move is too short"));
+        }
+
         let (x, y) = coords_from_square(&format!("{}{}", chars[0], chars[1]))?;
 
         if chars.len() == 2 {
@@ -252,9 +689,16 @@ impl GameMove {
                 }
             }
 
-            if drops.is_empty() {
-                drops.push(pickup);
+            match (pickup, drops.iter().sum::<u32>()) {
+                (pickup, 0) => drops.push(pickup.unwrap_or(1)),
+                (Some(pickup), dropped) if dropped != pickup => {
+                    return Err(err!(format!(
+                        "the spread picks up {pickup} stones but drops {dropped}"
+                    )))
+                }
+                _ => (),
             }
+            check_drops(&drops)?;
 
             Ok(GameMove::Spread {
                 x,
@@ -267,6 +711,51 @@ impl GameMove {
         }
     }
 
+    // Checks that the move fits on a board of the given size, whatever is on the board.
+    pub fn check_size(&self, size: u32) -> io::Result<()> {
+        let (Self::Place { x, y, .. } | Self::Spread { x, y, .. }) = *self;
+        if x >= size || y >= size {
+            return Err(MoveError::OffBoard { x, y, size }.into());
+        }
+
+        let Self::Spread {
+            direction, drops, ..
+        } = self
+        else {
+            return Ok(());
+        };
+
+        check_drops(drops)?;
+
+        let carried = drops.iter().map(|&d| u64::from(d)).sum::<u64>();
+        if carried > u64::from(size) {
+            return Err(MoveError::OverCarry {
+                carried,
+                limit: size,
+            }
+            .into());
+        }
+
+        let room = match direction {
+            Direction::North => size - 1 - y,
+            Direction::South => y,
+            Direction::East => size - 1 - x,
+            Direction::West => x,
+        };
+        if drops.len() as u32 > room {
+            return Err(MoveError::TooFar {
+                x,
+                y,
+                direction: *direction,
+                drops: drops.len(),
+                room,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
     pub fn to_ptn(&self) -> String {
         match self {
             Self::Place { x, y, piece_type } => {
@@ -304,9 +793,7 @@ impl GameMove {
 
                 if drops.len() > 1 {
                     for drop in drops {
-                        buffer
-                            .write_char(char::from_digit(*drop, 10).unwrap())
-                            .unwrap();
+                        write!(buffer, "{drop}").unwrap();
                     }
                 }
 
@@ -316,6 +803,97 @@ impl GameMove {
     }
 }
 
+// Checks what can be checked of a spread's drops without knowing the board size.
+fn check_drops(drops: &[u32]) -> io::Result<()> {
+    if drops.is_empty() {
+        return Err(MoveError::NoDrops.into());
+    }
+    if let Some(drop) = drops.iter().position(|&d| d == 0) {
+        return Err(MoveError::EmptyDrop { drop: drop + 1 }.into());
+    }
+
+    let carried = drops.iter().map(|&d| u64::from(d)).sum::<u64>();
+    if carried > u64::from(MAX_CARRY) {
+        return Err(MoveError::OverCarry {
+            carried,
+            limit: MAX_CARRY,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+// Why a move can't be played on a board of its size, whatever is on the board.  Drops are
+// counted from 1.
+#[derive(Debug, PartialEq)]
+pub enum MoveError {
+    OffBoard {
+        x: u32,
+        y: u32,
+        size: u32,
+    },
+    NoDrops,
+    EmptyDrop {
+        drop: usize,
+    },
+    OverCarry {
+        carried: u64,
+        limit: u32,
+    },
+    TooFar {
+        x: u32,
+        y: u32,
+        direction: Direction,
+        drops: usize,
+        room: u32,
+    },
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::OffBoard { x, y, size } => {
+                let square = square_from_coords(x, y);
+                write!(f, "{square} is off the {size}x{size} board")
+            }
+            Self::NoDrops => write!(f, "the spread has no drops"),
+            Self::EmptyDrop { drop } => write!(f, "drop {drop} of the spread is empty"),
+            Self::OverCarry { carried, limit } => write!(
+                f,
+                "the spread carries {carried} stones, over the carry limit of {limit}"
+            ),
+            Self::TooFar {
+                x,
+                y,
+                direction,
+                drops,
+                room,
+            } => {
+                let square = square_from_coords(x, y);
+                let name = match direction {
+                    Direction::North => "above",
+                    Direction::South => "below",
+                    Direction::East => "right of",
+                    Direction::West => "left of",
+                };
+                write!(
+                    f,
+                    "the spread has {drops} drops, but there are only {room} squares {name} {square}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for MoveError {}
+
+impl From<MoveError> for io::Error {
+    fn from(error: MoveError) -> Self {
+        io::Error::other(error)
+    }
+}
+
 fn coords_from_square(value: &str) -> io::Result<(u32, u32)> {
     if value.len() != 2 {
         return Err(err!("invalid space"));
@@ -342,18 +920,242 @@ fn coords_from_square(value: &str) -> io::Result<(u32, u32)> {
     Ok((file_number, rank_number))
 }
 
-fn square_from_coords(x: u32, y: u32) -> String {
-    format!(
-        "{}{}",
-        char::from_digit(x + 10, 18).unwrap(),
-        char::from_digit(y + 1, 10).unwrap(),
+// Formats a time as a UTC date and time of day, as they're written in PTN tags.
+pub fn utc_date_time(time: SystemTime) -> (String, String) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let (days, seconds) = ((seconds / 86400) as i64, seconds % 86400);
+
+    // Converts days since the epoch to a calendar date; see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (
+        format!("{year}.{month:02}.{day:02}"),
+        format!(
+            "{:02}:{:02}:{:02}",
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        ),
     )
 }
 
+// Squares too far out to have a name, which only turn up in errors, are written as coordinates.
+fn square_from_coords(x: u32, y: u32) -> String {
+    let file = x.checked_add(10).and_then(|f| char::from_digit(f, 36));
+    match (file, y.checked_add(1)) {
+        (Some(file), Some(rank)) => format!("{file}{rank}"),
+        _ => format!("({x}, {y})"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn game_from_playtak() {
+        let game = "Game Start 100 6 Alice vs Bob black 900 -4 30 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+
+        assert_eq!(game.id, 100);
+        assert_eq!(game.size, 6);
+        assert_eq!(game.opponent, "Alice");
+        assert_eq!(game.color, Color::Black);
+        assert_eq!(game.half_komi, -4);
+        assert_eq!((game.flatstones, game.capstones), (30, 1));
+        assert!(!game.unrated && !game.tournament);
+        assert_eq!(game.url(), "https://playtak.com/games/100/view");
+
+        let game = "Game Start 101 5 Alice vs Bob white 600 0 21 1 1 0"
+            .parse::<Game>()
+            .unwrap();
+        assert!(game.unrated && !game.tournament);
+    }
+
+    #[test]
+    fn parse_game_listings() {
+        let game = "GameList Add 560813 Alice Bob 6 900 20 4 30 1 0 0 0 0"
+            .parse::<ListedGame>()
+            .unwrap();
+        assert_eq!(
+            (game.id, game.white.as_str(), game.black.as_str()),
+            (560813, "Alice", "Bob")
+        );
+        assert_eq!((game.size, game.time, game.increment), (6, 900, 20));
+        assert_eq!((game.half_komi, game.plies), (Some(4), None));
+
+        let game =
+            "GameList Add Game#12 Alice vs Bob, 5x5, 600, 10, 14 half-moves played, Alice to move"
+                .parse::<ListedGame>()
+                .unwrap();
+        assert_eq!(
+            (game.id, game.white.as_str(), game.black.as_str()),
+            (12, "Alice", "Bob")
+        );
+        assert_eq!((game.size, game.time, game.increment), (5, 600, 10));
+        assert_eq!((game.half_komi, game.plies), (None, Some(14)));
+
+        assert!("GameList Add 1 a b".parse::<ListedGame>().is_err());
+        assert!("GameList Add Game#1 a b, 5x5"
+            .parse::<ListedGame>()
+            .is_err());
+    }
+
+    #[test]
+    fn game_to_ptn() {
+        let mut game = "Game Start 100 5 Alice vs Bob black 600 3 21 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+
+        game.increment = Some(10);
+        game.started = Some(UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000));
+        game.moves = ["a1", "e5", "c3"]
+            .into_iter()
+            .map(|m| GameMove::from_ptn(m).unwrap())
+            .collect();
+        game.result = Some("0-R".to_owned());
+        game.comments.insert(1, "+0.85/17 3.2s".to_owned());
+
+        assert_eq!(game.player, "Bob");
+        assert_eq!(
+            game.to_ptn(),
+            r#"[Site "PlayTak.com"]
+[Event "Online Play"]
+[Date "2023.11.14"]
+[Time "22:13:20"]
+[Player1 "Alice"]
+[Player2 "Bob"]
+[Clock "10:00 +10"]
+[Result "0-R"]
+[Size "5"]
+[Komi "1.5"]
+[Flats "21"]
+[Caps "1"]
+[Rated "true"]
+[PlayTakId "100"]
+
+1. a1 e5 {+0.85/17 3.2s}
+2. c3
+0-R
+"#
+        );
+    }
+
+    #[test]
+    fn our_turn() {
+        let mut game = Game {
+            size: 5,
+            color: Color::Black,
+            ..Default::default()
+        };
+        assert_eq!(game.to_move(), Color::White);
+        assert!(!game.is_our_turn());
+
+        game.moves.push(GameMove::from_ptn("a1").unwrap());
+        assert_eq!(game.to_move(), Color::Black);
+        assert!(game.is_our_turn());
+
+        game.tps = Some("x5/x5/x5/x5/2,x4 2 1".to_owned());
+        assert_eq!(game.to_move(), Color::White);
+        assert!(!game.is_our_turn());
+    }
+
+    #[test]
+    fn snapshot_position() {
+        let mut game = Game {
+            size: 5,
+            flatstones: 21,
+            capstones: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            game.snapshot_position_string(4).unwrap(),
+            "position tps x5/x5/x5/x5/x5 1 1 moves\n"
+        );
+
+        game.moves = ["a1", "e5", "Cc3", "d3", "c3>"]
+            .into_iter()
+            .map(|m| GameMove::from_ptn(m).unwrap())
+            .collect();
+        assert_eq!(
+            game.snapshot_position_string(4).unwrap(),
+            "position tps x4,1/x5/x2,1C,2,x/x5/2,x4 1 3 moves c3>\n"
+        );
+    }
+
+    #[test]
+    fn ptn_moves_mark_smashes() {
+        let game = Game {
+            size: 5,
+            moves: ["a1", "e5", "Cc3", "Sd3", "c3>*"]
+                .into_iter()
+                .map(|m| GameMove::from_ptn(m).unwrap())
+                .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(game.ptn_moves(), ["a1", "e5", "Cc3", "Sd3", "c3>*"]);
+
+        let game = Game {
+            size: 5,
+            moves: ["a1", "h8<"]
+                .into_iter()
+                .map(|m| GameMove::from_ptn(m).unwrap())
+                .collect(),
+            ..Default::default()
+        };
+        assert_eq!(game.ptn_moves(), ["a1", "h8<"]);
+    }
+
+    #[test]
+    fn parse_ptn() {
+        let ptn = Ptn::parse(
+            r#"[Site "PlayTak.com"]
+[Player1 "Alice"]
+[Player2 "Bob"]
+[Size "5"]
+[Komi "2"]
+[Result ""]
+
+1. a1 e5 {Both players start in a corner.}
+2. Cc3 Sd3
+3. c3>* { A smash,
+which flattens d3. } R-0
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(ptn.tag("site"), Some("PlayTak.com"));
+        assert_eq!(ptn.tag("Player2"), Some("Bob"));
+        assert_eq!(ptn.tag("Result"), None);
+        assert_eq!(ptn.tag("TPS"), None);
+        assert_eq!(ptn.moves.len(), 5);
+        assert_eq!(ptn.moves[4], GameMove::from_ptn("c3>").unwrap());
+        assert_eq!(ptn.result.as_deref(), Some("R-0"));
+
+        assert!(Ptn::parse("[Size 5]\n1. a1").is_err());
+        assert!(Ptn::parse("1. a1 {unterminated").is_err());
+        assert!(Ptn::parse("1. a1 x9").is_err());
+    }
+
     #[test]
     fn move_from_playtak() {
         let game_move = GameMove::from_playtak("Game#123456 P A1").unwrap();
@@ -386,6 +1188,82 @@ mod tests {
                 drops: vec![2, 1, 2, 1],
             },
         );
+
+        for line in [
+            "Game#1 M B4 F4 2 1",
+            "Game#1 M B4 C5 1",
+            "Game#1 M B4 B4",
+            "Game#1 M B4 B5",
+            "Game#1 M B4 B5 0",
+            "Game#1 M A1 C1 12 1",
+            "Game#1 M A1 B1 9",
+        ] {
+            assert!(GameMove::from_playtak(line).is_err(), "{line}");
+        }
+    }
+
+    #[test]
+    fn check_move_size() {
+        let check = |ptn: &str, size| GameMove::from_ptn(ptn).unwrap().check_size(size);
+
+        assert!(check("e5", 5).is_ok());
+        assert!(check("5a1>1112", 6).is_ok());
+        assert!(check("f1", 5).is_err());
+        assert!(check("6a1>", 5).is_err());
+        assert!(check("5a1>11111", 5).is_err());
+        assert!(check("2c3-11", 5).is_ok());
+        assert!(check("3c3-111", 5).is_err());
+
+        let error = check("6a1>", 5).unwrap_err().to_string();
+        assert!(error.contains("carry limit of 5"), "{error}");
+
+        // A game that can't be followed still keeps moves on the board.
+        let mut game = "Game Start 1 5 Alice vs Bob white 600 0 30 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+        game.moves.push(GameMove::from_ptn("a1").unwrap());
+        game.moves.push(GameMove::from_ptn("a1").unwrap());
+        assert!(game.board().is_err());
+        assert!(game.check_move(&GameMove::from_ptn("e5").unwrap()).is_ok());
+        assert!(game
+            .check_move(&GameMove::from_ptn("2a2-11").unwrap())
+            .is_err());
+
+        // Callers can tell the reasons apart.
+        let reason = |ptn: &str, size| {
+            let error = check(ptn, size).unwrap_err();
+            *error.into_inner().unwrap().downcast::<MoveError>().unwrap()
+        };
+        assert_eq!(
+            reason("f1", 5),
+            MoveError::OffBoard {
+                x: 5,
+                y: 0,
+                size: 5
+            }
+        );
+        assert_eq!(
+            reason("6a1>", 5),
+            MoveError::OverCarry {
+                carried: 6,
+                limit: 5
+            }
+        );
+        assert_eq!(
+            reason("3c3-111", 5),
+            MoveError::TooFar {
+                x: 2,
+                y: 2,
+                direction: Direction::South,
+                drops: 3,
+                room: 2,
+            }
+        );
+        let error = GameMove::from_playtak("Game#1 M B4 B5 0").unwrap_err();
+        assert_eq!(
+            *error.into_inner().unwrap().downcast::<MoveError>().unwrap(),
+            MoveError::EmptyDrop { drop: 1 }
+        );
     }
 
     #[test]
@@ -395,7 +1273,8 @@ mod tests {
             y: 0,
             piece_type: PieceType::Flatstone,
         }
-        .to_playtak(123456);
+        .to_playtak(123456)
+        .unwrap();
         assert_eq!(game_move, "Game#123456 P A1\n",);
 
         let game_move = GameMove::Place {
@@ -403,7 +1282,8 @@ mod tests {
             y: 5,
             piece_type: PieceType::Capstone,
         }
-        .to_playtak(123456);
+        .to_playtak(123456)
+        .unwrap();
         assert_eq!(game_move, "Game#123456 P C6 C\n",);
 
         let game_move = GameMove::Spread {
@@ -412,8 +1292,27 @@ mod tests {
             direction: Direction::East,
             drops: vec![2, 1, 2, 1],
         }
-        .to_playtak(123456);
+        .to_playtak(123456)
+        .unwrap();
         assert_eq!(game_move, "Game#123456 M B4 F4 2 1 2 1\n",);
+
+        // A spread off the edge is an error rather than a panic.
+        let game_move = GameMove::Spread {
+            x: 0,
+            y: 1,
+            direction: Direction::South,
+            drops: vec![1, 1],
+        };
+        assert!(game_move.to_playtak(123456).is_err());
+        let game_move = GameMove::Place {
+            x: 40,
+            y: u32::MAX,
+            piece_type: PieceType::Flatstone,
+        };
+        assert_eq!(
+            game_move.to_playtak(123456).unwrap(),
+            "Game#123456 P (40, 4294967295)\n"
+        );
     }
 
     #[test]
@@ -481,6 +1380,23 @@ mod tests {
                 drops: vec![2, 2, 1]
             },
         );
+
+        let game_move = GameMove::from_ptn("Ca3'!").unwrap();
+        assert_eq!(
+            game_move,
+            GameMove::Place {
+                x: 0,
+                y: 2,
+                piece_type: PieceType::Capstone
+            },
+        );
+
+        assert!(GameMove::from_ptn("").is_err());
+        assert!(GameMove::from_ptn("S").is_err());
+        assert!(GameMove::from_ptn("3a").is_err());
+        assert!(GameMove::from_ptn("4a1>12").is_err());
+        assert!(GameMove::from_ptn("2a1>101").is_err());
+        assert!(GameMove::from_ptn("0a1>").is_err());
     }
 
     #[test]
@@ -0,0 +1,296 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use tracing::{debug, warn};
+
+use super::err;
+use super::runtime::{self, TcpListener, TcpStream};
+
+// Nothing served here needs requests any bigger than this.
+const MAX_REQUEST_SIZE: usize = 64 * 1024;
+
+// How long a client has to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How often an idle event stream is sent a comment, which is how closed streams are noticed.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+// How long to wait before accepting again when accepting a connection fails.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+// Just enough of HTTP/1.1 to serve a few pages and event streams on a local address.  Each
+// connection carries a single request.
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: Option<String>,
+    // Header names are lowercased.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub enum Response {
+    Full {
+        status: u16,
+        content_type: &'static str,
+        body: String,
+    },
+    // Server-sent events, with each item as the data of one event.  Shared events can be
+    // subscribed to by pages from anywhere.
+    Events {
+        events: Pin<Box<dyn Stream<Item = String> + Send>>,
+        shared: bool,
+    },
+}
+
+impl Response {
+    pub fn html(body: &str) -> Self {
+        Self::Full {
+            status: 200,
+            content_type: "text/html; charset=utf-8",
+            body: body.to_owned(),
+        }
+    }
+
+    pub fn json(value: Value) -> Self {
+        Self::Full {
+            status: 200,
+            content_type: "application/json",
+            body: format!("{value}\n"),
+        }
+    }
+
+    pub fn error(status: u16, message: &str) -> Self {
+        Self::Full {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{message}\n"),
+        }
+    }
+}
+
+pub async fn serve<H, F>(listener: TcpListener, handler: H)
+where
+    H: Fn(Request) -> F + Clone + Send + 'static,
+    F: Future<Output = Response> + Send,
+{
+    loop {
+        let stream = match listener.accept().await {
+            Ok(stream) => stream,
+            Err(error) => {
+                warn!(%error, "Could not accept an HTTP connection.");
+                runtime::sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        let handler = handler.clone();
+        runtime::spawn(async move {
+            if let Err(error) = handle_connection(stream, handler).await {
+                debug!(%error, "HTTP connection ended.");
+            }
+        });
+    }
+}
+
+async fn handle_connection<H, F>(stream: TcpStream, handler: H) -> io::Result<()>
+where
+    H: Fn(Request) -> F,
+    F: Future<Output = Response>,
+{
+    let mut reader = futures::io::BufReader::new(stream.clone());
+    let mut writer = stream;
+
+    let request = match runtime::timeout(REQUEST_TIMEOUT, read_request(&mut reader)).await {
+        Some(Ok(request)) => request,
+        Some(Err(error)) => {
+            let response = Response::error(400, &error.to_string());
+            return write_response(&mut writer, response).await;
+        }
+        None => return Err(io::Error::from(io::ErrorKind::TimedOut)),
+    };
+
+    debug!(
+        method = request.method,
+        path = request.path,
+        "HTTP request."
+    );
+    write_response(&mut writer, handler(request).await).await
+}
+
+async fn read_request(mut reader: impl AsyncBufRead + Unpin) -> io::Result<Request> {
+    let mut line = String::new();
+    let mut size = reader.read_line(&mut line).await?;
+
+    let mut parts = line.split_ascii_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(err!("malformed request line"));
+    };
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_owned(), Some(query.to_owned())),
+        None => (target.to_owned(), None),
+    };
+    let method = method.to_owned();
+
+    let mut headers = Vec::new();
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await? {
+            0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+            n => size += n,
+        }
+
+        if size > MAX_REQUEST_SIZE {
+            return Err(err!("request too large"));
+        }
+
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+            if name == "content-length" {
+                content_length = value
+                    .parse::<usize>()
+                    .map_err(|_| err!("invalid content length"))?;
+            }
+            headers.push((name, value.to_owned()));
+        }
+    }
+
+    if size + content_length > MAX_REQUEST_SIZE {
+        return Err(err!("request too large"));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Request {
+        method,
+        path,
+        query,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    })
+}
+
+async fn write_response(mut writer: impl AsyncWrite + Unpin, response: Response) -> io::Result<()> {
+    match response {
+        Response::Full {
+            status,
+            content_type,
+            body,
+        } => {
+            let head = format!(
+                "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+                 Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+                reason(status),
+                body.len()
+            );
+
+            writer.write_all(head.as_bytes()).await?;
+            writer.write_all(body.as_bytes()).await?;
+            writer.flush().await
+        }
+        Response::Events { mut events, shared } => {
+            let head = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n{}\
+                 Cache-Control: no-store\r\nConnection: close\r\n\r\n",
+                if shared {
+                    "Access-Control-Allow-Origin: *\r\n"
+                } else {
+                    ""
+                }
+            );
+            writer.write_all(head.as_bytes()).await?;
+            writer.flush().await?;
+
+            loop {
+                let event = match runtime::timeout(KEEP_ALIVE_INTERVAL, events.next()).await {
+                    Some(Some(data)) => {
+                        data.lines()
+                            .map(|line| format!("data: {line}\n"))
+                            .collect::<String>()
+                            + "\n"
+                    }
+                    Some(None) => return Ok(()),
+                    None => ": keep-alive\n\n".to_owned(),
+                };
+
+                writer.write_all(event.as_bytes()).await?;
+                writer.flush().await?;
+            }
+        }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        415 => "Unsupported Media Type",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+
+    #[test]
+    fn parse_request() {
+        let request = block_on(read_request(Cursor::new(
+            "POST /seek?size=6 HTTP/1.1\r\nHost: localhost\r\ncontent-length: 5\r\n\r\nhello",
+        )))
+        .unwrap();
+
+        assert_eq!(
+            request,
+            Request {
+                method: "POST".to_owned(),
+                path: "/seek".to_owned(),
+                query: Some("size=6".to_owned()),
+                headers: vec![
+                    ("host".to_owned(), "localhost".to_owned()),
+                    ("content-length".to_owned(), "5".to_owned()),
+                ],
+                body: "hello".to_owned(),
+            }
+        );
+        assert_eq!(request.header("Content-Length"), Some("5"));
+
+        assert!(block_on(read_request(Cursor::new("\r\n\r\n"))).is_err());
+        assert!(block_on(read_request(Cursor::new("GET / HTTP/1.1\r\nHost"))).is_err());
+        assert!(block_on(read_request(Cursor::new(
+            "POST / HTTP/1.1\r\nContent-Length: 999999\r\n\r\n"
+        )))
+        .is_err());
+    }
+}
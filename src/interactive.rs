@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write as _};
+
+use clap::Parser;
+use futures::channel::mpsc::{self, UnboundedSender};
+use futures::{select, AsyncWriteExt, FutureExt, StreamExt};
+use tracing::{error, info};
+
+use super::chat::Moderation;
+use super::engine::Engine;
+use super::game::Game;
+use super::message::{GameUpdate, Message};
+use super::runtime::{self, stdin_lines};
+use super::seek::Seek;
+use super::session::Session;
+use super::{print_seeks, read, read_initial_state, run_game, write, ArgCommand};
+
+#[derive(Debug, Parser)]
+#[command(no_binary_name = true, name = "seek")]
+struct SeekLine {
+    #[command(flatten)]
+    seek: Seek,
+}
+
+const HELP: &str = "Commands:
+  list [mine]               List the open seeks, or only those directed at you.
+  seek <options>            Post a seek. Takes the same options as the seek command.
+  accept <id | player>      Accept a seek by id or by the player who posted it.
+  tell <player> <message>   Send a private message.
+  resign                    Resign the current game.
+  quit                      Log out and exit.";
+
+pub async fn run_interactive(args: &ArgCommand) -> io::Result<()> {
+    let mut session = Session::connect_retrying(args.login()).await?;
+
+    let (initial_seeks, _, resumed_game) = read_initial_state(&mut session.reader).await?;
+
+    let mut seeks = initial_seeks
+        .into_iter()
+        .filter_map(|s| s.id.map(|id| (id, s)))
+        .collect::<BTreeMap<_, _>>();
+
+    runtime::spawn(session.keep_alive());
+
+    // Lines for the game in progress are forwarded to the task that's running it.
+    let mut current_game: Option<(u32, UnboundedSender<io::Result<String>>)> = None;
+
+    if let Some(game) = resumed_game {
+        current_game = start_game(args, &session, game).await;
+    }
+
+    println!("{HELP}");
+    prompt();
+
+    let mut input = stdin_lines();
+
+    loop {
+        select! {
+            line = read(&mut session.reader).fuse() => {
+                let line = line?;
+
+                match Message::parse(&line) {
+                    Message::SeekNew(seek @ Seek { id: Some(id), .. }) => {
+                        seeks.insert(id, seek);
+                    }
+                    Message::SeekRemove(id) => {
+                        seeks.remove(&id);
+                    }
+                    Message::Chat(chat) => {
+                        println!("{}", chat.line);
+                        prompt();
+                    }
+                    Message::Error(error) => {
+                        println!("Error from the server: {error}");
+                        prompt();
+                    }
+                    Message::GameStart(game) if current_game.is_none() => {
+                        current_game = start_game(args, &session, game).await;
+                    }
+                    Message::Nok if current_game.is_none() => {
+                        println!("The server rejected the last command.");
+                        prompt();
+                    }
+                    // The game's task parses the line again for itself.
+                    message @ (Message::Nok | Message::Game { .. }) => {
+                        if let Some((id, sender)) = &current_game {
+                            let over = match message {
+                                Message::Game { id: game_id, update } if game_id == *id => {
+                                    Some(matches!(update, GameUpdate::Over(_) | GameUpdate::Abandoned))
+                                }
+                                Message::Nok => Some(false),
+                                _ => None,
+                            };
+
+                            if let Some(over) = over {
+                                sender.unbounded_send(Ok(line)).ok();
+
+                                if over {
+                                    current_game = None;
+                                    prompt();
+                                }
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+            }
+            line = input.next().fuse() => {
+                let Some(line) = line else {
+                    break;
+                };
+                let line = line?;
+                let words = line.split_ascii_whitespace().collect::<Vec<_>>();
+
+                match words.as_slice() {
+                    [] => (),
+                    ["help"] => println!("{HELP}"),
+                    ["list"] if seeks.is_empty() => println!("No open seeks."),
+                    ["list"] => print_seeks(seeks.values(), &session.name, false, &HashMap::new()),
+                    ["list", "mine"] => print_seeks(seeks.values(), &session.name, true, &HashMap::new()),
+                    ["seek", options @ ..] => match SeekLine::try_parse_from(options) {
+                        Ok(SeekLine { seek }) if seek.preset.is_some() => {
+                            println!("Presets are only available to the seek command.");
+                        }
+                        Ok(SeekLine { seek }) => {
+                            info!("Posting seek.");
+                            write(&mut session.writer, seek.to_seek_string()).await?;
+                        }
+                        Err(error) => print!("{}", error.render()),
+                    },
+                    ["accept", target] => {
+                        let seek_id = target.parse::<u32>().ok().or_else(|| {
+                            seeks
+                                .values()
+                                .find(|s| {
+                                    s.player
+                                        .as_deref()
+                                        .is_some_and(|p| p.eq_ignore_ascii_case(target))
+                                })
+                                .and_then(|s| s.id)
+                        });
+
+                        if let Some(seek_id) = seek_id {
+                            info!("Accepting seek {seek_id}.");
+                            write(&mut session.writer, format!("Accept {seek_id}\n")).await?;
+                        } else {
+                            println!("No seek found from {target}.");
+                        }
+                    }
+                    ["tell", player, message @ ..] if !message.is_empty() => {
+                        let message = message.join(" ");
+                        write(&mut session.writer, format!("Tell {player} {message}\n")).await?;
+                    }
+                    ["resign"] => {
+                        if let Some((id, _)) = &current_game {
+                            info!(game_id = id, "Resigning game.");
+                            write(&mut session.writer, format!("Game#{id} Resign\n")).await?;
+                        } else {
+                            println!("There is no game in progress.");
+                        }
+                    }
+                    ["quit"] | ["exit"] => break,
+                    _ => println!("Unrecognized command. Type \"help\" for a list of commands."),
+                }
+
+                prompt();
+            }
+        }
+    }
+
+    write(&mut session.writer, "quit\n").await?;
+    session.writer.flush().await
+}
+
+async fn start_game(
+    args: &ArgCommand,
+    session: &Session,
+    game: Game,
+) -> Option<(u32, UnboundedSender<io::Result<String>>)> {
+    let id = game.id;
+
+    let mut engine =
+        match Engine::initialize(args.engine_arguments(), args.engine_settings(), &game).await {
+            Ok(engine) => engine,
+            Err(error) => {
+                error!(%error, game_id = id, "Could not start the engine for the game.");
+                return None;
+            }
+        };
+
+    let (sender, receiver) = mpsc::unbounded();
+    let playtak_writer = session.writer.clone();
+
+    runtime::spawn(async move {
+        if let Err(error) = run_game(
+            game,
+            &mut engine,
+            (playtak_writer, receiver),
+            (&mut None, &mut Moderation::default()),
+            (None, None),
+        )
+        .await
+        {
+            error!(%error, game_id = id, "Game ended with an error.");
+        }
+        engine.quit().await.ok();
+    });
+
+    Some((id, sender))
+}
+
+fn prompt() {
+    print!("> ");
+    io::stdout().flush().ok();
+}
@@ -0,0 +1,190 @@
+use std::cmp::Ordering;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+use tracing::{info, warn};
+
+use super::err;
+use super::game::{Game, GameMove};
+
+// A file per game that every move and clock update is appended to as it happens, so that if
+// the client dies mid-game, the next run has its own record to check the server's replay of the
+// game against.  The file is removed once the game is over.
+pub struct Journal {
+    file: File,
+    path: PathBuf,
+}
+
+// How the server's replay of a resumed game compares with the journal.
+#[derive(Debug, PartialEq)]
+pub enum Agreement {
+    Same,
+    // The server has moves the journal doesn't, like ones the opponent made after the crash.
+    ServerAhead(usize),
+    // The journal has moves the server never received.
+    JournalAhead(usize),
+    // The first ply where the two disagree.
+    Diverged(usize),
+}
+
+impl Journal {
+    // Opens the game's journal, adding to it if the game is being resumed.
+    pub fn open(directory: &Path, game: &Game) -> io::Result<Self> {
+        fs::create_dir_all(directory)?;
+        let path = path(directory, game.id);
+
+        let mut journal = Self {
+            file: OpenOptions::new().create(true).append(true).open(&path)?,
+            path,
+        };
+
+        if journal.file.metadata()?.len() == 0 {
+            journal.append(&format!("game {} {}", game.id, game.size))?;
+        }
+
+        Ok(journal)
+    }
+
+    pub fn record_move(&mut self, ply: usize, game_move: &GameMove) {
+        self.append_or_warn(&format!("move {ply} {}", game_move.to_ptn()));
+    }
+
+    // The server took back moves, so the journal's record stops at the given ply.
+    pub fn record_undo(&mut self, ply: usize) {
+        self.append_or_warn(&format!("undo {ply}"));
+    }
+
+    pub fn record_time(&mut self, (white, black): (u32, u32)) {
+        self.append_or_warn(&format!("time {white} {black}"));
+    }
+
+    // The game is over, so there's nothing left to resume.
+    pub fn finish(self) {
+        if let Err(error) = fs::remove_file(&self.path) {
+            warn!(%error, path = ?self.path, "Could not remove the game journal.");
+        }
+    }
+
+    // Each line is synced before moving on, so that a crash loses at most the line being written.
+    fn append(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.file, "{line}")?;
+        self.file.sync_data()
+    }
+
+    fn append_or_warn(&mut self, line: &str) {
+        if let Err(error) = self.append(line) {
+            warn!(%error, path = ?self.path, "Could not write to the game journal.");
+        }
+    }
+
+    // The moves recorded in the game's journal, if it has one.
+    pub fn load(directory: &Path, id: u32) -> io::Result<Option<Vec<GameMove>>> {
+        let file = match File::open(path(directory, id)) {
+            Ok(file) => file,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error),
+        };
+
+        let mut moves = Vec::new();
+
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            match line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+                ["game", ..] | ["time", ..] => (),
+                ["move", ply, game_move] => {
+                    let ply = ply.parse::<usize>().map_err(|_| err!("invalid ply"))?;
+                    // Moves are only ever appended in order, but a resumed game can record a
+                    // ply again.
+                    moves.truncate(ply);
+                    moves.push(GameMove::from_ptn(game_move)?);
+                }
+                ["undo", ply] => {
+                    moves.truncate(ply.parse::<usize>().map_err(|_| err!("invalid ply"))?);
+                }
+                // The last line can be cut short by a crash.
+                _ => warn!(line, "Ignoring a line in the game journal."),
+            }
+        }
+
+        Ok(Some(moves))
+    }
+}
+
+fn path(directory: &Path, id: u32) -> PathBuf {
+    directory.join(format!("{id}.journal"))
+}
+
+pub fn compare(server: &[GameMove], journal: &[GameMove]) -> Agreement {
+    if let Some(ply) = server.iter().zip(journal).position(|(s, j)| s != j) {
+        return Agreement::Diverged(ply);
+    }
+
+    match server.len().cmp(&journal.len()) {
+        Ordering::Equal => Agreement::Same,
+        Ordering::Greater => Agreement::ServerAhead(server.len() - journal.len()),
+        Ordering::Less => Agreement::JournalAhead(journal.len() - server.len()),
+    }
+}
+
+// Checks a game the server is resuming against our own record of it.  The server has the final
+// say, but a disagreement is worth knowing about.
+pub fn check_resumed(directory: &Path, game: &Game) {
+    let journal = match Journal::load(directory, game.id) {
+        Ok(Some(moves)) => moves,
+        Ok(None) => return,
+        Err(error) => {
+            warn!(%error, game_id = game.id, "Could not read the game journal.");
+            return;
+        }
+    };
+
+    match compare(&game.moves, &journal) {
+        Agreement::Same => info!(
+            game_id = game.id,
+            "The server's record matches the journal."
+        ),
+        Agreement::ServerAhead(moves) => {
+            info!(
+                game_id = game.id,
+                moves, "The server has moves made since the journal was written."
+            )
+        }
+        Agreement::JournalAhead(moves) => warn!(
+            game_id = game.id,
+            moves, "The server never received the last moves in the journal."
+        ),
+        Agreement::Diverged(ply) => warn!(
+            game_id = game.id,
+            ply,
+            "The server's record of the game disagrees with the journal. Going with the server's."
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn moves(ptn: &str) -> Vec<GameMove> {
+        ptn.split_ascii_whitespace()
+            .map(|m| GameMove::from_ptn(m).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn compare_records() {
+        let journal = moves("a1 e5 c3");
+
+        assert_eq!(compare(&moves("a1 e5 c3"), &journal), Agreement::Same);
+        assert_eq!(
+            compare(&moves("a1 e5 c3 d3"), &journal),
+            Agreement::ServerAhead(1)
+        );
+        assert_eq!(compare(&moves("a1"), &journal), Agreement::JournalAhead(2));
+        assert_eq!(
+            compare(&moves("a1 e4 c3"), &journal),
+            Agreement::Diverged(1)
+        );
+    }
+}
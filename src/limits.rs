@@ -0,0 +1,228 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use clap::Args;
+use tracing::warn;
+
+use super::elo::Record;
+use super::err;
+use super::game::Game;
+
+const DAY: u64 = 24 * 60 * 60;
+
+#[derive(Args, Clone, Debug, Default)]
+pub struct SessionLimits {
+    #[arg(long, requires = "continuous")]
+    pub max_games: Option<u32>,
+    #[arg(long, requires = "continuous")]
+    pub max_games_per_opponent: Option<u32>,
+    #[arg(long, value_name = "SECONDS", requires = "continuous")]
+    pub opponent_cooldown: Option<u64>,
+    // Counted over the last 24 hours, across sessions if there's a state directory.
+    #[arg(long, requires = "continuous")]
+    pub max_daily_games_per_opponent: Option<u32>,
+    #[arg(long, value_name = "DIR", requires = "continuous")]
+    pub state_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Default)]
+pub struct SessionStats {
+    games: u32,
+    opponents: HashMap<String, OpponentRecord>,
+    pub record: Record,
+    pub history: OpponentHistory,
+}
+
+#[derive(Debug)]
+struct OpponentRecord {
+    games: u32,
+    last_finished: Instant,
+}
+
+impl SessionStats {
+    pub fn record_game(&mut self, game: &Game) {
+        self.games += 1;
+        self.record.add(game);
+
+        let record = self
+            .opponents
+            .entry(game.opponent.to_lowercase())
+            .or_insert(OpponentRecord {
+                games: 0,
+                last_finished: Instant::now(),
+            });
+
+        record.games += 1;
+        record.last_finished = Instant::now();
+
+        self.history.record(&game.opponent, unix_time());
+    }
+
+    pub fn games(&self) -> u32 {
+        self.games
+    }
+
+    pub fn finished(&self, limits: &SessionLimits) -> bool {
+        limits.max_games.is_some_and(|m| self.games >= m)
+    }
+
+    pub fn check(&self, limits: &SessionLimits, opponent: &str) -> Result<(), &'static str> {
+        if limits
+            .max_daily_games_per_opponent
+            .is_some_and(|m| self.history.games_since(opponent, unix_time() - DAY) >= m)
+        {
+            return Err("opponent has reached the daily game limit");
+        }
+
+        let Some(record) = self.opponents.get(&opponent.to_lowercase()) else {
+            return Ok(());
+        };
+
+        if limits
+            .max_games_per_opponent
+            .is_some_and(|m| record.games >= m)
+        {
+            return Err("opponent has reached the game limit");
+        }
+
+        if limits
+            .opponent_cooldown
+            .is_some_and(|c| record.last_finished.elapsed() < Duration::from_secs(c))
+        {
+            return Err("opponent is cooling down");
+        }
+
+        Ok(())
+    }
+}
+
+// When each opponent's games of the last day finished, in seconds since the Unix epoch.  With
+// a file, it's loaded at startup and saved after every game.
+#[derive(Debug, Default)]
+pub struct OpponentHistory {
+    path: Option<PathBuf>,
+    games: BTreeMap<String, Vec<u64>>,
+}
+
+impl OpponentHistory {
+    // A missing file is an empty history.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let games = match fs::read_to_string(path) {
+            Ok(contents) => {
+                serde_json::from_str(&contents).map_err(|error| err!(error.to_string()))?
+            }
+            Err(error) if error.kind() == io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => return Err(error),
+        };
+
+        Ok(Self {
+            path: Some(path.to_owned()),
+            games,
+        })
+    }
+
+    fn record(&mut self, opponent: &str, now: u64) {
+        self.games
+            .entry(opponent.to_lowercase())
+            .or_default()
+            .push(now);
+
+        // Nothing older than a day is needed.
+        for times in self.games.values_mut() {
+            times.retain(|&time| time + DAY > now);
+        }
+        self.games.retain(|_, times| !times.is_empty());
+
+        if let Some(path) = &self.path {
+            let saved = serde_json::to_string(&self.games)
+                .map_err(|error| err!(error.to_string()))
+                .and_then(|json| fs::write(path, json));
+            if let Err(error) = saved {
+                warn!(%error, ?path, "Could not save the opponent history.");
+            }
+        }
+    }
+
+    fn games_since(&self, opponent: &str, since: u64) -> u32 {
+        self.games.get(&opponent.to_lowercase()).map_or(0, |times| {
+            times.iter().filter(|&&time| time > since).count() as u32
+        })
+    }
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_limits() {
+        let limits = SessionLimits {
+            max_games: Some(3),
+            max_games_per_opponent: Some(2),
+            ..Default::default()
+        };
+
+        let game = |opponent: &str| {
+            format!("Game Start 7 5 Bot vs {opponent} white 600 0 21 1 0 0")
+                .parse::<Game>()
+                .unwrap()
+        };
+
+        let mut stats = SessionStats::default();
+        assert!(stats.check(&limits, "Alice").is_ok());
+
+        stats.record_game(&game("Alice"));
+        stats.record_game(&game("alice"));
+        assert!(stats.check(&limits, "Alice").is_err());
+        assert!(stats.check(&limits, "Bob").is_ok());
+        assert!(!stats.finished(&limits));
+
+        stats.record_game(&game("Bob"));
+        assert!(stats.finished(&limits));
+
+        let limits = SessionLimits {
+            opponent_cooldown: Some(60),
+            ..Default::default()
+        };
+        assert!(stats.check(&limits, "Bob").is_err());
+        assert!(stats.check(&limits, "Carol").is_ok());
+    }
+
+    #[test]
+    fn opponent_history() {
+        let path = std::env::temp_dir().join(format!("playtak-tei-{}.json", std::process::id()));
+        let now = unix_time();
+
+        let mut history = OpponentHistory::load(&path).unwrap();
+        history.record("Alice", now - DAY);
+        history.record("Alice", now - 60);
+        history.record("alice", now);
+        history.record("Bob", now);
+
+        let history = OpponentHistory::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(history.games_since("ALICE", now - DAY), 2);
+        assert_eq!(history.games_since("Bob", now - DAY), 1);
+
+        let stats = SessionStats {
+            history,
+            ..Default::default()
+        };
+        let limits = SessionLimits {
+            max_daily_games_per_opponent: Some(2),
+            ..Default::default()
+        };
+        assert!(stats.check(&limits, "Alice").is_err());
+        assert!(stats.check(&limits, "Bob").is_ok());
+    }
+}
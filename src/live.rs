@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use tracing::warn;
+
+use super::board::Board;
+use super::crash;
+use super::game::{Game, GameMove};
+
+// How many lines of chat, protocol traffic, and log output are kept.
+const HISTORY: usize = 200;
+
+// A picture of what the client is doing, kept for whatever is showing it live.  Nothing is
+// recorded until it's enabled, so it costs nothing otherwise.
+#[derive(Clone, Debug, Default)]
+pub struct LiveState {
+    pub game: Option<LiveGame>,
+    pub chat: VecDeque<String>,
+    // Lines sent to and received from the server, marked with > and <.
+    pub protocol: VecDeque<String>,
+    // Log output, when it's shown live instead of going to the terminal.
+    #[cfg_attr(not(feature = "tui"), allow(dead_code))]
+    pub log: VecDeque<String>,
+    // Incremented on every change, so that watchers can tell when there's something new.
+    pub version: u64,
+}
+
+#[derive(Clone, Debug)]
+pub struct LiveGame {
+    pub id: u32,
+    pub white: String,
+    pub black: String,
+    pub board: Option<Board>,
+    pub time: (u32, u32),
+    pub moves: Vec<String>,
+    pub result: Option<String>,
+    pub pv: Option<String>,
+    // The engine's evaluation as it reported it, like "cp 35" or "mate 3".
+    pub score: Option<String>,
+    // The engine's evaluation in centipawns at each ply it was known for.
+    pub evals: Vec<(usize, i32)>,
+    // The reply the engine expects to our last move.
+    pub ponder: Option<String>,
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STATE: Mutex<Option<LiveState>> = Mutex::new(None);
+
+// A file holding the PTN of the game in progress, for stream overlays and the like that watch it.
+static PTN_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn write_ptn(path: PathBuf) {
+    *PTN_FILE.lock().unwrap_or_else(|e| e.into_inner()) = Some(path);
+}
+
+pub fn snapshot() -> LiveState {
+    lock().clone().unwrap_or_default()
+}
+
+fn lock() -> MutexGuard<'static, Option<LiveState>> {
+    STATE.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn update(f: impl FnOnce(&mut LiveState)) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut state = lock();
+    let state = state.get_or_insert_with(Default::default);
+    f(state);
+    state.version += 1;
+}
+
+fn push(lines: &mut VecDeque<String>, line: String) {
+    if lines.len() == HISTORY {
+        lines.pop_front();
+    }
+    lines.push_back(line);
+}
+
+pub fn game(game: &Game) {
+    crash::game(game);
+    save_ptn(game);
+
+    update(|state| {
+        let (white, black) = game.white_and_black();
+
+        // The engine's output is kept for as long as it's the same game.
+        let (pv, score, mut evals) = match state.game.take() {
+            Some(live) if live.id == game.id => (live.pv, live.score, live.evals),
+            _ => (None, None, Vec::new()),
+        };
+
+        let ply = game.moves.len();
+        if let Some(centipawns) = score.as_deref().and_then(centipawns) {
+            if evals.last().is_none_or(|&(last, _)| last < ply) {
+                evals.push((ply, centipawns));
+            }
+        }
+
+        state.game = Some(LiveGame {
+            id: game.id,
+            white: white.to_owned(),
+            black: black.to_owned(),
+            board: game.board().ok(),
+            time: game.time,
+            moves: game.ptn_moves(),
+            result: game.result.clone(),
+            pv,
+            score,
+            evals,
+            ponder: game.ponder.as_ref().map(GameMove::to_ptn),
+        });
+    });
+}
+
+// Replaces the PTN file whole, so that nothing watching it sees half a game.
+fn save_ptn(game: &Game) {
+    let Some(path) = PTN_FILE.lock().unwrap_or_else(|e| e.into_inner()).clone() else {
+        return;
+    };
+
+    let partial = path.with_extension("ptn.partial");
+    if let Err(error) =
+        fs::write(&partial, game.to_ptn()).and_then(|()| fs::rename(&partial, &path))
+    {
+        warn!(%error, ?path, "Could not write the game's PTN.");
+    }
+}
+
+// Takes the principal variation and score from an engine's info line.
+pub fn engine_info(info: &[&str]) {
+    update(|state| {
+        let Some(game) = &mut state.game else {
+            return;
+        };
+
+        if let Some(i) = info.iter().position(|&p| p == "pv") {
+            game.pv = Some(info[i + 1..].join(" "));
+        }
+        if let Some(i) = info.iter().position(|&p| p == "score") {
+            game.score = Some(
+                info[i + 1..]
+                    .iter()
+                    .take(2)
+                    .copied()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        }
+    });
+}
+
+// A score like "cp 35" or "mate -3" as centipawns, with mates as large scores.
+fn centipawns(score: &str) -> Option<i32> {
+    match score.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+        ["cp", value] => value.parse().ok(),
+        ["mate", value] => value
+            .parse::<i32>()
+            .ok()
+            .map(|moves| 10000 * moves.signum()),
+        _ => None,
+    }
+}
+
+pub fn received(line: &str) {
+    update(|state| {
+        if ["Tell ", "Told ", "Shout ", "ShoutRoom "]
+            .iter()
+            .any(|p| line.starts_with(p))
+        {
+            push(&mut state.chat, line.to_owned());
+        }
+        push(&mut state.protocol, format!("< {line}"));
+    });
+}
+
+pub fn sent(line: &str) {
+    update(|state| {
+        let line = line.trim_end();
+
+        // Never show the password.
+        let line = match line.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            ["Login", username, _] => format!("Login {username} ********"),
+            _ => line.to_owned(),
+        };
+
+        push(&mut state.protocol, format!("> {line}"));
+    });
+}
+
+#[cfg_attr(not(feature = "tui"), allow(dead_code))]
+pub fn log(output: &str) {
+    update(|state| {
+        for line in output.lines() {
+            push(&mut state.log, line.to_owned());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_centipawns() {
+        assert_eq!(centipawns("cp 35"), Some(35));
+        assert_eq!(centipawns("cp -120"), Some(-120));
+        assert_eq!(centipawns("mate -3"), Some(-10000));
+        assert_eq!(centipawns("mate"), None);
+        assert_eq!(centipawns("lowerbound 5"), None);
+    }
+}
@@ -0,0 +1,59 @@
+use std::env;
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{self, Write};
+use std::process;
+
+// Held for the life of the process; the OS releases the lock if we exit or crash.
+#[derive(Debug)]
+pub struct AccountLock {
+    _file: File,
+}
+
+impl AccountLock {
+    pub fn acquire(username: &str) -> io::Result<Self> {
+        let path = env::temp_dir().join(format!("playtak-tei-{}.lock", username.to_lowercase()));
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)?;
+
+        match file.try_lock() {
+            Ok(()) => (),
+            Err(TryLockError::WouldBlock) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "account is locked by another instance",
+                ))
+            }
+            Err(TryLockError::Error(error)) => return Err(error),
+        }
+
+        file.set_len(0)?;
+        writeln!(file, "{}", process::id())?;
+
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_lock_fails() {
+        let username = format!("LockTest{}", process::id());
+
+        let lock = AccountLock::acquire(&username).unwrap();
+        assert_eq!(
+            AccountLock::acquire(&username.to_uppercase())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::AddrInUse
+        );
+
+        drop(lock);
+        assert!(AccountLock::acquire(&username).is_ok());
+    }
+}
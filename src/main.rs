@@ -1,35 +1,149 @@
+use std::collections::{HashMap, HashSet};
 use std::env;
-use std::io;
-use std::time::Duration;
-
-use async_std::io::{BufReader, WriteExt};
-use async_std::net::TcpStream;
-use async_std::prelude::*;
-use async_std::process::{Command, Stdio};
-use async_std::task;
-use clap::{arg, command, Args, Parser};
-use futures::{select, AsyncWrite, FutureExt};
-use tracing::{debug, error, info};
-
-use self::game::{Game, GameMove};
-use self::option::{validate_and_set_option, SpinOption};
-use self::seek::{capstones_for_size, flatstones_for_size, Seek};
-
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use clap::{Args, Parser, ValueEnum};
+use futures::{future, select, AsyncWrite, AsyncWriteExt, FutureExt, Stream, StreamExt};
+use tracing::{debug, error, info, info_span, instrument, warn, Instrument, Span};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::EnvFilter;
+
+use self::analysis::{run_continue, run_convert};
+use self::announce::{Announce, Announcer};
+use self::chat::{ChatKind, ChatMessage, ChatModeration, Moderation};
+use self::clock::{ClockDrift, ClockLog};
+use self::config::Config;
+use self::control::{next_command, Command, Control};
+use self::doctor::run_doctor;
+use self::engine::{Engine, EngineSettings};
+use self::etiquette::{BotMode, Etiquette, GameSlot};
+use self::filter::{OpponentFilter, SeekFilter};
+use self::game::{Game, GameMove, ListedGame};
+use self::interactive::run_interactive;
+use self::journal::Journal;
+use self::limits::{OpponentHistory, SessionLimits, SessionStats};
+use self::lock::AccountLock;
+use self::message::{read_message, GameUpdate, Message};
+use self::milestone::{MilestoneChat, Milestones};
+use self::notify::{Notification, Notifiers};
+use self::offers::Offers;
+use self::provider::{Human, MoveProvider};
+use self::rematch::{RematchSettings, Rematches};
+use self::schedule::{open_window, Window};
+use self::seek::{Seek, SeekColor, REMOVE_SEEK};
+use self::session::{ConnectionSettings, Session};
+use self::status::{Status, StatusFile};
+use self::suite::run_suite;
+use self::transcript::{Transcribed, Transcript};
+use self::web::run_dashboard;
+
+mod analysis;
+mod announce;
+mod api;
+mod board;
+mod chat;
+mod clock;
+mod config;
+mod console;
+mod container;
+mod control;
+mod crash;
+mod credentials;
+mod doctor;
+mod elo;
+mod engine;
+mod engines;
+mod etiquette;
+mod filter;
 mod game;
+mod http;
+mod interactive;
+mod journal;
+mod limits;
+mod live;
+mod lock;
+mod message;
+mod milestone;
+mod notify;
+mod offers;
 mod option;
+mod provider;
+mod rematch;
+mod resources;
+mod runtime;
+mod schedule;
+#[cfg(test)]
+mod scripted;
 mod seek;
+mod session;
+mod settle;
+mod status;
+mod suite;
+mod trace;
+mod transcript;
+#[cfg(feature = "tui")]
+mod tui;
+mod web;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(10);
+// How many times in a row the client reconnects to resynchronize a game before giving up.
+const MAX_RESYNCS: u32 = 2;
+// How long the server has to start the game after a seek is accepted.
+const ACCEPT_TIMEOUT: Duration = Duration::from_secs(10);
+// How long after a game to wait for the server to record it.
+const VERIFY_DELAY: Duration = Duration::from_secs(10);
+// The board to warm the engine up on when there's no telling what the first game will be.
+const WARMUP_SIZE: u32 = 6;
 
 #[derive(Args, Clone, Debug)]
 struct Login {
     #[arg(short = 't', long = "token", group = "login")]
     guest_token: Option<String>,
-    #[arg(short, long, group = "login", requires = "password")]
+    #[arg(short, long, group = "login", env = "PLAYTAK_USERNAME")]
     username: Option<String>,
-    #[arg(short, long, requires = "username")]
+    #[arg(short, long, env = "PLAYTAK_PASSWORD", hide_env_values = true)]
     password: Option<String>,
+    #[cfg(feature = "keyring")]
+    #[arg(long, action, requires = "username")]
+    keyring: bool,
+    #[command(flatten)]
+    connection: ConnectionSettings,
 }
 
 impl Login {
+    // Settles on the password before anything connects, so that it's only asked for or looked
+    // up once.  A password of "-" is asked for on the terminal.
+    fn resolve_password(&mut self) -> io::Result<()> {
+        let Some(username) = &self.username else {
+            return Ok(());
+        };
+
+        if self.password.as_deref() == Some("-") {
+            self.password = Some(credentials::prompt_password(username)?);
+        }
+
+        #[cfg(feature = "keyring")]
+        if self.keyring {
+            match &self.password {
+                Some(password) => credentials::save_password(username, password)?,
+                None => self.password = Some(credentials::load_password(username)?),
+            }
+        }
+
+        if self.password.is_none() {
+            return Err(err!(format!("no password given for {username}")));
+        }
+
+        Ok(())
+    }
+
     fn to_login_string(&self) -> String {
         format!(
             "Login {}\n",
@@ -53,15 +167,24 @@ impl Login {
 struct ListCommand {
     #[command(flatten)]
     login: Login,
+    #[arg(long, action)]
+    mine_only: bool,
+    #[arg(long, action, conflicts_with = "mine_only")]
+    games: bool,
+    #[arg(long, action)]
+    api: bool,
 }
 
 #[derive(Args, Debug)]
-#[group(required = true)]
+#[group(required = true, multiple = false)]
 struct AcceptInfo {
     #[arg(short, long = "seek")]
     seek_id: Option<u32>,
     #[arg(short, long)]
     opponent: Option<String>,
+    // Keeps accepting seeks, so like seek --continuous, the session limits apply.
+    #[arg(long, id = "continuous", action)]
+    auto: bool,
 }
 
 #[derive(Args, Debug)]
@@ -70,7 +193,46 @@ struct AcceptCommand {
     login: Login,
     #[command(flatten)]
     accept: AcceptInfo,
-    #[arg(required = true, num_args = 1.., trailing_var_arg = true)]
+    #[command(flatten)]
+    filter: OpponentFilter,
+    #[command(flatten)]
+    seek_filter: SeekFilter,
+    #[command(flatten)]
+    limits: SessionLimits,
+    #[arg(long, action)]
+    human: bool,
+    #[cfg(feature = "tui")]
+    #[arg(long, action, conflicts_with = "human")]
+    tui: bool,
+    #[arg(long, value_name = "ADDRESS")]
+    http: Option<String>,
+    #[arg(long, value_name = "ADDRESS")]
+    control: Option<String>,
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        requires = "control",
+        env = "PLAYTAK_TEI_CONTROL_TOKEN",
+        hide_env_values = true
+    )]
+    control_token: Option<String>,
+    #[arg(long, action)]
+    dry_run: bool,
+    #[command(flatten)]
+    engine_settings: EngineSettings,
+    #[arg(long, value_name = "PATH")]
+    ptn_dir: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    live_ptn: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    journal_dir: Option<PathBuf>,
+    #[arg(long, value_name = "DIR")]
+    transcript: Option<PathBuf>,
+    #[arg(
+        required_unless_present_any = ["human", "engine", "engine_docker"],
+        num_args = 1..,
+        trailing_var_arg = true
+    )]
     engine_arguments: Vec<String>,
 }
 
@@ -80,400 +242,2566 @@ struct SeekCommand {
     login: Login,
     #[command(flatten)]
     seek: Seek,
-    #[arg(required = true, num_args = 1.., trailing_var_arg = true)]
+    #[command(flatten)]
+    filter: OpponentFilter,
+    #[arg(long, action)]
+    continuous: bool,
+    #[arg(long, action, requires = "continuous")]
+    alternate_colors: bool,
+    #[command(flatten)]
+    limits: SessionLimits,
+    #[command(flatten)]
+    bot_mode: BotMode,
+    #[command(flatten)]
+    announce: Announce,
+    #[command(flatten)]
+    rematch: RematchSettings,
+    #[command(flatten)]
+    moderation: ChatModeration,
+    #[command(flatten)]
+    milestones: MilestoneChat,
+    #[arg(long, action)]
+    daemon: bool,
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    status_file: Option<PathBuf>,
+    #[arg(long, value_name = "SECONDS", default_value_t = 300)]
+    status_interval: u64,
+    #[arg(long, value_name = "PATH")]
+    ptn_dir: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    live_ptn: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    journal_dir: Option<PathBuf>,
+    #[arg(long, value_name = "DIR")]
+    transcript: Option<PathBuf>,
+    #[arg(long, action, conflicts_with = "daemon")]
+    human: bool,
+    #[cfg(feature = "tui")]
+    #[arg(long, action, conflicts_with_all = ["human", "daemon"])]
+    tui: bool,
+    #[arg(long, value_name = "ADDRESS")]
+    http: Option<String>,
+    #[arg(long, value_name = "ADDRESS")]
+    control: Option<String>,
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        requires = "control",
+        env = "PLAYTAK_TEI_CONTROL_TOKEN",
+        hide_env_values = true
+    )]
+    control_token: Option<String>,
+    #[arg(long, action)]
+    dry_run: bool,
+    #[command(flatten)]
+    engine_settings: EngineSettings,
+    #[arg(
+        required_unless_present_any = ["human", "engine", "engine_docker"],
+        num_args = 1..,
+        trailing_var_arg = true
+    )]
+    engine_arguments: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct InteractiveCommand {
+    #[command(flatten)]
+    login: Login,
+    #[command(flatten)]
+    engine_settings: EngineSettings,
+    #[arg(
+        required_unless_present_any = ["engine", "engine_docker"],
+        num_args = 1..,
+        trailing_var_arg = true
+    )]
     engine_arguments: Vec<String>,
 }
 
+#[derive(Args, Debug)]
+struct ResumeCommand {
+    #[command(flatten)]
+    login: Login,
+    #[arg(long, value_name = "PATH")]
+    ptn_dir: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    live_ptn: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    journal_dir: Option<PathBuf>,
+    #[arg(long, value_name = "DIR")]
+    transcript: Option<PathBuf>,
+    #[command(flatten)]
+    engine_settings: EngineSettings,
+    #[arg(
+        required_unless_present_any = ["engine", "engine_docker"],
+        num_args = 1..,
+        trailing_var_arg = true
+    )]
+    engine_arguments: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct ContinueCommand {
+    #[arg(long, value_name = "PATH", required_unless_present = "tps")]
+    ptn: Option<PathBuf>,
+    #[arg(long, conflicts_with = "ptn")]
+    tps: Option<String>,
+    #[arg(long, requires = "ptn")]
+    ply: Option<usize>,
+    #[arg(short = 'k', long, allow_negative_numbers = true)]
+    half_komi: Option<i32>,
+    #[arg(long, value_name = "MS", default_value_t = 5000)]
+    movetime: u64,
+    #[command(flatten)]
+    engine_settings: EngineSettings,
+    #[arg(
+        required_unless_present_any = ["engine", "engine_docker"],
+        num_args = 1..,
+        trailing_var_arg = true
+    )]
+    engine_arguments: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct SuiteCommand {
+    #[arg(long, value_name = "PATH")]
+    suite: PathBuf,
+    #[arg(long, value_name = "MS", default_value_t = 5000)]
+    movetime: u64,
+    #[arg(long, conflicts_with = "movetime")]
+    depth: Option<u32>,
+    #[command(flatten)]
+    engine_settings: EngineSettings,
+    #[arg(
+        required_unless_present_any = ["engine", "engine_docker"],
+        num_args = 1..,
+        trailing_var_arg = true
+    )]
+    engine_arguments: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct DoctorCommand {
+    #[command(flatten)]
+    login: Login,
+    #[command(flatten)]
+    engine_settings: EngineSettings,
+    #[arg(long, value_name = "DIR")]
+    state_dir: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    ptn_dir: Option<PathBuf>,
+    #[arg(long, value_name = "PATH")]
+    journal_dir: Option<PathBuf>,
+    #[arg(long, value_name = "DIR")]
+    transcript: Option<PathBuf>,
+    #[arg(num_args = 0.., trailing_var_arg = true)]
+    engine_arguments: Vec<String>,
+}
+
+#[derive(Args, Debug)]
+struct ConvertCommand {
+    #[arg(long, value_name = "PATH", required_unless_present = "tps")]
+    ptn: Option<PathBuf>,
+    #[arg(long, conflicts_with = "ptn")]
+    tps: Option<String>,
+    #[arg(long, requires = "ptn")]
+    ply: Option<usize>,
+}
+
+#[derive(Args, Debug)]
+struct MultiCommand {
+    #[arg(long, value_name = "PATH")]
+    config: PathBuf,
+    #[arg(long, action)]
+    daemon: bool,
+}
+
+#[derive(Debug, Parser)]
+struct Cli {
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Pretty)]
+    log_format: LogFormat,
+    #[arg(long, global = true, action)]
+    no_color: bool,
+    #[arg(
+        long,
+        global = true,
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true
+    )]
+    trace: Option<Option<PathBuf>>,
+    #[arg(long, global = true, action)]
+    resign_on_panic: bool,
+    #[command(subcommand)]
+    command: ArgCommand,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum LogFormat {
+    Pretty,
+    // One JSON object per line, for log aggregation.
+    Json,
+    // Games and seeks written for people to read, with only warnings and errors logged.
+    Console,
+}
+
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Parser)]
 enum ArgCommand {
     List(ListCommand),
     Accept(AcceptCommand),
     Seek(SeekCommand),
+    Multi(MultiCommand),
+    Interactive(InteractiveCommand),
+    Resume(ResumeCommand),
+    Continue(ContinueCommand),
+    Convert(ConvertCommand),
+    Suite(SuiteCommand),
+    Doctor(DoctorCommand),
 }
 
-fn main() {
-    let args = ArgCommand::parse();
-
-    tracing_subscriber::fmt::init();
+impl ArgCommand {
+    fn login(&self) -> &Login {
+        match self {
+            ArgCommand::List(ListCommand { login, .. })
+            | ArgCommand::Accept(AcceptCommand { login, .. })
+            | ArgCommand::Seek(SeekCommand { login, .. })
+            | ArgCommand::Interactive(InteractiveCommand { login, .. })
+            | ArgCommand::Resume(ResumeCommand { login, .. })
+            | ArgCommand::Doctor(DoctorCommand { login, .. }) => login,
+            ArgCommand::Multi(_)
+            | ArgCommand::Continue(_)
+            | ArgCommand::Convert(_)
+            | ArgCommand::Suite(_) => {
+                unreachable!()
+            }
+        }
+    }
 
-    // Limit the number of threads async-std tries to spawn; we don't need that many.
-    if env::var("ASYNC_STD_THREAD_COUNT").is_err() {
-        env::set_var("ASYNC_STD_THREAD_COUNT", "1");
+    // Listing from the API doesn't log in, so its login needs no password.
+    fn login_mut(&mut self) -> Option<&mut Login> {
+        match self {
+            ArgCommand::List(ListCommand { api: true, .. }) => None,
+            ArgCommand::List(ListCommand { login, .. })
+            | ArgCommand::Accept(AcceptCommand { login, .. })
+            | ArgCommand::Seek(SeekCommand { login, .. })
+            | ArgCommand::Interactive(InteractiveCommand { login, .. })
+            | ArgCommand::Resume(ResumeCommand { login, .. })
+            | ArgCommand::Doctor(DoctorCommand { login, .. }) => Some(login),
+            ArgCommand::Multi(_)
+            | ArgCommand::Continue(_)
+            | ArgCommand::Convert(_)
+            | ArgCommand::Suite(_) => None,
+        }
     }
 
-    task::block_on(main_inner(args)).ok();
-}
+    fn engine_arguments(&self) -> &[String] {
+        match self {
+            ArgCommand::Accept(AcceptCommand {
+                engine_arguments, ..
+            })
+            | ArgCommand::Seek(SeekCommand {
+                engine_arguments, ..
+            })
+            | ArgCommand::Interactive(InteractiveCommand {
+                engine_arguments, ..
+            })
+            | ArgCommand::Resume(ResumeCommand {
+                engine_arguments, ..
+            }) => engine_arguments,
+            _ => unreachable!(),
+        }
+    }
 
-macro_rules! assert_response {
-    ($reader:expr, $value:expr) => {
-        let line = read($reader).await?;
-        if line != $value {
-            error!(received = ?line, expected = $value, "Unexpected value.");
-            return Err(io::Error::from(io::ErrorKind::InvalidData));
+    fn engine_settings(&self) -> &EngineSettings {
+        match self {
+            ArgCommand::Accept(AcceptCommand {
+                engine_settings, ..
+            })
+            | ArgCommand::Seek(SeekCommand {
+                engine_settings, ..
+            })
+            | ArgCommand::Interactive(InteractiveCommand {
+                engine_settings, ..
+            })
+            | ArgCommand::Resume(ResumeCommand {
+                engine_settings, ..
+            }) => engine_settings,
+            _ => unreachable!(),
         }
-    };
-}
+    }
 
-macro_rules! err {
-    () => {
-        io::Error::from(io::ErrorKind::Other)
-    };
-    ($message:expr) => {
-        io::Error::new(io::ErrorKind::Other, $message)
-    };
-}
-pub(crate) use err;
+    fn ptn_dir(&self) -> Option<&Path> {
+        match self {
+            ArgCommand::Accept(AcceptCommand { ptn_dir, .. })
+            | ArgCommand::Seek(SeekCommand { ptn_dir, .. })
+            | ArgCommand::Resume(ResumeCommand { ptn_dir, .. }) => ptn_dir.as_deref(),
+            _ => None,
+        }
+    }
 
-trait Writer: AsyncWrite + WriteExt + Unpin {}
-impl<T> Writer for T where T: AsyncWrite + WriteExt + Unpin {}
+    fn live_ptn(&self) -> Option<&Path> {
+        match self {
+            ArgCommand::Accept(AcceptCommand { live_ptn, .. })
+            | ArgCommand::Seek(SeekCommand { live_ptn, .. })
+            | ArgCommand::Resume(ResumeCommand { live_ptn, .. }) => live_ptn.as_deref(),
+            _ => None,
+        }
+    }
 
-async fn write(mut writer: impl Writer, value: impl AsRef<[u8]>) -> io::Result<()> {
-    if let Ok(value) = std::str::from_utf8(value.as_ref()) {
-        debug!(?value, "Sending");
+    fn transcript_dir(&self) -> Option<&Path> {
+        match self {
+            ArgCommand::Accept(AcceptCommand { transcript, .. })
+            | ArgCommand::Seek(SeekCommand { transcript, .. })
+            | ArgCommand::Resume(ResumeCommand { transcript, .. }) => transcript.as_deref(),
+            _ => None,
+        }
     }
 
-    let result = writer.write_all(value.as_ref()).await;
+    fn journal_dir(&self) -> Option<&Path> {
+        match self {
+            ArgCommand::Accept(AcceptCommand { journal_dir, .. })
+            | ArgCommand::Seek(SeekCommand { journal_dir, .. })
+            | ArgCommand::Resume(ResumeCommand { journal_dir, .. }) => journal_dir.as_deref(),
+            _ => None,
+        }
+    }
 
-    if let Err(error) = &result {
-        error!(%error, "Could not write to stream.");
+    fn milestones(&self) -> Option<Milestones> {
+        match self {
+            ArgCommand::Seek(SeekCommand { milestones, .. }) => Milestones::new(milestones),
+            _ => None,
+        }
     }
 
-    result
-}
+    fn dry_run(&self) -> bool {
+        matches!(
+            self,
+            ArgCommand::Accept(AcceptCommand { dry_run: true, .. })
+                | ArgCommand::Seek(SeekCommand { dry_run: true, .. })
+        )
+    }
 
-trait Reader: Stream<Item = io::Result<String>> + Unpin {}
-impl<T> Reader for T where T: Stream<Item = io::Result<String>> + Unpin {}
+    fn human(&self) -> bool {
+        matches!(
+            self,
+            ArgCommand::Accept(AcceptCommand { human: true, .. })
+                | ArgCommand::Seek(SeekCommand { human: true, .. })
+        )
+    }
 
-async fn read(mut reader: impl Reader) -> io::Result<String> {
-    let result = if let Some(next) = reader.next().await {
-        next
-    } else {
-        error!("Stream closed unexpectedly.");
-        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
-    };
+    fn http(&self) -> Option<&str> {
+        match self {
+            ArgCommand::Accept(AcceptCommand { http, .. })
+            | ArgCommand::Seek(SeekCommand { http, .. }) => http.as_deref(),
+            _ => None,
+        }
+    }
 
-    if let Err(error) = &result {
-        error!(%error, "Could not read from stream.");
+    fn control(&self) -> Option<(&str, Option<&str>)> {
+        match self {
+            ArgCommand::Accept(AcceptCommand {
+                control,
+                control_token,
+                ..
+            })
+            | ArgCommand::Seek(SeekCommand {
+                control,
+                control_token,
+                ..
+            }) => control
+                .as_deref()
+                .map(|control| (control, control_token.as_deref())),
+            _ => None,
+        }
     }
 
-    if let Ok(line) = &result {
-        debug!(?line, "Received");
+    #[cfg(feature = "tui")]
+    fn tui(&self) -> bool {
+        matches!(
+            self,
+            ArgCommand::Accept(AcceptCommand { tui: true, .. })
+                | ArgCommand::Seek(SeekCommand { tui: true, .. })
+        )
     }
+}
 
-    result
+// Settings and bookkeeping that outlive a single connection to the server.
+#[derive(Debug, Default)]
+struct BotState {
+    seek: Option<Seek>,
+    filter: OpponentFilter,
+    seek_filter: SeekFilter,
+    limits: SessionLimits,
+    stats: SessionStats,
+    status_file: Option<StatusFile>,
+    reload: Arc<AtomicBool>,
+    resyncs: u32,
+    control: Option<Control>,
+    notifiers: Notifiers,
+    // When the bot is available for games, if it isn't always, and which window is open.
+    schedule: Vec<Window>,
+    window: Option<usize>,
+    etiquette: Etiquette,
+    // Whether the seek is posted, and whether it was withdrawn until asked for again.
+    posted: bool,
+    paused: bool,
+    // Whether posting the seek is waiting on the minimum interval between seeks.
+    delayed: bool,
+    announcer: Announcer,
+    rematches: Rematches,
+    moderation: Moderation,
+    // The name we're logged in as, and how many players the server last said were online.
+    name: String,
+    online: Option<u32>,
+    // The check of the last game against the server's record, which is seen through before
+    // exiting.
+    verification: Option<runtime::JoinHandle<()>>,
 }
 
-async fn main_inner(args: ArgCommand) -> io::Result<()> {
-    let (mut playtak_writer, mut playtak_reader) =
-        match TcpStream::connect("playtak.com:10000").await {
-            Ok(stream) => {
-                info!("Connected to PlayTak.com.");
-                (stream.clone(), BufReader::new(stream).lines().fuse())
-            }
-            Err(error) => {
-                error!(%error, "Could not connect to PlayTak.com.");
-                return Err(error);
+impl BotState {
+    fn new(args: &ArgCommand) -> io::Result<Self> {
+        let mut state = Self::default();
+
+        if let ArgCommand::Seek(SeekCommand {
+            daemon,
+            status_file,
+            bot_mode,
+            announce,
+            rematch,
+            moderation,
+            ..
+        }) = args
+        {
+            state.status_file = status_file.clone().map(StatusFile::new);
+            state.etiquette = Etiquette::new(bot_mode.clone());
+            state.announcer = Announcer::new(announce.clone());
+            state.rematches = Rematches::new(rematch.clone());
+            state.moderation = Moderation::new(moderation.clone());
+
+            #[cfg(unix)]
+            if *daemon {
+                signal_hook::flag::register(signal_hook::consts::SIGHUP, state.reload.clone())?;
             }
-        };
-
-    assert_response!(&mut playtak_reader, "Welcome!");
-    assert_response!(&mut playtak_reader, "Login or Register");
-
-    write(&mut playtak_writer, "Client playtak-tei\n").await?;
+        }
 
-    assert_response!(&mut playtak_reader, "OK");
+        state.configure(args)?;
 
-    debug!("Client acknowledged.");
+        // Each account keeps its own history, so that accounts can share a directory.
+        if let Some(directory) = &state.limits.state_dir {
+            let name = args.login().username.as_deref().unwrap_or("guest");
+            let path = directory.join(format!("opponents-{}.json", name.to_lowercase()));
+            state.stats.history = OpponentHistory::load(&path)?;
+        }
 
-    let login_name = match &args {
-        ArgCommand::List(ListCommand { login })
-        | ArgCommand::Accept(AcceptCommand { login, .. })
-        | ArgCommand::Seek(SeekCommand { login, .. }) => {
-            write(&mut playtak_writer, login.to_login_string()).await?;
+        Ok(state)
+    }
 
-            let response = read(&mut playtak_reader).await?;
-            if response == "Authentication failure" {
-                error!("Could not authenticate. Are the username and password correct?");
-                return Err(err!());
-            } else if response.starts_with("Welcome") {
-                response
-                    .split_ascii_whitespace()
-                    .nth(1)
-                    .and_then(|n| n.strip_suffix('!'))
-                    .map(|n| n.to_owned())
-                    .expect("could not parse login name")
-            } else {
-                error!("Could not log in.");
-                return Err(err!());
-            }
-        }
-    };
+    fn configure(&mut self, args: &ArgCommand) -> io::Result<()> {
+        if let ArgCommand::Seek(SeekCommand {
+            seek,
+            filter,
+            limits,
+            config,
+            ..
+        }) = args
+        {
+            let (mut seek, mut filter, mut limits) = (seek.clone(), filter.clone(), limits.clone());
+            let mut notifiers = Notifiers::default();
+            let (mut schedule, mut window) = (Vec::new(), None);
+
+            if let Some(path) = config {
+                let config = Config::load(path)?;
+                config.seek.apply(&mut seek, &mut filter, &mut limits);
+                notifiers = Notifiers::new(&config.notifiers);
+
+                let username = args.login().username.as_deref();
+                let account = username.and_then(|u| config.account(u));
+                if let Some(account) = account {
+                    account.seek.apply(&mut seek, &mut filter, &mut limits);
+                }
 
-    info!("Logged in as {login_name}.");
+                if let Some(preset) = &seek.preset {
+                    let preset = config.preset(preset)?;
+                    preset.apply(&mut seek, &mut filter, &mut limits);
+                }
 
-    let mut seeks = Vec::new();
-    loop {
-        let input = read(&mut playtak_reader).await?;
-
-        // Read only until the server is done sending seeks.
-        if input.starts_with("Seek new") {
-            seeks.push(input.parse::<Seek>().map_err(|error| err!(error))?);
-        } else if input.starts_with("Game Start") {
-            info!("Resuming game.");
-
-            let mut game = input.parse::<Game>().map_err(|error| err!(error))?;
-
-            'resume: loop {
-                let line = read(&mut playtak_reader).await?;
-
-                if line != "Message Your game is resumed" {
-                    let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
-
-                    if parts[1] == "P" || parts[1] == "M" {
-                        game.moves.push(GameMove::from_playtak(&line)?);
-                    } else if parts[1] == "Time" {
-                        game.time = (
-                            parts[2]
-                                .parse::<u32>()
-                                .map_err(|_| err!("could not parse white time"))?,
-                            parts[3]
-                                .parse::<u32>()
-                                .map_err(|_| err!("could not parse black time"))?,
-                        );
-                    }
-                } else {
-                    break 'resume;
+                window = open_window(&config.schedule, SystemTime::now());
+                if let Some(window) = window {
+                    config.schedule[window]
+                        .seek
+                        .apply(&mut seek, &mut filter, &mut limits);
                 }
+                schedule = config.schedule;
+            } else if seek.preset.is_some() {
+                return Err(err!("presets are read from the file given with --config"));
             }
 
-            let (engine_writer, engine_reader) = initialize_engine(&args, &game).await?;
+            // A single allowed opponent can be enforced by the server with a directed seek.
+            if let (None, [player]) = (&seek.opponent, filter.only.as_slice()) {
+                seek.opponent = Some(player.clone());
+            }
 
-            return run_game(
-                game,
-                (engine_writer, engine_reader),
-                (playtak_writer, playtak_reader),
-            )
-            .await;
-        } else {
-            break;
+            self.seek = Some(seek);
+            self.filter = filter;
+            self.limits = limits;
+            self.notifiers = notifiers;
+            self.schedule = schedule;
+            self.window = window;
+        } else if let ArgCommand::Accept(AcceptCommand {
+            filter,
+            seek_filter,
+            limits,
+            ..
+        }) = args
+        {
+            self.filter = filter.clone();
+            self.seek_filter = seek_filter.clone();
+            self.limits = limits.clone();
         }
-    }
 
-    if matches!(args, ArgCommand::List(_)) {
-        println!("Available seeks:\n");
+        Ok(())
+    }
 
-        for seek in seeks {
-            println!("{seek}\n");
+    // Returns true if a reload was requested and the configuration was reloaded successfully.
+    fn reload(&mut self, args: &ArgCommand) -> bool {
+        if !self.reload.swap(false, Ordering::Relaxed) {
+            return false;
         }
 
-        return write(&mut playtak_writer, "quit\n").await;
+        match self.configure(args) {
+            Ok(()) => {
+                info!("Reloaded configuration.");
+                true
+            }
+            Err(error) => {
+                error!(%error, "Could not reload configuration. Keeping the previous settings.");
+                false
+            }
+        }
     }
 
-    task::spawn(ping(playtak_writer.clone()));
+    // Returns true if the seek changed, either because the configuration was reloaded or
+    // because a scheduled window opened or closed.
+    fn update_seek(&mut self, args: &ArgCommand) -> bool {
+        if self.reload(args) {
+            return true;
+        }
 
-    // Post or accept the seek.
-    match &args {
-        ArgCommand::Accept(AcceptCommand {
-            accept: AcceptInfo { seek_id, opponent },
-            ..
-        }) => {
-            if let Some(seek_id) = seek_id {
-                info!("Accepting seek {seek_id}.");
-                write(&mut playtak_writer, format!("Accept {seek_id}\n")).await?;
-            } else if let Some(opponent) = opponent {
-                if let Some(seek) = seeks.iter().find(|s| s.player.as_ref() == Some(opponent)) {
-                    let seek_id = seek.id.unwrap();
-                    info!(id = seek_id, "Accepting seek from {opponent}.");
-                    write(&mut playtak_writer, format!("Accept {seek_id}\n")).await?;
-                } else {
-                    error!("Cannot find seek from {opponent}.");
-                    return Err(err!());
-                }
-            }
+        let window = open_window(&self.schedule, SystemTime::now());
+        if window == self.window {
+            return false;
         }
-        ArgCommand::Seek(SeekCommand { seek, .. }) => {
-            info!("Posting seek.");
-            write(&mut playtak_writer, seek.to_seek_string()).await?;
+
+        match self.configure(args) {
+            Ok(()) => match self.window {
+                Some(_) => info!("A scheduled window opened."),
+                None => info!("The scheduled window closed."),
+            },
+            Err(error) => {
+                error!(%error, "Could not reload configuration. Keeping the previous settings.");
+                self.window = window;
+            }
         }
-        _ => unreachable!(),
+
+        true
     }
 
-    let game = loop {
-        let line = read(&mut playtak_reader).await?;
+    // Why the seek shouldn't be posted right now, if there's a reason.
+    fn seek_hold(&self) -> Option<&'static str> {
+        let seek = self.seek.as_ref()?;
 
-        if line == "NOK" {
-            error!("Could not accept or post seek.");
-            return Err(err!());
-        } else if line.starts_with("Game Start") {
-            break line.parse::<Game>().map_err(|error| err!(error))?;
+        if self.paused {
+            Some("the seek was withdrawn")
+        } else if !self.schedule.is_empty() && self.window.is_none() {
+            Some("no scheduled window is open")
+        } else {
+            self.etiquette.allows_seek(seek).err()
         }
-    };
-
-    let (engine_writer, engine_reader) = initialize_engine(&args, &game).await?;
-
-    run_game(
-        game,
-        (engine_writer, engine_reader),
-        (playtak_writer, playtak_reader),
-    )
-    .await
-}
+    }
 
-async fn ping(mut writer: TcpStream) -> io::Result<()> {
-    loop {
-        task::sleep(Duration::from_secs(30)).await;
-        write(&mut writer, "PING\n").await?;
+    // Whether accept --auto should take the seek: it's someone else's, open to us, and allowed
+    // by the filters and the session limits.
+    async fn wants_seek(&self, args: &ArgCommand, seek: &Seek) -> bool {
+        let Some(player) = &seek.player else {
+            return false;
+        };
+        let open = seek
+            .opponent
+            .as_ref()
+            .is_none_or(|o| o.eq_ignore_ascii_case(&self.name));
+
+        open && !player.eq_ignore_ascii_case(&self.name)
+            && self.filter.allows(player)
+            && self.seek_filter.allows(seek)
+            && self.stats.check(&self.limits, player).is_ok()
+            && self
+                .filter
+                .check_rating(&args.login().connection.api_url, player)
+                .await
+                .is_ok()
     }
-}
 
-async fn initialize_engine(
-    args: &ArgCommand,
-    game: &Game,
-) -> io::Result<(impl Writer, impl Reader)> {
-    let (mut engine_writer, mut engine_reader) = {
-        let (engine, arguments) = match &args {
+    // Accepts the first of the seeks that accept --auto wants, if any, returning its id and when
+    // it was accepted.
+    async fn accept_any(
+        &self,
+        mut writer: impl Writer,
+        args: &ArgCommand,
+        seeks: &[Seek],
+        engine: Option<&Engine>,
+    ) -> io::Result<Option<(u32, Instant)>> {
+        if !matches!(
+            args,
             ArgCommand::Accept(AcceptCommand {
-                engine_arguments, ..
+                accept: AcceptInfo { auto: true, .. },
+                ..
             })
-            | ArgCommand::Seek(SeekCommand {
-                engine_arguments, ..
-            }) => (engine_arguments[0].as_str(), &engine_arguments[1..]),
-            _ => unreachable!(),
-        };
-
-        let mut child = Command::new(engine)
-            .args(arguments)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
-            .spawn()?;
-
-        (
-            child.stdin.take().unwrap(),
-            BufReader::new(child.stdout.take().unwrap()).lines().fuse(),
-        )
-    };
+        ) {
+            return Ok(None);
+        }
 
-    write(&mut engine_writer, "tei\n").await?;
+        let playable = seeks
+            .iter()
+            .filter(|seek| engine.is_none_or(|engine| engine.check_seek(seek).is_ok()));
+        for seek in playable {
+            if let (Some(seek_id), true) = (seek.id, self.wants_seek(args, seek).await) {
+                let player = seek.player.as_deref().unwrap_or_default();
+                info!(seek_id, "Accepting seek from {player}.");
+                write(&mut writer, format!("Accept {seek_id}\n")).await?;
+                return Ok(Some((seek_id, Instant::now())));
+            }
+        }
 
-    let mut engine_name = "TEI engine".to_owned();
-    let mut engine_options = Vec::new();
-    loop {
-        let line = read(&mut engine_reader).await?;
-
-        if line.starts_with("id name") {
-            engine_name = line.strip_prefix("id name ").unwrap().to_owned();
-        } else if line.starts_with("option") && line.contains("type spin") {
-            engine_options.push(line.parse::<SpinOption>().map_err(|error| err!(error))?);
-        } else if line == "teiok" {
-            break;
-        }
-    }
-
-    // Validate the game options with the available engine options and set them.
-
-    validate_and_set_option(
-        &mut engine_writer,
-        &engine_options,
-        "HalfKomi",
-        game.half_komi as i32,
-        0,
-    )
-    .await?;
-    validate_and_set_option(
-        &mut engine_writer,
-        &engine_options,
-        "Flatstones",
-        game.flatstones as i32,
-        flatstones_for_size(game.size) as i32,
-    )
-    .await?;
-    validate_and_set_option(
-        &mut engine_writer,
-        &engine_options,
-        "Capstones",
-        game.capstones as i32,
-        capstones_for_size(game.size) as i32,
-    )
-    .await?;
-
-    info!("{engine_name} initialized.");
-
-    Ok((engine_writer, engine_reader))
-}
+        Ok(None)
+    }
 
-async fn run_game(
-    mut game: Game,
-    (mut engine_writer, mut engine_reader): (impl Writer, impl Reader),
-    (mut playtak_writer, mut playtak_reader): (impl Writer, impl Reader),
-) -> io::Result<()> {
-    info!(
-        id = game.id,
-        size = game.size,
-        opponent = game.opponent,
-        color = game.color,
-        "Starting game."
-    );
+    // A rematch on offer is posted in place of the seek.  A seek that can't be posted again
+    // yet is left for the wait loop's timer to post once it's due.
+    async fn post_seek(&mut self, mut writer: impl Writer) -> io::Result<()> {
+        let Some(seek) = self.rematches.seek().or(self.seek.as_ref()).cloned() else {
+            return Ok(());
+        };
+        let seek = &seek;
+
+        if let Some(delay) = self.etiquette.seek_delay() {
+            if !self.delayed {
+                info!(
+                    seconds = delay.as_secs_f32().ceil() as u64,
+                    "Waiting before posting the seek again."
+                );
+            }
+            self.delayed = true;
+            return Ok(());
+        }
+        self.delayed = false;
+
+        info!(color = ?seek.color, "Posting seek.");
+        write(&mut writer, seek.to_seek_string()).await?;
+        self.etiquette.seek_posted(seek);
+        self.posted = true;
+        self.set_status(Status::Seeking(seek));
+
+        if self.announcer.is_due(self.window) && self.etiquette.allow_chat() {
+            let text = self.announcer.text(&self.name, seek);
+            info!(text, "Announcing the seek.");
+            write(&mut writer, format!("Shout {text}\n")).await?;
+            self.announcer.announced(self.window);
+        }
 
-    let our_turn = match (&game.color, game.moves.len()) {
-        (c, n) if c == "white" && n % 2 == 0 => true,
-        (c, n) if c == "black" && n % 2 == 1 => true,
-        _ => false,
-    };
+        Ok(())
+    }
 
-    write(&mut engine_writer, game.new_game_string()).await?;
+    async fn withdraw_seek(&mut self, writer: impl Writer) -> io::Result<()> {
+        if self.posted {
+            write(writer, REMOVE_SEEK).await?;
+        }
+        self.etiquette.seek_withdrawn();
+        self.posted = false;
+        self.delayed = false;
+        self.set_status(Status::Idle);
 
-    if our_turn {
-        write(&mut engine_writer, game.position_string()).await?;
-        write(&mut engine_writer, game.search_string()).await?;
+        Ok(())
     }
 
-    'game: loop {
-        select! {
-            line = read(&mut engine_reader).fuse() => {
-                let line = line?;
-
-                let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
+    // Posts or withdraws the seek so that it's up exactly when it should be.  A changed seek
+    // that's already up is posted again.
+    async fn sync_seek(&mut self, writer: impl Writer, changed: bool) -> io::Result<()> {
+        if self.seek.is_none() {
+            return Ok(());
+        }
 
-                if parts[0] == "bestmove" {
-                    let game_move = GameMove::from_ptn(parts[1])?;
+        match (self.seek_hold(), self.posted) {
+            (None, false) => self.post_seek(writer).await,
+            (None, true) if changed || self.delayed => self.post_seek(writer).await,
+            (Some(reason), true) => {
+                info!(reason, "Withdrawing seek.");
+                self.withdraw_seek(writer).await
+            }
+            _ => Ok(()),
+        }
+    }
 
-                    write(&mut playtak_writer, game_move.to_playtak(game.id)).await?;
+    // Says that the bot is still waiting for a game, and for how long, so that it can be seen
+    // to be alive without debug logging.
+    fn report_waiting(&self, waited: Duration) {
+        let Some(seek) = self.rematches.seek().or(self.seek.as_ref()) else {
+            return;
+        };
+        let minutes = waited.as_secs() / 60;
+        let online = self
+            .online
+            .map(|online| format!(", {online} players online"))
+            .unwrap_or_default();
+
+        info!(
+            waited_minutes = minutes,
+            online = self.online,
+            seek = seek.summary(),
+            "Waiting for an opponent."
+        );
+        console::status(&format!(
+            "Waiting for an opponent for {minutes}m{online}: {}",
+            seek.summary()
+        ));
+    }
 
-                    game.moves.push(game_move);
+    // Carries out a command from the control API or an owner between games.
+    async fn handle_command(
+        &mut self,
+        writer: impl Writer,
+        command: Command,
+    ) -> io::Result<Result<(), &'static str>> {
+        let result = match (command, self.seek.is_some()) {
+            (Command::Seek, true) => {
+                self.paused = false;
+                match self.seek_hold() {
+                    Some(reason) => Err(reason),
+                    None => {
+                        self.post_seek(writer).await?;
+                        Ok(())
+                    }
                 }
             }
-            line = read(&mut playtak_reader).fuse() => {
-                let line = line?;
+            (Command::Unseek, true) => {
+                info!("Withdrawing seek.");
+                self.paused = true;
+                self.withdraw_seek(writer).await?;
+                Ok(())
+            }
+            (Command::Seek | Command::Unseek, false) => Err("this session doesn't seek"),
+            (Command::Resign | Command::Offer(_) | Command::Withdraw(_), _) => {
+                Err("there is no game in progress")
+            }
+            (command, _) => {
+                queue_command(&mut self.control, command);
+                Ok(())
+            }
+        };
 
-                let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
+        Ok(result)
+    }
 
-                if parts[0] == "NOK" {
-                    error!("Received NOK from PlayTak.com");
-                }
+    fn set_status(&self, status: Status) {
+        if let Some(control) = &self.control {
+            control.set_status(status.to_json(&self.stats));
+        }
+        if let Some(status_file) = &self.status_file {
+            status_file.update(status, &self.stats);
+        }
+    }
 
-                if parts[0] != format!("Game#{}", game.id) {
-                    continue;
-                }
+    fn shutting_down(&self) -> bool {
+        self.control.as_ref().is_some_and(|c| c.shutdown)
+    }
+}
 
-                if parts[1] == "Time" {
-                    game.time = (
-                        parts[2].parse::<u32>().map_err(|_| err!("could not parse white time"))?,
-                        parts[3].parse::<u32>().map_err(|_| err!("could not parse black time"))?,
-                    );
-                } else if parts[1] == "P" || parts[1] == "M" {
-                    let game_move = GameMove::from_playtak(&line)?;
+fn main() {
+    let Cli {
+        log_format,
+        no_color,
+        trace,
+        resign_on_panic,
+        command: mut args,
+    } = Cli::parse();
+
+    settle::install(resign_on_panic);
+
+    if let Some(Err(error)) = trace.map(|path| trace::enable(path.as_deref())) {
+        eprintln!("Could not start the trace: {error}");
+        return;
+    }
+
+    if let Some(Err(error)) = args.login_mut().map(Login::resolve_password) {
+        eprintln!("Could not get the password: {error}");
+        return;
+    }
+
+    #[cfg(feature = "tui")]
+    let _dashboard = match args.tui() {
+        true => match tui::Dashboard::start() {
+            Ok(dashboard) => Some(dashboard),
+            Err(error) => {
+                eprintln!("Could not start the dashboard: {error}");
+                return;
+            }
+        },
+        false => {
+            init_logging(log_format, no_color);
+            None
+        }
+    };
+
+    #[cfg(not(feature = "tui"))]
+    init_logging(log_format, no_color);
+
+    match args {
+        ArgCommand::Multi(command) => runtime::block_on(run_accounts(command)).ok(),
+        ArgCommand::Continue(command) => runtime::block_on(run_continue(&command)).ok(),
+        ArgCommand::Convert(command) => run_convert(&command).ok(),
+        ArgCommand::Suite(command) => runtime::block_on(run_suite(&command)).ok(),
+        ArgCommand::Doctor(command) => runtime::block_on(run_doctor(&command)).ok(),
+        args @ ArgCommand::Interactive(_) => runtime::block_on(async {
+            let _lock = lock_account(&args)?;
+            run_interactive(&args).await
+        })
+        .map_err(report_crash)
+        .ok(),
+        args => runtime::block_on(run(args)).map_err(report_crash).ok(),
+    };
+}
+
+// Leaves a report of anything that ended the session unexpectedly, to go with a bug report.
+// Failing to log in or finding the account already in use aren't bugs.
+fn report_crash(error: io::Error) {
+    if matches!(
+        error.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::AddrInUse
+    ) {
+        return;
+    }
+
+    match crash::write_report(&error) {
+        Ok(path) => {
+            error!(?path, "Wrote a crash report.");
+            eprintln!("A crash report was written to {}.", path.display());
+        }
+        Err(error) => error!(%error, "Could not write a crash report."),
+    }
+}
+
+// Color is used where it's supported, unless it's turned off with --no-color or NO_COLOR.
+fn init_logging(format: LogFormat, no_color: bool) {
+    let color = !no_color && env::var_os("NO_COLOR").is_none();
+
+    let level = match format {
+        LogFormat::Console => LevelFilter::WARN,
+        _ => LevelFilter::INFO,
+    };
+    let filter = EnvFilter::builder()
+        .with_default_directive(level.into())
+        .from_env_lossy();
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_ansi(color);
+
+    match format {
+        LogFormat::Pretty => builder.init(),
+        LogFormat::Console => {
+            console::enable(color && io::stdout().is_terminal());
+            builder.with_writer(io::stderr).init();
+        }
+        LogFormat::Json => builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .init(),
+    }
+}
+
+async fn run_accounts(command: MultiCommand) -> io::Result<()> {
+    let config = Config::load(&command.config).map_err(|error| {
+        error!(%error, "Could not load configuration.");
+        error
+    })?;
+
+    if config.accounts.is_empty() {
+        error!("No accounts are defined in the configuration.");
+        return Err(err!());
+    }
+
+    let mut sessions = Vec::new();
+    for account in &config.accounts {
+        // Build each account's command through clap so that it gets the same defaults and
+        // validation as one given on the command line.  Its seek settings are then layered
+        // on from the configuration file.
+        let Some(size) = account.seek.size.or(config.seek.size) else {
+            error!(account = account.username, "No seek size given.");
+            return Err(err!());
+        };
+
+        let mut arguments = vec![
+            "playtak-tei".to_owned(),
+            "seek".to_owned(),
+            "--username".to_owned(),
+            account.username.clone(),
+            "--password".to_owned(),
+            account.password.clone(),
+            "--size".to_owned(),
+            size.to_string(),
+            "--continuous".to_owned(),
+            "--config".to_owned(),
+            command.config.display().to_string(),
+        ];
+
+        if command.daemon {
+            arguments.push("--daemon".to_owned());
+        }
+
+        if let Some(status_file) = &account.status_file {
+            arguments.push("--status-file".to_owned());
+            arguments.push(status_file.display().to_string());
+        }
+
+        arguments.push("--".to_owned());
+        arguments.extend(account.engine.iter().cloned());
+
+        let args = ArgCommand::try_parse_from(arguments).map_err(|error| {
+            error!(account = account.username, %error, "Invalid account configuration.");
+            err!(error.to_string())
+        })?;
+
+        let span = info_span!("account", name = account.username);
+        sessions.push(runtime::spawn(run(args).instrument(span)));
+    }
+
+    let mut result = Ok(());
+    for session in sessions {
+        if let Err(error) = session.await {
+            result = Err(error);
+        }
+    }
+
+    result
+}
+
+// Logging into an account that's already in use disconnects the other session.
+fn lock_account(args: &ArgCommand) -> io::Result<Option<AccountLock>> {
+    let Some(username) = &args.login().username else {
+        return Ok(None);
+    };
+
+    match AccountLock::acquire(username) {
+        Ok(lock) => Ok(Some(lock)),
+        Err(error) if error.kind() == io::ErrorKind::AddrInUse => {
+            error!("Another instance of playtak-tei is already logged in as {username}.");
+            Err(error)
+        }
+        Err(error) => {
+            error!(%error, "Could not lock account.");
+            Err(error)
+        }
+    }
+}
+
+async fn run(args: ArgCommand) -> io::Result<()> {
+    let mut state = BotState::new(&args).map_err(|error| {
+        error!(%error, "Could not load configuration.");
+        error
+    })?;
+
+    if args.dry_run() {
+        return dry_run(&args, &state).await;
+    }
+
+    if let ArgCommand::List(list @ ListCommand { api: true, .. }) = &args {
+        return list_from_api(list).await;
+    }
+
+    let _lock = lock_account(&args)?;
 
-                    game.moves.push(game_move);
+    if let Some(path) = args.live_ptn() {
+        live::write_ptn(path.to_owned());
+    }
+
+    if let Some(address) = args.http() {
+        let listener = runtime::listen(address).await.map_err(|error| {
+            error!(%error, address, "Could not listen for HTTP connections.");
+            error
+        })?;
+        info!(address, "Serving the dashboard.");
+        runtime::spawn(run_dashboard(listener));
+    }
+
+    if let Some((address, token)) = args.control() {
+        let token = token.map(str::to_owned);
+        let control = Control::listen(address, token).await.map_err(|error| {
+            error!(%error, address, "Could not listen for control commands.");
+            error
+        })?;
+        info!(address, "Listening for control commands.");
+        state.control = Some(control);
+    }
+
+    // Owners' commands are carried out like the control API's, with or without an endpoint.
+    if state.control.is_none() && state.moderation.has_owners() {
+        state.control = Some(Control::local());
+    }
+
+    let daemon = matches!(args, ArgCommand::Seek(SeekCommand { daemon: true, .. }));
 
-                    write(&mut engine_writer, game.position_string()).await?;
-                    write(&mut engine_writer, game.search_string()).await?;
-                } else if parts[1] == "Over" {
-                    info!(result = parts[2], "Game finished.");
-                    break 'game;
+    loop {
+        state.set_status(Status::Connecting);
+
+        match main_inner(&args, &mut state).await {
+            // Logging back in has the server resend the game from the start.
+            Err(error) if is_desync(&error) && state.resyncs < MAX_RESYNCS => {
+                state.resyncs += 1;
+                state.set_status(Status::Reconnecting);
+                warn!("Lost track of the game. Reconnecting to resynchronize.");
+            }
+            // Authentication failures won't fix themselves by reconnecting.
+            Err(error) if daemon && error.kind() != io::ErrorKind::PermissionDenied => {
+                state.set_status(Status::Reconnecting);
+                warn!(
+                    %error,
+                    "Session interrupted. Reconnecting in {} seconds.",
+                    RECONNECT_DELAY.as_secs()
+                );
+                runtime::sleep(RECONNECT_DELAY).await;
+            }
+            result => {
+                state.set_status(Status::Finished);
+                if let Some(verification) = state.verification.take() {
+                    verification.await;
                 }
+                return result;
             }
         }
     }
+}
+
+macro_rules! err {
+    () => {
+        io::Error::from(io::ErrorKind::Other)
+    };
+    ($message:expr) => {
+        io::Error::new(io::ErrorKind::Other, $message)
+    };
+}
+pub(crate) use err;
+
+// Our record of a game no longer matches the server's.
+#[derive(Debug)]
+struct Desync;
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "lost track of the game")
+    }
+}
+
+impl Error for Desync {}
+
+fn is_desync(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<Desync>())
+}
+
+trait Writer: AsyncWrite + Unpin {}
+impl<T> Writer for T where T: AsyncWrite + Unpin {}
+
+async fn write(mut writer: impl Writer, value: impl AsRef<[u8]>) -> io::Result<()> {
+    if let Ok(value) = std::str::from_utf8(value.as_ref()) {
+        debug!(
+            event = "protocol",
+            direction = "sent",
+            raw_line = %trace::redact('>', value.trim_end()),
+            "Sending"
+        );
+    }
+
+    let result = writer.write_all(value.as_ref()).await;
+
+    if let Err(error) = &result {
+        error!(%error, "Could not write to stream.");
+    }
+
+    result
+}
+
+trait Reader: Stream<Item = io::Result<String>> + Unpin {}
+impl<T> Reader for T where T: Stream<Item = io::Result<String>> + Unpin {}
+
+async fn read(mut reader: impl Reader) -> io::Result<String> {
+    let result = if let Some(next) = reader.next().await {
+        next
+    } else {
+        error!("Stream closed unexpectedly.");
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    };
+
+    if let Err(error) = &result {
+        error!(%error, "Could not read from stream.");
+    }
+
+    if let Ok(line) = &result {
+        debug!(
+            event = "protocol",
+            direction = "received",
+            raw_line = line,
+            "Received"
+        );
+    }
+
+    result
+}
+
+// Shows what would be sent to the server and checks the engine, without connecting.
+async fn dry_run(args: &ArgCommand, state: &BotState) -> io::Result<()> {
+    let login = args.login();
+    let masked_login = Login {
+        password: login.password.as_ref().map(|_| "********".to_owned()),
+        ..login.clone()
+    };
+
+    println!("Would send to PlayTak.com:\n");
+    print!("  Client playtak-tei\n  {}", masked_login.to_login_string());
+
+    let game = match args {
+        ArgCommand::Accept(AcceptCommand {
+            accept:
+                AcceptInfo {
+                    seek_id,
+                    opponent,
+                    auto,
+                },
+            ..
+        }) => {
+            if let Some(seek_id) = seek_id {
+                println!("  Accept {seek_id}");
+            } else if let Some(opponent) = opponent {
+                println!("  Accept <the id of the seek from {opponent}>");
+            } else if *auto {
+                println!("  Accept <the id of each seek that passes the filters>");
+            }
+            None
+        }
+        ArgCommand::Seek(_) => {
+            let seek = state.seek.as_ref().unwrap();
+            print!("  {}", seek.to_seek_string());
+
+            Some(Game {
+                size: seek.size,
+                half_komi: seek.half_komi,
+                flatstones: seek.flatstones(),
+                capstones: seek.capstones(),
+                ..Default::default()
+            })
+        }
+        _ => unreachable!(),
+    };
+
+    if args.human() {
+        println!("\nMoves would be read from the terminal.");
+        return Ok(());
+    }
+
+    let mut engine = Engine::spawn(args.engine_arguments(), args.engine_settings()).await?;
+
+    println!("\nEngine \"{}\" completed the handshake.", engine.name);
+    for option in &engine.options {
+        println!(
+            "  option {} (default {}, range {}..={})",
+            option.name,
+            option.default,
+            option.range.start(),
+            option.range.end()
+        );
+    }
+
+    engine.apply_settings(args.engine_settings()).await?;
+
+    if let Some(game) = game {
+        if let Some(seek) = &state.seek {
+            engine.check_seek(seek).map_err(|reason| {
+                println!("The engine can't play the seek's games: {reason}.");
+                err!(reason)
+            })?;
+        }
+        engine.configure(&game).await?;
+        println!("The engine supports the seek's game options.");
+    }
+
+    engine.quit().await
+}
+
+async fn main_inner(args: &ArgCommand, state: &mut BotState) -> io::Result<()> {
+    // Listing gives up straight away, since it has the API to fall back on.
+    let session = match args {
+        ArgCommand::List(_) => Session::connect(args.login()).await,
+        _ => Session::connect_retrying(args.login()).await,
+    };
+    let session = match (session, args) {
+        (Ok(session), _) => session,
+        // Listing doesn't need a login, so there's another way to go about it.
+        (Err(error), ArgCommand::List(list)) => {
+            warn!(%error, "Could not log in. Listing seeks from the PlayTak.com API instead.");
+            return list_from_api(list).await;
+        }
+        (Err(error), _) => return Err(error),
+    };
+    let keep_alive = session.keep_alive();
+    let Session {
+        writer: mut playtak_writer,
+        reader: mut playtak_reader,
+        name,
+        ..
+    } = session;
+    state.name = name;
+
+    let (mut seeks, games, resumed_game) = read_initial_state(&mut playtak_reader).await?;
+
+    if let ArgCommand::List(list) = args {
+        if list.games {
+            print_games(&games);
+        } else {
+            print_seeks(&seeks, &state.name, list.mine_only, &HashMap::new());
+        }
+
+        write(&mut playtak_writer, "quit\n").await?;
+        return playtak_writer.flush().await;
+    }
+
+    if matches!(args, ArgCommand::Resume(_)) && resumed_game.is_none() {
+        error!("There is no game to resume.");
+        write(&mut playtak_writer, "quit\n").await?;
+        playtak_writer.flush().await?;
+        return Err(io::Error::from(io::ErrorKind::NotFound));
+    }
+
+    runtime::spawn(keep_alive);
+
+    // How often to report on waiting for a game, unless it's turned off with 0.
+    let status_interval = match args {
+        ArgCommand::Seek(SeekCommand {
+            status_interval, ..
+        }) if *status_interval > 0 => Some(Duration::from_secs(*status_interval)),
+        _ => None,
+    };
+
+    let (continuous, alternate_colors) = match args {
+        ArgCommand::Seek(SeekCommand {
+            continuous,
+            alternate_colors,
+            daemon,
+            ..
+        }) => (*continuous || *daemon, *alternate_colors),
+        ArgCommand::Accept(AcceptCommand {
+            accept: AcceptInfo { auto, .. },
+            ..
+        }) => (*auto, false),
+        _ => (false, false),
+    };
+
+    if alternate_colors
+        && matches!(
+            state.seek,
+            Some(Seek {
+                color: SeekColor::Random,
+                ..
+            })
+        )
+    {
+        warn!("Alternating colors has no effect on a seek with a random color.");
+    }
+
+    // The engine is started before anything is posted or accepted, so that games it can't play
+    // are turned down up front.
+    let mut engine = match args.human() {
+        true => None,
+        false => Some(Engine::spawn(args.engine_arguments(), args.engine_settings()).await?),
+    };
+    if let Some(engine) = &mut engine {
+        let size = resumed_game
+            .as_ref()
+            .map(|game| game.size)
+            .or(state.seek.as_ref().map(|seek| seek.size))
+            .unwrap_or(WARMUP_SIZE);
+        engine.warm_up(args.engine_settings(), size).await?;
+    }
+    if let (Some(engine), ArgCommand::Seek(_), Some(seek)) = (&engine, args, &state.seek) {
+        engine.check_seek(seek).map_err(|reason| {
+            error!(reason, "{} can't play the seek's games.", engine.name);
+            err!(reason)
+        })?;
+    }
+
+    if let Some(game) = resumed_game {
+        if let Some(directory) = args.journal_dir() {
+            journal::check_resumed(directory, &game);
+        }
+
+        state.set_status(Status::Playing(&game));
+        let slot = GameSlot::take();
+
+        let game = play(
+            args,
+            &mut engine,
+            game,
+            (&mut playtak_writer, &mut playtak_reader),
+            &mut state.control,
+            &mut state.moderation,
+            &state.notifiers,
+        )
+        .await?;
+        drop(slot);
+        state.verification = verify_game(args, &game).or(state.verification.take());
+
+        state.stats.record_game(&game);
+        state.resyncs = 0;
+    }
+
+    // Set once a game is called off before it begins, after which an opponent's seek is waited
+    // for if it's gone.
+    let mut aborted = false;
+
+    // Only one game is played, resumed or not, unless the session is continuous.
+    'session: while (continuous || state.stats.games() == 0)
+        && !state.stats.finished(&state.limits)
+        && !state.shutting_down()
+    {
+        state.update_seek(args);
+
+        // The seek we accepted and when, until its game starts.
+        let mut accepting = None;
+
+        // Post or accept the seek.
+        match args {
+            ArgCommand::Accept(AcceptCommand {
+                accept:
+                    AcceptInfo {
+                        seek_id,
+                        opponent,
+                        auto,
+                    },
+                ..
+            }) => {
+                if let Some(seek_id) = seek_id {
+                    if let Some(seek) = seeks.iter().find(|s| s.id == Some(*seek_id)) {
+                        check_seek(engine.as_ref(), seek)?;
+                    }
+                    info!("Accepting seek {seek_id}.");
+                    write(&mut playtak_writer, format!("Accept {seek_id}\n")).await?;
+                    accepting = Some((*seek_id, Instant::now()));
+                } else if *auto {
+                    accepting = state
+                        .accept_any(&mut playtak_writer, args, &seeks, engine.as_ref())
+                        .await?;
+                    if accepting.is_none() {
+                        info!("Waiting for a seek to accept.");
+                    }
+                } else if let Some(opponent) = opponent {
+                    if let Some(seek) = seeks.iter().find(|s| s.player.as_ref() == Some(opponent)) {
+                        check_seek(engine.as_ref(), seek)?;
+                        let seek_id = seek.id.unwrap();
+                        info!(seek_id, "Accepting seek from {opponent}.");
+                        write(&mut playtak_writer, format!("Accept {seek_id}\n")).await?;
+                        accepting = Some((seek_id, Instant::now()));
+                    } else if aborted {
+                        info!("Waiting for {opponent} to seek again.");
+                    } else {
+                        error!("Cannot find seek from {opponent}.");
+                        return Err(err!());
+                    }
+                }
+            }
+            ArgCommand::Seek(_) => {
+                // Any seek posted before the last game was removed when it started.
+                state.posted = false;
+
+                match state.seek_hold() {
+                    None => state.post_seek(&mut playtak_writer).await?,
+                    Some(reason) => {
+                        info!(reason, "Not posting the seek for now.");
+                        state.set_status(Status::Idle);
+                    }
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        // When the wait for a game began, and when it was last reported on.
+        let waiting_since = Instant::now();
+        let mut reported = waiting_since;
+
+        let mut game = loop {
+            let message = select! {
+                message = read_message(&mut playtak_reader).fuse() => message?,
+                _ = runtime::sleep(Duration::from_secs(1)).fuse() => {
+                    // Replace the posted seek if the configuration changed while waiting, and post
+                    // or withdraw it as scheduled windows open and close, limits come and go, and
+                    // a delayed seek comes due.
+                    let mut changed = state.update_seek(args);
+                    changed |= state.rematches.expire();
+                    state.etiquette.challenge_expired();
+                    state.sync_seek(&mut playtak_writer, changed).await?;
+
+                    if state.posted && status_interval.is_some_and(|i| reported.elapsed() >= i) {
+                        state.report_waiting(waiting_since.elapsed());
+                        reported = Instant::now();
+                    }
+
+                    if let Some((seek_id, sent)) = accepting {
+                        if sent.elapsed() >= ACCEPT_TIMEOUT {
+                            seeks.retain(|s| s.id != Some(seek_id));
+                            seek_lost(args)?;
+                            accepting = state.accept_any(&mut playtak_writer, args, &seeks, engine.as_ref()).await?;
+                        }
+                    }
+                    continue;
+                }
+                request = next_command(&mut state.control).fuse() => {
+                    let (command, reply) = request;
+                    let result = state.handle_command(&mut playtak_writer, command).await?;
+                    reply.send(result).ok();
+
+                    if state.shutting_down() {
+                        state.withdraw_seek(&mut playtak_writer).await?;
+                        break 'session;
+                    }
+                    continue;
+                }
+            };
+
+            if let Message::Nok = message {
+                if let Some((seek_id, _)) = accepting {
+                    seeks.retain(|s| s.id != Some(seek_id));
+                    seek_lost(args)?;
+                    accepting = state
+                        .accept_any(&mut playtak_writer, args, &seeks, engine.as_ref())
+                        .await?;
+                    continue;
+                }
+                error!("Could not accept or post seek.");
+                return Err(err!());
+            } else if let Message::SeekNew(seek) = message {
+                if let ArgCommand::Accept(AcceptCommand {
+                    accept:
+                        AcceptInfo {
+                            opponent: Some(opponent),
+                            ..
+                        },
+                    ..
+                }) = args
+                {
+                    if accepting.is_none() && seek.player.as_ref() == Some(opponent) {
+                        let seek_id = seek.id.unwrap();
+                        info!(seek_id, "Accepting seek from {opponent}.");
+                        write(&mut playtak_writer, format!("Accept {seek_id}\n")).await?;
+                        accepting = Some((seek_id, Instant::now()));
+                    }
+                }
+                // An opponent who's been offered a rematch can ask for it themselves.
+                if let ArgCommand::Seek(_) = args {
+                    if accepting.is_none() && state.rematches.is_reseek(&seek, &state.name) {
+                        let seek_id = seek.id.unwrap();
+                        info!(seek_id, "Accepting the rematch.");
+                        write(&mut playtak_writer, format!("Accept {seek_id}\n")).await?;
+                        accepting = Some((seek_id, Instant::now()));
+                    }
+                }
+                seeks.push(seek);
+                if accepting.is_none() {
+                    accepting = state
+                        .accept_any(&mut playtak_writer, args, &seeks, engine.as_ref())
+                        .await?;
+                }
+            } else if let Message::SeekRemove(seek_id) = message {
+                seeks.retain(|s| s.id != Some(seek_id));
+            } else if let Message::Online(count) = message {
+                state.online = Some(count);
+            } else if let Message::Error(error) = message {
+                error!(error, "Received an error from PlayTak.com.");
+            } else if let Message::Chat(chat) = message {
+                if let Some(command) = owner_command(&mut state.moderation, &chat) {
+                    let result = match command {
+                        Ok(command) => state.handle_command(&mut playtak_writer, command).await?,
+                        Err(reason) => Err(reason),
+                    };
+                    reply_to_owner(
+                        &mut playtak_writer,
+                        &mut state.moderation,
+                        &chat.player,
+                        result,
+                    )
+                    .await?;
+
+                    if state.shutting_down() {
+                        state.withdraw_seek(&mut playtak_writer).await?;
+                        break 'session;
+                    }
+                }
+            } else if let Message::GameStart(game) = message {
+                if state.seek.is_some() {
+                    // The server removes the seek once a game starts from it.
+                    state.posted = false;
+
+                    let mut rejection = if !state.filter.allows(&game.opponent) {
+                        Err("opponent is not allowed")
+                    } else {
+                        state
+                            .stats
+                            .check(&state.limits, &game.opponent)
+                            .and(state.etiquette.allows_game())
+                    };
+
+                    if rejection.is_ok() {
+                        let api_url = &args.login().connection.api_url;
+                        rejection = state.filter.check_rating(api_url, &game.opponent).await;
+
+                        if let (Err(_), Some(message)) = (rejection, &state.filter.rating_message) {
+                            if state.moderation.may_reply(&game.opponent)
+                                && state.etiquette.allow_chat()
+                            {
+                                let tell = format!("Tell {} {message}\n", game.opponent);
+                                write(&mut playtak_writer, tell).await?;
+                            }
+                        }
+                    }
+
+                    if let Err(reason) = rejection {
+                        info!(
+                            game_id = game.id,
+                            reason, "Rejecting game from {}.", game.opponent
+                        );
+                        write(&mut playtak_writer, format!("Game#{} Resign\n", game.id)).await?;
+                        state.sync_seek(&mut playtak_writer, false).await?;
+                        continue;
+                    }
+                }
+
+                state.rematches.started(&game);
+                break game;
+            }
+        };
+
+        game.increment = match &state.seek {
+            Some(seek) => Some(seek.increment),
+            None => seeks
+                .iter()
+                .find(|s| s.player.as_ref() == Some(&game.opponent))
+                .map(|s| s.increment),
+        };
+
+        state.set_status(Status::Playing(&game));
+        let slot = GameSlot::take();
+
+        let game = play(
+            args,
+            &mut engine,
+            game,
+            (&mut playtak_writer, &mut playtak_reader),
+            &mut state.control,
+            &mut state.moderation,
+            &state.notifiers,
+        )
+        .await?;
+        drop(slot);
+        state.verification = verify_game(args, &game).or(state.verification.take());
+
+        // A game that was called off before it began doesn't count.
+        if game.was_aborted() {
+            info!(game_id = game.id, "The game was aborted before it began.");
+            aborted = true;
+        } else {
+            state.stats.record_game(&game);
+        }
+        state.resyncs = 0;
+
+        if let Some(seek) = &state.seek {
+            state.rematches.offer(&game, seek);
+        }
+
+        if let (true, Some(seek)) = (alternate_colors, &mut state.seek) {
+            seek.color = seek.color.opposite();
+        }
+    }
+
+    if let Some(engine) = engine {
+        engine.quit().await?;
+    }
+
+    if continuous {
+        let record = state.stats.record;
+        info!(
+            games = state.stats.games(),
+            wins = record.wins,
+            losses = record.losses,
+            draws = record.draws,
+            "Session finished."
+        );
+        if let Some(estimate) = record.estimate() {
+            info!(
+                "Performance: {:+.1} ± {:.1} Elo, {:.1}% likelihood of superiority.",
+                estimate.elo, estimate.margin, estimate.los
+            );
+        }
+    }
+
+    state.notifiers.flush().await;
+
+    write(&mut playtak_writer, "quit\n").await?;
+    playtak_writer.flush().await
+}
+
+// Lists seeks with the ones directed at us first, since those are the ones waiting on us.
+// Seeks are shown with their players' ratings, where they're known.
+fn print_seeks<'a>(
+    seeks: impl IntoIterator<Item = &'a Seek>,
+    name: &str,
+    mine_only: bool,
+    ratings: &HashMap<String, u32>,
+) {
+    let (mine, others) = seeks
+        .into_iter()
+        .partition::<Vec<_>, _>(|seek| seek.is_directed_at(name));
+
+    if console::is_enabled() {
+        let others = if mine_only { Vec::new() } else { others };
+        console::seek_table(mine.into_iter().chain(others), name, ratings);
+        return;
+    }
+
+    let rating = |seek: &Seek| {
+        seek.player
+            .as_ref()
+            .and_then(|player| ratings.get(player))
+            .map(|rating| format!(", rating: {rating}"))
+            .unwrap_or_default()
+    };
+
+    if !mine.is_empty() {
+        println!("Seeks directed at you:\n");
+        for seek in mine {
+            println!("{seek}{}  <-- for you\n", rating(seek));
+        }
+    } else if mine_only {
+        println!("No seeks are directed at you.");
+    }
+
+    if !mine_only {
+        println!("Available seeks:\n");
+        for seek in others {
+            println!("{seek}{}\n", rating(seek));
+        }
+    }
+}
+
+fn print_games(games: &[ListedGame]) {
+    if console::is_enabled() {
+        console::game_table(games);
+        return;
+    }
+
+    if games.is_empty() {
+        println!("No games are in progress.");
+        return;
+    }
+
+    println!("Games in progress:\n");
+    for game in games {
+        let komi = game
+            .half_komi
+            .map(|half_komi| format!(", komi: {:3.1}", f64::from(half_komi) / 2.0))
+            .unwrap_or_default();
+        let plies = game
+            .plies
+            .map(|plies| format!(", plies: {plies}"))
+            .unwrap_or_default();
+
+        println!(
+            "  Game {}: {} vs {}\n      size: {}, time: {}+{}{komi}{plies}\n",
+            game.id, game.white, game.black, game.size, game.time, game.increment
+        );
+    }
+}
+
+// Lists seeks and games in progress from the web API, which takes no login, so there's no name
+// to pick out the seeks directed at us by but the one given.
+async fn list_from_api(list: &ListCommand) -> io::Result<()> {
+    let api_url = &list.login.connection.api_url;
+    if list.games {
+        let games = api::games(api_url).await.map_err(|error| {
+            error!(%error, "Could not list games from the PlayTak.com API.");
+            error
+        })?;
+        print_games(&games.into_iter().map(ListedGame::from).collect::<Vec<_>>());
+        return Ok(());
+    }
+
+    let (seeks, games) = future::try_join(api::seeks(api_url), api::games(api_url))
+        .await
+        .map_err(|error| {
+            error!(%error, "Could not list seeks from the PlayTak.com API.");
+            error
+        })?;
+    let games = games.into_iter().map(ListedGame::from).collect::<Vec<_>>();
+
+    let players = seeks
+        .iter()
+        .filter_map(|seek| seek.player.clone())
+        .collect::<HashSet<_>>();
+    let ratings = future::join_all(players.into_iter().map(|player| async move {
+        let rating = api::rating(api_url, &player).await.ok().flatten();
+        rating.map(|rating| (player, rating))
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect::<HashMap<_, _>>();
+
+    let name = list.login.username.as_deref().unwrap_or_default();
+    print_seeks(&seeks, name, list.mine_only, &ratings);
+
+    if !list.mine_only && !games.is_empty() {
+        print_games(&games);
+    }
 
     Ok(())
 }
+
+// Reads the seeks and games in progress the server sends after login, along with a game to
+// resume, if any.
+async fn read_initial_state(
+    mut reader: impl Reader,
+) -> io::Result<(Vec<Seek>, Vec<ListedGame>, Option<Game>)> {
+    let (mut seeks, mut games) = (Vec::new(), Vec::new());
+    loop {
+        // Read only until the server is done sending seeks and games.
+        match read_message(&mut reader).await? {
+            Message::SeekNew(seek) => seeks.push(seek),
+            Message::GameListAdd(game) => games.push(game),
+            Message::GameStart(mut game) => {
+                info!("Resuming game.");
+
+                loop {
+                    match read_message(&mut reader).await? {
+                        Message::Notice(message) if message == "Your game is resumed" => break,
+                        Message::Game { id, update } if id == game.id => match update {
+                            GameUpdate::Move(game_move) => game.moves.push(game_move),
+                            GameUpdate::Time(white, black) => game.time = (white, black),
+                            GameUpdate::Undo => {
+                                game.moves.pop();
+                            }
+                            _ => (),
+                        },
+                        _ => (),
+                    }
+                }
+
+                return Ok((seeks, games, Some(game)));
+            }
+            _ => return Ok((seeks, games, None)),
+        }
+    }
+}
+
+// Plays a game through to the end with the move provider chosen by the command.  An engine
+// left over from an earlier game is reused, and the engine is kept for the next game unless
+// something went wrong.
+async fn play(
+    args: &ArgCommand,
+    engine: &mut Option<Engine>,
+    game: Game,
+    (playtak_writer, playtak_reader): (impl Writer, impl Reader),
+    control: &mut Option<Control>,
+    moderation: &mut Moderation,
+    notifiers: &Notifiers,
+) -> io::Result<Game> {
+    notifiers.send(Notification::game_start(&game));
+
+    let journal = args.journal_dir().and_then(|directory| {
+        Journal::open(directory, &game)
+            .map_err(|error| warn!(%error, ?directory, "Could not open a game journal."))
+            .ok()
+    });
+
+    let (playtak_transcript, tei_transcript) = match args.transcript_dir() {
+        Some(directory) => match Transcript::create(directory, &game) {
+            Ok((playtak, tei)) => (Some(playtak), Some(tei)),
+            Err(error) => {
+                warn!(%error, ?directory, "Could not create the game's transcripts.");
+                (None, None)
+            }
+        },
+        None => (None, None),
+    };
+    let playtak = (
+        Transcribed::new(playtak_writer, playtak_transcript.clone()),
+        Transcribed::new(playtak_reader, playtak_transcript),
+    );
+
+    if args.human() {
+        let game = run_game(
+            game,
+            &mut Human::new(),
+            playtak,
+            (control, moderation),
+            (journal, args.milestones()),
+        )
+        .await?;
+        notifiers.send(Notification::game_over(&game));
+        save_ptn(args, &game);
+        return Ok(game);
+    }
+
+    let mut current = match engine.take() {
+        Some(current) => current,
+        None => Engine::spawn(args.engine_arguments(), args.engine_settings()).await?,
+    };
+    current.transcribe(tei_transcript);
+    if current.is_prepared() {
+        current.reset(args.engine_settings(), &game).await?;
+    } else {
+        current.prepare(args.engine_settings(), &game).await?;
+    }
+
+    // Options that can't be set won't be tried again.
+    if let Some(control) = control {
+        let mut rejected = Vec::new();
+        for (name, &value) in &control.options {
+            if let Err(error) = current.set_option(name, value).await {
+                warn!(%error, "Could not set engine option \"{name}\".");
+                rejected.push(name.clone());
+            }
+        }
+        control.options.retain(|name, _| !rejected.contains(name));
+    }
+
+    let extras = (journal, args.milestones());
+    match run_game(game, &mut current, playtak, (control, moderation), extras).await {
+        Ok(game) => {
+            notifiers.send(Notification::game_over(&game));
+            save_ptn(args, &game);
+
+            if current.is_running() {
+                *engine = Some(current);
+            } else {
+                warn!("{} exited during the game.", current.name);
+            }
+            Ok(game)
+        }
+        Err(error) => {
+            current.quit().await.ok();
+            Err(error)
+        }
+    }
+}
+
+fn save_ptn(args: &ArgCommand, game: &Game) {
+    let Some(directory) = args.ptn_dir() else {
+        return;
+    };
+
+    let path = directory.join(format!("{}.ptn", game.id));
+    match fs::write(&path, game.to_ptn()) {
+        Ok(()) => info!(?path, "Saved game."),
+        Err(error) => error!(%error, ?path, "Could not save game."),
+    }
+}
+
+// Checks a finished game against the server's record of it in the background, which catches
+// moves or a result that we got wrong without noticing.
+fn verify_game(args: &ArgCommand, game: &Game) -> Option<runtime::JoinHandle<()>> {
+    game.result.as_ref()?;
+
+    let api_url = args.login().connection.api_url.clone();
+    let game = game.clone();
+
+    Some(runtime::spawn(async move {
+        runtime::sleep(VERIFY_DELAY).await;
+
+        match api::game(&api_url, game.id).await {
+            Ok(Some(record)) => match record.mismatch(&game) {
+                Some(mismatch) => warn!(
+                    game_id = game.id,
+                    mismatch, "The server's record of the game doesn't match ours."
+                ),
+                None => debug!(
+                    game_id = game.id,
+                    "The server's record of the game matches ours."
+                ),
+            },
+            Ok(None) => warn!(game_id = game.id, "The server has no record of the game."),
+            Err(error) => warn!(
+                %error,
+                game_id = game.id,
+                "Could not fetch the server's record of the game."
+            ),
+        }
+    }))
+}
+
+// Plays the game until it's over and returns it with its moves and result.  Everything logged
+// during the game is in a span with the game's id, and what's logged about a move is in a span
+// of its own under that one.
+#[instrument(name = "game", skip_all, fields(game_id = game.id))]
+async fn run_game(
+    mut game: Game,
+    player: &mut impl MoveProvider,
+    (mut playtak_writer, mut playtak_reader): (impl Writer, impl Reader),
+    (control, moderation): (&mut Option<Control>, &mut Moderation),
+    (mut journal, mut milestones): (Option<Journal>, Option<Milestones>),
+) -> io::Result<Game> {
+    info!(
+        game_id = game.id,
+        size = game.size,
+        opponent = game.opponent,
+        color = %game.color,
+        rated = !game.unrated,
+        tournament = game.tournament,
+        url = game.url(),
+        event = "game_start",
+        "Starting game."
+    );
+    console::game_start(&game);
+    let _settled = settle::game(game.id);
+
+    player.start(&game).await?;
+    live::game(&game);
+
+    // Set once the move provider has failed, after which moves are chosen by the client.
+    let mut failed = false;
+
+    let mut span = move_span(&game);
+
+    // When the move provider was last asked for a move.
+    let mut requested = None;
+
+    if game.is_our_turn() {
+        player.request_move(&game).await?;
+        requested = Some(Instant::now());
+    } else {
+        player.ponder(&game).await?;
+    }
+
+    // When the move waiting for the server to accept it was sent, and its span.
+    let mut unconfirmed: Option<(Instant, Span)> = None;
+
+    // Engine options changed during the game, waiting for the engine to finish searching.
+    let mut options = Vec::new();
+
+    let mut offers = Offers::default();
+
+    let mut clocks = ClockLog::new(&game);
+
+    // Our clock before the move waiting for the server to accept it, and how long we took over
+    // it by our own reckoning, for comparing with what the server charged.
+    let mut charged: Option<(u32, Duration)> = None;
+    let mut drift = ClockDrift::default();
+
+    'game: loop {
+        if !game.is_our_turn() && !failed {
+            set_options(player, &mut options, control).await;
+        }
+
+        let next_move = async {
+            if failed {
+                future::pending().await
+            } else {
+                player.next_move().instrument(span.clone()).await
+            }
+        };
+
+        let game_move = select! {
+            game_move = next_move.fuse() => match game_move {
+                Ok(None) if player.take_resignation() => {
+                    info!(game_id = game.id, "The engine resigned.");
+                    write(&mut playtak_writer, format!("Game#{} Resign\n", game.id)).await?;
+                    None
+                }
+                Ok(game_move) => game_move,
+                Err(error) => {
+                    error!(%error, game_id = game.id, "The move provider failed. Playing fallback moves.");
+                    failed = true;
+                    game.is_our_turn().then(|| game.fallback_move()).flatten()
+                }
+            },
+            message = read_message(&mut playtak_reader).fuse() => {
+                let update = match message? {
+                    Message::Nok => {
+                        error!("Received NOK from PlayTak.com");
+
+                        if unconfirmed.is_some() {
+                            error!(game_id = game.id, "The server rejected our move.");
+                            return Err(io::Error::other(Desync));
+                        }
+                        continue;
+                    }
+                    Message::Game { id, update } if id == game.id => update,
+                    // The server only plays one game at a time with us, so ours is gone.
+                    Message::GameStart(other) => {
+                        warn!(
+                            game_id = game.id,
+                            other_game_id = other.id,
+                            "The server started another game."
+                        );
+                        return Err(io::Error::other(Desync));
+                    }
+                    Message::Chat(chat) => {
+                        if let Some(command) = owner_command(moderation, &chat) {
+                            let result = match command {
+                                Ok(command) => {
+                                    game_command(
+                                        command,
+                                        &game,
+                                        &mut playtak_writer,
+                                        control,
+                                        &mut options,
+                                        &mut offers,
+                                    )
+                                    .await?
+                                }
+                                Err(reason) => Err(reason),
+                            };
+                            reply_to_owner(&mut playtak_writer, moderation, &chat.player, result).await?;
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+
+                match update {
+                    GameUpdate::Time(white, black) => {
+                        if let Some((sent, span)) = unconfirmed.take() {
+                            debug!(
+                                parent: &span,
+                                game_id = game.id,
+                                round_trip_ms = sent.elapsed().as_millis() as u64,
+                                event = "move_accepted",
+                                "The server accepted our move."
+                            );
+                        }
+                        game.time = (white, black);
+                        clocks.record(&game);
+                        if let Some((before, thought)) = charged.take() {
+                            check_clock(&game, before, thought, &mut drift, player);
+                        }
+                        live::game(&game);
+                        if let Some(journal) = &mut journal {
+                            journal.record_time(game.time);
+                        }
+
+                        // There's no point in finishing a search once we've lost on time.
+                        if game.our_time() == 0 && game.is_our_turn() && !failed {
+                            info!(game_id = game.id, "Out of time.");
+                            player.stop().await?;
+                        }
+                        None
+                    }
+                    GameUpdate::Move(game_move) => {
+                        if let Err(error) = game.check_move(&game_move) {
+                            error!(
+                                %error,
+                                game_id = game.id,
+                                game_move = game_move.to_ptn(),
+                                "The opponent's move is not legal in our position."
+                            );
+                            return Err(io::Error::other(Desync));
+                        }
+
+                        if let Some(expected) = game.ponder.take() {
+                            debug!(
+                                game_id = game.id,
+                                expected = expected.to_ptn(),
+                                hit = expected == game_move,
+                                "Compared the opponent's move with the expected reply."
+                            );
+                        }
+
+                        record_move(&mut journal, &game, &game_move);
+                        game.moves.push(game_move);
+                        log_position(&game);
+                        span = move_span(&game);
+
+                        if failed {
+                            game.fallback_move()
+                        } else {
+                            player.request_move(&game).await?;
+                            requested = Some(Instant::now());
+                            None
+                        }
+                    }
+                    GameUpdate::InvalidMove(error) => {
+                        error!(error, game_id = game.id, "Could not follow a move in the game.");
+                        return Err(io::Error::other(Desync));
+                    }
+                    GameUpdate::Over(result) => {
+                        info!(
+                            game_id = game.id,
+                            result,
+                            url = game.url(),
+                            event = "game_over",
+                            "Game finished."
+                        );
+                        if let Ok(board) = game.board() {
+                            info!(game_id = game.id, "Final position:\n{board}");
+                        }
+                        game.result = Some(result);
+                        live::game(&game);
+                        console::game_over(&game);
+                        log_time_usage(&game, &clocks);
+                        if let Some(journal) = journal.take() {
+                            journal.finish();
+                        }
+                        if !failed {
+                            player.finish().await?;
+                        }
+                        break 'game;
+                    }
+                    GameUpdate::Abandoned => {
+                        info!(
+                            game_id = game.id,
+                            url = game.url(),
+                            event = "game_abandoned",
+                            "Game abandoned."
+                        );
+                        console::game_over(&game);
+                        log_time_usage(&game, &clocks);
+                        if let Some(journal) = journal.take() {
+                            journal.finish();
+                        }
+                        if !failed {
+                            player.finish().await?;
+                        }
+                        break 'game;
+                    }
+                    GameUpdate::Offer(offer) => {
+                        info!(game_id = game.id, "The opponent asked to {offer}.");
+                        offers.received(offer, game.moves.len());
+                        None
+                    }
+                    GameUpdate::Withdraw(offer) => {
+                        info!(game_id = game.id, "The opponent withdrew their offer to {offer}.");
+                        offers.removed(offer);
+                        None
+                    }
+                    GameUpdate::Undo => {
+                        if game.is_our_turn() && !failed {
+                            player.stop().await?;
+                            player.take_comment();
+                            player.take_ponder_move();
+                        }
+
+                        let Some(undone) = game.moves.pop() else {
+                            error!(game_id = game.id, "The server took back a move that was never made.");
+                            return Err(io::Error::other(Desync));
+                        };
+                        info!(game_id = game.id, game_move = undone.to_ptn(), "Took back a move.");
+                        game.comments.remove(&game.moves.len());
+                        game.ponder = None;
+                        offers.undone();
+                        unconfirmed = None;
+                        requested = None;
+                        charged = None;
+                        if let Some(journal) = &mut journal {
+                            journal.record_undo(game.moves.len());
+                        }
+                        log_position(&game);
+                        span = move_span(&game);
+
+                        if !game.is_our_turn() {
+                            if !failed {
+                                player.ponder(&game).await?;
+                            }
+                            None
+                        } else if failed {
+                            game.fallback_move()
+                        } else {
+                            player.request_move(&game).await?;
+                            requested = Some(Instant::now());
+                            None
+                        }
+                    }
+                    GameUpdate::Other => None,
+                }
+            }
+            request = next_command(control).fuse() => {
+                let (command, reply) = request;
+                let result = game_command(
+                    command,
+                    &game,
+                    &mut playtak_writer,
+                    control,
+                    &mut options,
+                    &mut offers,
+                )
+                .await?;
+                reply.send(result).ok();
+                None
+            }
+        };
+
+        let Some(mut game_move) = game_move else {
+            continue;
+        };
+
+        // Only the move we asked for is relayed, whatever else the provider says.
+        if !game.is_our_turn() {
+            warn!(
+                game_id = game.id,
+                game_move = game_move.to_ptn(),
+                "Ignoring a move made out of turn."
+            );
+            continue;
+        }
+
+        if let Err(error) = game.check_move(&game_move) {
+            if !failed && player.reject_move(&game, &game_move, &error).await? {
+                continue;
+            }
+
+            // Losing on the board is better than losing to a broken provider.
+            player.take_comment();
+            player.take_ponder_move();
+            match game.fallback_move() {
+                Some(fallback) => {
+                    warn!(
+                        game_id = game.id,
+                        game_move = fallback.to_ptn(),
+                        "Playing a fallback move instead."
+                    );
+                    game_move = fallback;
+                }
+                None => {
+                    error!(game_id = game.id, "Resigning after repeated illegal moves.");
+                    write(&mut playtak_writer, format!("Game#{} Resign\n", game.id)).await?;
+                    continue;
+                }
+            }
+        }
+
+        if let Some(requested) = requested.take() {
+            let thought = requested.elapsed();
+            debug!(
+                parent: &span,
+                think_ms = thought.as_millis() as u64,
+                game_id = game.id,
+                game_move = game_move.to_ptn(),
+                event = "move_sent",
+                "Sending our move."
+            );
+            charged = Some((game.our_time(), thought));
+        }
+
+        write(&mut playtak_writer, game_move.to_playtak(game.id)?).await?;
+        unconfirmed = Some((Instant::now(), span.clone()));
+
+        if let Some(comment) = player.take_comment().filter(|_| !failed) {
+            game.comments.insert(game.moves.len(), comment);
+        }
+        record_move(&mut journal, &game, &game_move);
+        game.moves.push(game_move);
+        // Only a reply that's legal after our move is worth keeping.
+        game.ponder = player
+            .take_ponder_move()
+            .filter(|reply| !failed && game.check_move(reply).is_ok());
+        log_position(&game);
+        span = move_span(&game);
+
+        // Milestones share the limit on replies to the opponent, so they can't flood the chat.
+        if let Some(text) = milestones
+            .as_mut()
+            .and_then(|milestones| milestones.after_our_move(&game))
+            .filter(|_| !failed && moderation.may_reply(&game.opponent))
+        {
+            info!(
+                game_id = game.id,
+                text, "Telling {} about a milestone.", game.opponent
+            );
+            write(
+                &mut playtak_writer,
+                format!("Tell {} {text}\n", game.opponent),
+            )
+            .await?;
+        }
+
+        if !failed {
+            player.ponder(&game).await?;
+        }
+    }
+
+    Ok(game)
+}
+
+// Turns down a seek named on the command line that the engine can't play.
+fn check_seek(engine: Option<&Engine>, seek: &Seek) -> io::Result<()> {
+    let Some(engine) = engine else {
+        return Ok(());
+    };
+
+    engine.check_seek(seek).map_err(|reason| {
+        error!(
+            seek_id = seek.id,
+            reason, "{} can't play the seek's games.", engine.name
+        );
+        err!(reason)
+    })
+}
+
+// Gives up on a seek that was taken or withdrawn before the server started its game.  Only an
+// opponent named on the command line, or any seek at all, can be waited for.
+fn seek_lost(args: &ArgCommand) -> io::Result<()> {
+    match args {
+        ArgCommand::Accept(AcceptCommand {
+            accept: AcceptInfo { auto: true, .. },
+            ..
+        })
+        | ArgCommand::Seek(_) => {
+            warn!("The seek is gone.");
+            Ok(())
+        }
+        ArgCommand::Accept(AcceptCommand {
+            accept:
+                AcceptInfo {
+                    opponent: Some(opponent),
+                    ..
+                },
+            ..
+        }) => {
+            warn!("The seek is gone. Waiting for {opponent} to seek again.");
+            Ok(())
+        }
+        _ => {
+            error!("The seek was taken or withdrawn before it could be accepted.");
+            Err(err!())
+        }
+    }
+}
+
+fn move_span(game: &Game) -> Span {
+    info_span!("move", ply = game.moves.len(), to_move = %game.to_move())
+}
+
+// Carries out a command from the control API or an owner during a game.  Engine options are
+// held until the engine isn't searching, and kept for later games.  Offers that would repeat or
+// withdraw nothing, or accept one the opponent made before the last move, aren't sent.
+async fn game_command(
+    command: Command,
+    game: &Game,
+    mut playtak_writer: impl Writer,
+    control: &mut Option<Control>,
+    options: &mut Vec<(String, i32)>,
+    offers: &mut Offers,
+) -> io::Result<Result<(), &'static str>> {
+    let result = match command {
+        Command::Resign => {
+            info!(game_id = game.id, "Resigning.");
+            write(&mut playtak_writer, format!("Game#{} Resign\n", game.id)).await?;
+            Ok(())
+        }
+        Command::Offer(offer) => match offers.make(offer, game.moves.len()) {
+            Ok(()) => {
+                info!(game_id = game.id, "Asking to {offer}.");
+                let (command, _) = offer.commands();
+                write(&mut playtak_writer, format!("Game#{} {command}\n", game.id)).await?;
+                Ok(())
+            }
+            Err(reason) => Err(reason),
+        },
+        Command::Withdraw(offer) => match offers.withdraw(offer) {
+            Ok(()) => {
+                info!(game_id = game.id, "Withdrawing our offer to {offer}.");
+                let (_, command) = offer.commands();
+                write(&mut playtak_writer, format!("Game#{} {command}\n", game.id)).await?;
+                Ok(())
+            }
+            Err(reason) => Err(reason),
+        },
+        Command::Seek | Command::Unseek => Err("a game is in progress"),
+        Command::SetOption(name, value) => {
+            info!(
+                "Engine option \"{name}\" will be set to {value} once the engine isn't searching."
+            );
+            if let Some(control) = control {
+                control.options.insert(name.clone(), value);
+            }
+            options.push((name, value));
+            Ok(())
+        }
+        command => {
+            queue_command(control, command);
+            Ok(())
+        }
+    };
+
+    Ok(result)
+}
+
+// Sets the engine options changed during the game.  Options that can't be set won't be tried
+// again.
+async fn set_options(
+    player: &mut impl MoveProvider,
+    options: &mut Vec<(String, i32)>,
+    control: &mut Option<Control>,
+) {
+    for (name, value) in options.drain(..) {
+        match player.set_option(&name, value).await {
+            Ok(()) => info!("Set engine option \"{name}\" to {value}."),
+            Err(error) => {
+                warn!(%error, "Could not set engine option \"{name}\".");
+                if let Some(control) = control {
+                    control.options.remove(&name);
+                }
+            }
+        }
+    }
+}
+
+// The command in a tell from one of the bot's owners, or why it isn't one.  Every tell that
+// gets past moderation is logged.
+fn owner_command(
+    moderation: &mut Moderation,
+    chat: &ChatMessage,
+) -> Option<Result<Command, &'static str>> {
+    if chat.kind != ChatKind::Tell || !moderation.accepts(chat) {
+        return None;
+    }
+
+    info!(player = chat.player, text = chat.text, "Received a tell.");
+    moderation.is_owner(&chat.player).then(|| chat.text.parse())
+}
+
+// Tells an owner whether their command was carried out.
+async fn reply_to_owner(
+    writer: impl Writer,
+    moderation: &mut Moderation,
+    player: &str,
+    result: Result<(), &'static str>,
+) -> io::Result<()> {
+    if !moderation.may_reply(player) {
+        return Ok(());
+    }
+
+    let text = match result {
+        Ok(()) => "Done.".to_owned(),
+        Err(reason) => format!("Could not do that: {reason}."),
+    };
+    write(writer, format!("Tell {player} {text}\n")).await
+}
+
+// Takes note of a command that applies to later games.
+fn queue_command(control: &mut Option<Control>, command: Command) {
+    let Some(control) = control else {
+        return;
+    };
+
+    match command {
+        Command::SetOption(name, value) => {
+            info!("Engine option \"{name}\" will be set to {value} before the next game.");
+            control.options.insert(name, value);
+        }
+        Command::Shutdown => {
+            info!("Shutting down once no game is in progress.");
+            control.shutdown = true;
+        }
+        _ => (),
+    }
+}
+
+fn record_move(journal: &mut Option<Journal>, game: &Game, game_move: &GameMove) {
+    if let Some(journal) = journal {
+        journal.record_move(game.moves.len(), game_move);
+    }
+}
+
+// Compares what the server took off our clock for our last move with how long we took over it,
+// and lets the move provider know how much to allow for the difference from now on.  Without
+// the increment, there's no telling what the server took.
+fn check_clock(
+    game: &Game,
+    before: u32,
+    thought: Duration,
+    drift: &mut ClockDrift,
+    player: &mut impl MoveProvider,
+) {
+    let Some(increment) = game.increment else {
+        return;
+    };
+
+    let expected = i64::from(before + increment) * 1000 - thought.as_millis() as i64;
+    let reported = i64::from(game.our_time()) * 1000;
+    let overhead = drift.observe(expected - reported);
+
+    debug!(
+        game_id = game.id,
+        expected_ms = expected,
+        reported_ms = reported,
+        drift_ms = expected - reported,
+        overhead_ms = overhead,
+        event = "clock_drift",
+        "Compared our clock with the server's."
+    );
+    player.set_overhead(overhead);
+}
+
+// How both sides spent their time, to help with tuning time management.
+fn log_time_usage(game: &Game, clocks: &ClockLog) {
+    for usage in clocks.usage(game) {
+        let name = if usage.color == game.color {
+            &game.player
+        } else {
+            &game.opponent
+        };
+        let times = usage
+            .moves
+            .iter()
+            .map(|(number, time)| format!("{number}:{time}s"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (longest_move, longest) = usage.longest().unwrap_or_default();
+
+        info!(
+            game_id = game.id,
+            player = name,
+            color = %usage.color,
+            moves = usage.moves.len(),
+            average_s = format!("{:.1}", usage.average()),
+            longest_s = longest,
+            longest_move,
+            remaining_s = usage.remaining,
+            times,
+            event = "time_usage",
+            "Time used by {name}."
+        );
+        console::status(&if usage.moves.is_empty() {
+            format!("{name}: {}s left", usage.remaining)
+        } else {
+            format!(
+                "{name}: {:.1}s per move, longest {longest}s (move {longest_move}), {}s left",
+                usage.average(),
+                usage.remaining
+            )
+        });
+    }
+}
+
+// Shows the board after each move, so that a game can be followed in the logs.
+fn log_position(game: &Game) {
+    live::game(game);
+    console::game_move(game);
+
+    let (Some(last_move), Ok(board)) = (game.moves.last(), game.board()) else {
+        return;
+    };
+
+    info!(
+        game_id = game.id,
+        ply = game.moves.len(),
+        ponder = game.ponder.as_ref().map(GameMove::to_ptn),
+        event = "position",
+        "Position after {}:\n{board}",
+        last_move.to_ptn()
+    );
+}
@@ -0,0 +1,248 @@
+use std::io;
+
+use tracing::{debug, warn};
+
+use super::chat::ChatMessage;
+use super::game::{Game, GameMove, ListedGame};
+use super::offers::Offer;
+use super::seek::Seek;
+use super::{err, read, Reader};
+
+// A line from PlayTak.com, sorted by what it means to the client.
+#[derive(Debug)]
+pub enum Message {
+    SeekNew(Seek),
+    SeekRemove(u32),
+    GameStart(Game),
+    Game { id: u32, update: GameUpdate },
+    GameListAdd(ListedGame),
+    // Tells, Shouts, and the like.
+    Chat(ChatMessage),
+    // A "Message" line, which the server uses for notices.
+    Notice(String),
+    // How many players are online.
+    Online(u32),
+    Error(String),
+    Ok,
+    Nok,
+    // Anything the client has no use for, like the user list.
+    Other(String),
+}
+
+#[derive(Debug)]
+pub enum GameUpdate {
+    Move(GameMove),
+    Time(u32, u32),
+    Over(String),
+    Abandoned,
+    // The opponent made or took back an offer.
+    Offer(Offer),
+    Withdraw(Offer),
+    // Both players asked for the last move to be taken back.
+    Undo,
+    // A move that couldn't be parsed, with the reason.  Since there's no telling what was
+    // played, the game can't be followed past it.
+    InvalidMove(String),
+    Other,
+}
+
+impl Message {
+    // Never fails.  A line that can't be made sense of is logged and treated as Other, except
+    // for a game's moves, which can't be skipped without losing track of the game.
+    pub fn parse(line: &str) -> Self {
+        match Self::classify(line) {
+            Ok(Self::Other(line)) => {
+                debug!(line, "Ignoring unrecognized message.");
+                Self::Other(line)
+            }
+            Ok(message) => {
+                if let Self::Game {
+                    update: GameUpdate::InvalidMove(error),
+                    ..
+                } = &message
+                {
+                    warn!(error, line, "Could not parse move.");
+                }
+                message
+            }
+            Err(error) => {
+                warn!(%error, line, "Could not parse message.");
+                Self::Other(line.to_owned())
+            }
+        }
+    }
+
+    fn classify(line: &str) -> io::Result<Self> {
+        let mut words = line.split_ascii_whitespace();
+        let first = words.next().unwrap_or_default();
+        let second = words.next();
+
+        // Everything after the first word.
+        let rest = || {
+            line.trim_start()
+                .split_once(' ')
+                .map_or("", |(_, rest)| rest.trim())
+                .to_owned()
+        };
+
+        let message = match (first, second) {
+            ("Seek", Some("new")) => Self::SeekNew(line.parse().map_err(|e: &str| err!(e))?),
+            ("GameList", Some("Add")) => {
+                Self::GameListAdd(line.parse().map_err(|e: &str| err!(e))?)
+            }
+            ("Seek", Some("remove")) => Self::SeekRemove(
+                words
+                    .next()
+                    .and_then(|id| id.parse().ok())
+                    .ok_or_else(|| err!("invalid seek number"))?,
+            ),
+            ("Game", Some("Start")) => Self::GameStart(line.parse().map_err(|e: &str| err!(e))?),
+            ("Tell" | "Told" | "Shout" | "ShoutRoom", Some(_)) => match line.parse() {
+                Ok(chat) => Self::Chat(chat),
+                Err(_) => Self::Other(line.to_owned()),
+            },
+            ("Message", _) => Self::Notice(rest()),
+            ("Online", Some(count)) => {
+                Self::Online(count.parse().map_err(|_| err!("invalid player count"))?)
+            }
+            ("Error", _) => Self::Error(rest()),
+            ("OK", None) => Self::Ok,
+            ("NOK", None) => Self::Nok,
+            (first, Some(kind)) if first.starts_with("Game#") => {
+                let id = first["Game#".len()..]
+                    .parse()
+                    .map_err(|_| err!("invalid game id"))?;
+
+                let update = match kind {
+                    "P" | "M" => match GameMove::from_playtak(line) {
+                        Ok(game_move) => GameUpdate::Move(game_move),
+                        Err(error) => GameUpdate::InvalidMove(error.to_string()),
+                    },
+                    "Time" => {
+                        let mut time = || {
+                            words
+                                .next()
+                                .and_then(|t| t.parse().ok())
+                                .ok_or_else(|| err!("invalid time"))
+                        };
+                        GameUpdate::Time(time()?, time()?)
+                    }
+                    "Over" => GameUpdate::Over(words.next().unwrap_or_default().to_owned()),
+                    "Abandoned" | "Abandoned." => GameUpdate::Abandoned,
+                    "OfferDraw" => GameUpdate::Offer(Offer::Draw),
+                    "RemoveDraw" => GameUpdate::Withdraw(Offer::Draw),
+                    "RequestUndo" => GameUpdate::Offer(Offer::Undo),
+                    "RemoveUndo" => GameUpdate::Withdraw(Offer::Undo),
+                    "Undo" => GameUpdate::Undo,
+                    _ => {
+                        debug!(line, "Ignoring unrecognized game message.");
+                        GameUpdate::Other
+                    }
+                };
+
+                Self::Game { id, update }
+            }
+            _ => Self::Other(line.to_owned()),
+        };
+
+        Ok(message)
+    }
+}
+
+pub async fn read_message(reader: impl Reader) -> io::Result<Message> {
+    read(reader).await.map(|line| Message::parse(&line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_messages() {
+        assert!(matches!(
+            Message::parse("Game#12 P A1 C"),
+            Message::Game {
+                id: 12,
+                update: GameUpdate::Move(GameMove::Place { x: 0, y: 0, .. })
+            }
+        ));
+        assert!(matches!(
+            Message::parse("Game#12 P A1 X"),
+            Message::Game {
+                id: 12,
+                update: GameUpdate::InvalidMove(_)
+            }
+        ));
+        assert!(matches!(
+            Message::parse("Game#12 Time 170 165"),
+            Message::Game {
+                id: 12,
+                update: GameUpdate::Time(170, 165)
+            }
+        ));
+        assert!(matches!(
+            Message::parse("Game#12 Over 0-R"),
+            Message::Game { id: 12, update: GameUpdate::Over(result) } if result == "0-R"
+        ));
+        assert!(matches!(
+            Message::parse("Game#12 Abandoned. Bob quit"),
+            Message::Game {
+                id: 12,
+                update: GameUpdate::Abandoned
+            }
+        ));
+        assert!(matches!(
+            Message::parse("Message Your game is resumed"),
+            Message::Notice(message) if message == "Your game is resumed"
+        ));
+        assert!(matches!(
+            Message::parse("Seek remove 4"),
+            Message::SeekRemove(4)
+        ));
+        assert!(matches!(
+            Message::parse("Game#12 RemoveDraw"),
+            Message::Game {
+                id: 12,
+                update: GameUpdate::Withdraw(Offer::Draw)
+            }
+        ));
+        assert!(matches!(Message::parse("NOK"), Message::Nok));
+        assert!(matches!(Message::parse("Online 42"), Message::Online(42)));
+        assert!(matches!(Message::parse("Shout <Bob> hi"), Message::Chat(_)));
+
+        // Nothing the server sends should be able to cause a panic.
+        for line in [
+            "",
+            " ",
+            "Game#",
+            "Game#12",
+            "Game#12 P",
+            "Game#12 M A1",
+            "Game#1 M A1 C1 12 1",
+            "Game#1 M A1 B1 4294967295",
+            "Game#x Time 1 2",
+            "Game#12 Time 1",
+            "Game Start",
+            "Game Start 1 5 a vs b white",
+            "Seek new 1",
+            "Seek new 1 Bob 5 600 10 X 0 21 1 0 0 0 0",
+            "Seek remove",
+            "Message",
+            "OnlineUsers 5",
+            "GameList Add 1 a b",
+        ] {
+            let message = Message::parse(line);
+            if let Message::Game {
+                update: GameUpdate::Move(game_move),
+                ..
+            } = &message
+            {
+                game_move.to_ptn();
+            }
+            assert!(matches!(
+                message,
+                Message::Other(_) | Message::Notice(_) | Message::Game { .. }
+            ));
+        }
+    }
+}
@@ -0,0 +1,271 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+
+use super::game::{Game, GameMove, PieceType};
+
+const CAPSTONE_TEMPLATE: &str = "Here comes the capstone!";
+const WINNING_TEMPLATE: &str = "I like my position here.";
+const LOSING_TEMPLATE: &str = "Things aren't looking good for me.";
+const ROAD_THREAT_TEMPLATE: &str = "Watch out for that road!";
+
+// Telling the opponent about turning points in the game, for bots that are meant to be fun to
+// play.  An empty message leaves its milestone out.
+#[derive(Args, Clone, Debug, Default)]
+pub struct MilestoneChat {
+    #[arg(long, action)]
+    pub milestone_chat: bool,
+    #[arg(long, value_name = "TEXT", requires = "milestone_chat")]
+    pub capstone_message: Option<String>,
+    #[arg(long, value_name = "TEXT", requires = "milestone_chat")]
+    pub winning_message: Option<String>,
+    #[arg(long, value_name = "TEXT", requires = "milestone_chat")]
+    pub losing_message: Option<String>,
+    #[arg(long, value_name = "TEXT", requires = "milestone_chat")]
+    pub road_threat_message: Option<String>,
+    #[arg(
+        long,
+        value_name = "CENTIPAWNS",
+        default_value_t = 300,
+        value_parser = clap::value_parser!(i32).range(1..),
+        requires = "milestone_chat"
+    )]
+    pub eval_threshold: i32,
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 120,
+        requires = "milestone_chat"
+    )]
+    pub milestone_interval: u64,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum Milestone {
+    RoadThreat,
+    Winning,
+    Losing,
+    Capstone,
+}
+
+// The milestones of one game.  Each is only spoken of once, and the rest that are reached too
+// soon after one are passed over rather than saved up.
+#[derive(Debug)]
+pub struct Milestones {
+    settings: MilestoneChat,
+    reached: HashSet<Milestone>,
+    last: Option<Instant>,
+}
+
+impl Milestones {
+    pub fn new(settings: &MilestoneChat) -> Option<Self> {
+        settings.milestone_chat.then(|| Self {
+            settings: settings.clone(),
+            reached: HashSet::new(),
+            last: None,
+        })
+    }
+
+    // What to tell the opponent after our last move, if it reached a milestone and the last
+    // message was long enough ago.
+    pub fn after_our_move(&mut self, game: &Game) -> Option<String> {
+        let new = self
+            .reached(game)
+            .into_iter()
+            .filter(|&milestone| self.reached.insert(milestone))
+            .collect::<Vec<_>>();
+
+        let interval = Duration::from_secs(self.settings.milestone_interval);
+        if self.last.is_some_and(|last| last.elapsed() < interval) {
+            return None;
+        }
+
+        let template = new
+            .into_iter()
+            .find_map(|milestone| self.template(milestone))?;
+        let text = fill(template, game);
+        self.last = Some(Instant::now());
+        Some(text)
+    }
+
+    // The milestones reached as of our last move, most interesting first.
+    fn reached(&self, game: &Game) -> Vec<Milestone> {
+        let mut reached = Vec::new();
+
+        if let Ok(board) = game.board() {
+            if board.has_road_threat(game.color) && !board.has_road(game.color) {
+                reached.push(Milestone::RoadThreat);
+            }
+        }
+
+        let eval = game
+            .moves
+            .len()
+            .checked_sub(1)
+            .and_then(|index| game.comments.get(&index))
+            .and_then(|comment| centipawns(comment));
+        match eval {
+            Some(eval) if eval >= self.settings.eval_threshold => reached.push(Milestone::Winning),
+            Some(eval) if eval <= -self.settings.eval_threshold => reached.push(Milestone::Losing),
+            _ => (),
+        }
+
+        if let Some(GameMove::Place {
+            piece_type: PieceType::Capstone,
+            ..
+        }) = game.moves.last()
+        {
+            reached.push(Milestone::Capstone);
+        }
+
+        reached
+    }
+
+    fn template(&self, milestone: Milestone) -> Option<&str> {
+        let (message, default) = match milestone {
+            Milestone::RoadThreat => (&self.settings.road_threat_message, ROAD_THREAT_TEMPLATE),
+            Milestone::Winning => (&self.settings.winning_message, WINNING_TEMPLATE),
+            Milestone::Losing => (&self.settings.losing_message, LOSING_TEMPLATE),
+            Milestone::Capstone => (&self.settings.capstone_message, CAPSTONE_TEMPLATE),
+        };
+
+        Some(message.as_deref().unwrap_or(default)).filter(|text| !text.is_empty())
+    }
+}
+
+// The score at the start of a move's comment, like "+0.85/17 3.2s", in centipawns.  A mate
+// counts as far past any threshold.
+fn centipawns(comment: &str) -> Option<i32> {
+    let score = comment.split(['/', ' ']).next()?;
+
+    if score.starts_with("+M") {
+        Some(i32::MAX)
+    } else if score.starts_with("-M") {
+        Some(-i32::MAX)
+    } else {
+        score
+            .parse::<f64>()
+            .ok()
+            .map(|flats| (flats * 100.0).round() as i32)
+    }
+}
+
+// Fills in the template's placeholders: {opponent}, {name}, {move}, and {eval}.
+fn fill(template: &str, game: &Game) -> String {
+    let last = game.moves.len().checked_sub(1);
+    let game_move = last
+        .and_then(|index| game.moves.get(index))
+        .map(GameMove::to_ptn)
+        .unwrap_or_default();
+    let eval = last
+        .and_then(|index| game.comments.get(&index))
+        .and_then(|comment| comment.split(['/', ' ']).next())
+        .unwrap_or_default();
+
+    template
+        .replace("{opponent}", &game.opponent)
+        .replace("{name}", &game.player)
+        .replace("{move}", &game_move)
+        .replace("{eval}", eval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> MilestoneChat {
+        MilestoneChat {
+            milestone_chat: true,
+            eval_threshold: 300,
+            milestone_interval: 120,
+            ..Default::default()
+        }
+    }
+
+    fn push(game: &mut Game, ptn: &str, comment: Option<&str>) {
+        let game_move = GameMove::from_ptn(ptn).unwrap();
+        game.check_move(&game_move).unwrap();
+        if let Some(comment) = comment {
+            game.comments.insert(game.moves.len(), comment.to_owned());
+        }
+        game.moves.push(game_move);
+    }
+
+    #[test]
+    fn fill_template() {
+        let mut game = "Game Start 1 5 Alice vs Bob white 600 0 30 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+        push(&mut game, "a1", Some("+3.50/12 1.0s"));
+        assert_eq!(
+            fill("{name} plays {move} at {eval} against {opponent}", &game),
+            "Alice plays a1 at +3.50 against Bob"
+        );
+        assert_eq!(centipawns("+3.50/12 1.0s"), Some(350));
+        assert_eq!(centipawns("-M2 0.1s"), Some(-i32::MAX));
+        assert_eq!(centipawns("book"), None);
+    }
+
+    #[test]
+    fn reach_milestones() {
+        assert!(Milestones::new(&MilestoneChat::default()).is_none());
+
+        let mut game = "Game Start 1 5 Alice vs Bob white 600 0 30 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+        let mut settings = settings();
+        settings.milestone_interval = 0;
+        settings.losing_message = Some(String::new());
+        let mut milestones = Milestones::new(&settings).unwrap();
+
+        push(&mut game, "e5", Some("+0.10/10 1.0s"));
+        assert_eq!(milestones.after_our_move(&game), None);
+
+        // A losing position has its message turned off.
+        push(&mut game, "a5", None);
+        push(&mut game, "Cc3", Some("-4.00/10 1.0s"));
+        assert_eq!(
+            milestones.after_our_move(&game).as_deref(),
+            Some(CAPSTONE_TEMPLATE)
+        );
+
+        push(&mut game, "e4", None);
+        push(&mut game, "a1", Some("+M3 1.0s"));
+        assert_eq!(
+            milestones.after_our_move(&game).as_deref(),
+            Some(WINNING_TEMPLATE)
+        );
+
+        for ptn in ["e3", "b1", "e2", "c1", "b2"] {
+            push(&mut game, ptn, None);
+        }
+        push(&mut game, "d1", None);
+        assert_eq!(
+            milestones.after_our_move(&game).as_deref(),
+            Some(ROAD_THREAT_TEMPLATE)
+        );
+
+        // Once is enough for each.
+        push(&mut game, "c2", None);
+        push(&mut game, "a3", Some("+M1 1.0s"));
+        assert_eq!(milestones.after_our_move(&game), None);
+    }
+
+    #[test]
+    fn wait_between_messages() {
+        let mut game = "Game Start 1 5 Alice vs Bob white 600 0 30 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+        let mut milestones = Milestones::new(&settings()).unwrap();
+
+        push(&mut game, "a1", None);
+        push(&mut game, "e5", None);
+        push(&mut game, "Cc3", None);
+        assert!(milestones.after_our_move(&game).is_some());
+
+        push(&mut game, "e4", None);
+        push(&mut game, "b1", Some("+5.00/10 1.0s"));
+        assert_eq!(milestones.after_our_move(&game), None);
+    }
+}
@@ -0,0 +1,343 @@
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::warn;
+
+use super::game::Game;
+use super::{err, runtime};
+
+// Notifications are sent in the background, but a stuck service shouldn't pile them up.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How often the end of a session checks whether notifications are still being delivered.
+const FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+
+// Something worth telling whoever runs the bot about.
+#[derive(Debug)]
+pub struct Notification {
+    // A name for what happened, like "game_start", for services that take structured data.
+    pub event: &'static str,
+    pub text: String,
+    pub game: Value,
+}
+
+impl Notification {
+    pub fn game_start(game: &Game) -> Self {
+        let (white, black) = game.white_and_black();
+
+        Self {
+            event: "game_start",
+            text: format!(
+                "Game #{} started: {white} vs {black}, {size}x{size}. {}",
+                game.id,
+                game.url(),
+                size = game.size
+            ),
+            game: game_json(game),
+        }
+    }
+
+    pub fn game_over(game: &Game) -> Self {
+        let (white, black) = game.white_and_black();
+        let result = game.result.as_deref().unwrap_or("abandoned");
+
+        Self {
+            event: "game_over",
+            text: format!(
+                "Game #{} over: {white} vs {black}, {result}. {}",
+                game.id,
+                game.url()
+            ),
+            game: game_json(game),
+        }
+    }
+}
+
+fn game_json(game: &Game) -> Value {
+    let (white, black) = game.white_and_black();
+
+    json!({
+        "id": game.id,
+        "size": game.size,
+        "white": white,
+        "black": black,
+        "color": game.color.to_string(),
+        "moves": game.moves.len(),
+        "result": game.result,
+        "rated": !game.unrated,
+        "tournament": game.tournament,
+        "url": game.url(),
+    })
+}
+
+// A chat system or service that notifications can be sent to.  Each one only has to say how a
+// notification is delivered to it.
+pub trait Notifier: fmt::Debug + Send + Sync {
+    fn request(&self, notification: &Notification) -> WebRequest;
+}
+
+#[derive(Debug)]
+pub struct WebRequest {
+    pub method: &'static str,
+    pub url: String,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Value,
+}
+
+impl WebRequest {
+    fn post(url: String, body: Value) -> Self {
+        Self {
+            method: "POST",
+            url,
+            headers: Vec::new(),
+            body,
+        }
+    }
+}
+
+// How a notifier is set up in the configuration file, chosen by its kind.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum NotifierConfig {
+    Discord {
+        webhook_url: String,
+    },
+    Webhook {
+        url: String,
+    },
+    Matrix {
+        homeserver: String,
+        room_id: String,
+        access_token: String,
+    },
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+    },
+}
+
+impl NotifierConfig {
+    pub fn build(&self) -> Arc<dyn Notifier> {
+        match self.clone() {
+            Self::Discord { webhook_url } => Arc::new(Discord { webhook_url }),
+            Self::Webhook { url } => Arc::new(Webhook { url }),
+            Self::Matrix {
+                homeserver,
+                room_id,
+                access_token,
+            } => Arc::new(Matrix {
+                homeserver,
+                room_id,
+                access_token,
+            }),
+            Self::Telegram { bot_token, chat_id } => Arc::new(Telegram { bot_token, chat_id }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Discord {
+    webhook_url: String,
+}
+
+impl Notifier for Discord {
+    fn request(&self, notification: &Notification) -> WebRequest {
+        WebRequest::post(
+            self.webhook_url.clone(),
+            json!({ "content": notification.text }),
+        )
+    }
+}
+
+// Anything that takes a JSON POST, sent everything there is to know.
+#[derive(Debug)]
+struct Webhook {
+    url: String,
+}
+
+impl Notifier for Webhook {
+    fn request(&self, notification: &Notification) -> WebRequest {
+        WebRequest::post(
+            self.url.clone(),
+            json!({
+                "event": notification.event,
+                "text": notification.text,
+                "game": notification.game,
+            }),
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Matrix {
+    homeserver: String,
+    room_id: String,
+    access_token: String,
+}
+
+impl Notifier for Matrix {
+    fn request(&self, notification: &Notification) -> WebRequest {
+        // Matrix drops a message whose transaction id was already used, so each one is new.
+        static TRANSACTIONS: AtomicU64 = AtomicU64::new(0);
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let transaction = format!(
+            "playtak-tei-{nanos}-{}",
+            TRANSACTIONS.fetch_add(1, Ordering::Relaxed)
+        );
+
+        WebRequest {
+            method: "PUT",
+            url: format!(
+                "{}/_matrix/client/v3/rooms/{}/send/m.room.message/{transaction}",
+                self.homeserver.trim_end_matches('/'),
+                encode(&self.room_id),
+            ),
+            headers: vec![("Authorization", format!("Bearer {}", self.access_token))],
+            body: json!({ "msgtype": "m.notice", "body": notification.text }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Telegram {
+    bot_token: String,
+    chat_id: String,
+}
+
+impl Notifier for Telegram {
+    fn request(&self, notification: &Notification) -> WebRequest {
+        WebRequest::post(
+            format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token),
+            json!({ "chat_id": self.chat_id, "text": notification.text }),
+        )
+    }
+}
+
+// Percent-encodes everything but unreserved characters, for room ids like !abc:matrix.org.
+fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+// The notifiers a session sends to.  Sending never holds up the game, and a failure to
+// deliver is only logged.
+#[derive(Clone, Debug, Default)]
+pub struct Notifiers {
+    notifiers: Vec<Arc<dyn Notifier>>,
+    // How many notifications are still being delivered.
+    pending: Arc<AtomicUsize>,
+}
+
+impl Notifiers {
+    pub fn new(configs: &[NotifierConfig]) -> Self {
+        Self {
+            notifiers: configs.iter().map(NotifierConfig::build).collect(),
+            pending: Default::default(),
+        }
+    }
+
+    pub fn send(&self, notification: Notification) {
+        for notifier in &self.notifiers {
+            let request = notifier.request(&notification);
+            let pending = self.pending.clone();
+
+            pending.fetch_add(1, Ordering::SeqCst);
+            runtime::spawn(async move {
+                if let Err(error) = deliver(request).await {
+                    warn!(%error, "Could not send a notification.");
+                }
+                pending.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    }
+
+    // Waits for notifications that are still being delivered, so that the last ones aren't
+    // lost when the session ends.
+    pub async fn flush(&self) {
+        runtime::timeout(NOTIFY_TIMEOUT, async {
+            while self.pending.load(Ordering::SeqCst) > 0 {
+                runtime::sleep(FLUSH_INTERVAL).await;
+            }
+        })
+        .await;
+    }
+}
+
+async fn deliver(request: WebRequest) -> io::Result<()> {
+    runtime::spawn_blocking(move || {
+        let agent = ureq::AgentBuilder::new().timeout(NOTIFY_TIMEOUT).build();
+
+        let mut call = agent.request(request.method, &request.url);
+        for (name, value) in &request.headers {
+            call = call.set(name, value);
+        }
+
+        call.send_json(request.body)
+            .map(|_| ())
+            .map_err(|error| err!(error.to_string()))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_requests() {
+        let game = "Game Start 7 5 Alice vs Bob white 600 0 21 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+        let notification = Notification::game_start(&game);
+        assert_eq!(
+            notification.text,
+            "Game #7 started: Alice vs Bob, 5x5. https://playtak.com/games/7/view"
+        );
+
+        let config = toml::from_str::<NotifierConfig>(
+            r#"
+            kind = "matrix"
+            homeserver = "https://matrix.org/"
+            room_id = "!room:matrix.org"
+            access_token = "secret"
+            "#,
+        )
+        .unwrap();
+        let request = config.build().request(&notification);
+        assert_eq!(request.method, "PUT");
+        assert!(request
+            .url
+            .starts_with("https://matrix.org/_matrix/client/v3/rooms/%21room%3Amatrix.org/send/"));
+        assert_eq!(
+            request.headers,
+            [("Authorization", "Bearer secret".to_owned())]
+        );
+
+        let config = NotifierConfig::Telegram {
+            bot_token: "123:abc".to_owned(),
+            chat_id: "42".to_owned(),
+        };
+        let request = config.build().request(&notification);
+        assert_eq!(
+            request.url,
+            "https://api.telegram.org/bot123:abc/sendMessage"
+        );
+        assert_eq!(request.body["chat_id"], "42");
+
+        assert!(toml::from_str::<NotifierConfig>("kind = \"pager\"").is_err());
+    }
+}
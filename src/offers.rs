@@ -0,0 +1,120 @@
+use std::fmt;
+
+// Something either player can put forward during a game, which happens once both have.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Offer {
+    Draw,
+    Undo,
+}
+
+impl Offer {
+    // The command that makes the offer, and the one that takes it back.
+    pub fn commands(self) -> (&'static str, &'static str) {
+        match self {
+            Offer::Draw => ("OfferDraw", "RemoveDraw"),
+            Offer::Undo => ("RequestUndo", "RemoveUndo"),
+        }
+    }
+}
+
+impl fmt::Display for Offer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Offer::Draw => write!(f, "draw"),
+            Offer::Undo => write!(f, "undo"),
+        }
+    }
+}
+
+// Which side has an offer standing, and at which ply the opponent made theirs.
+#[derive(Debug, Default)]
+struct OfferState {
+    ours: bool,
+    theirs: Option<usize>,
+}
+
+// The draw offers and undo requests standing in a game, as the server sees them.  Making an
+// offer while the opponent's stands accepts it, so an offer is never made twice, and never made
+// to accept one from before the last move.
+#[derive(Debug, Default)]
+pub struct Offers {
+    draw: OfferState,
+    undo: OfferState,
+}
+
+impl Offers {
+    fn state(&mut self, offer: Offer) -> &mut OfferState {
+        match offer {
+            Offer::Draw => &mut self.draw,
+            Offer::Undo => &mut self.undo,
+        }
+    }
+
+    // Checks that the offer can be made at the given ply, and takes note of it if so.
+    pub fn make(&mut self, offer: Offer, ply: usize) -> Result<(), &'static str> {
+        if offer == Offer::Undo && ply == 0 {
+            return Err("there is no move to take back");
+        }
+
+        let state = self.state(offer);
+        if state.ours {
+            return Err("the offer was already made");
+        }
+        if state.theirs.is_some_and(|theirs| theirs != ply) {
+            return Err("the opponent's offer was made before the last move");
+        }
+
+        state.ours = true;
+        Ok(())
+    }
+
+    pub fn withdraw(&mut self, offer: Offer) -> Result<(), &'static str> {
+        let state = self.state(offer);
+        if !state.ours {
+            return Err("there is no offer to withdraw");
+        }
+
+        state.ours = false;
+        Ok(())
+    }
+
+    pub fn received(&mut self, offer: Offer, ply: usize) {
+        self.state(offer).theirs = Some(ply);
+    }
+
+    pub fn removed(&mut self, offer: Offer) {
+        self.state(offer).theirs = None;
+    }
+
+    // Both sides' undo requests are used up once a move is taken back.
+    pub fn undone(&mut self) {
+        self.undo = OfferState::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offer_state() {
+        let mut offers = Offers::default();
+
+        assert!(offers.make(Offer::Undo, 0).is_err());
+        assert!(offers.make(Offer::Draw, 4).is_ok());
+        assert!(offers.make(Offer::Draw, 4).is_err());
+        assert!(offers.withdraw(Offer::Draw).is_ok());
+        assert!(offers.withdraw(Offer::Draw).is_err());
+
+        offers.received(Offer::Undo, 5);
+        assert!(offers.make(Offer::Undo, 6).is_err());
+        assert!(offers.make(Offer::Undo, 5).is_ok());
+
+        offers.undone();
+        assert!(offers.make(Offer::Undo, 4).is_ok());
+
+        offers.received(Offer::Draw, 7);
+        offers.removed(Offer::Draw);
+        assert!(offers.make(Offer::Draw, 9).is_ok());
+    }
+}
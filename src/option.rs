@@ -2,7 +2,7 @@ use std::io;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 
-use async_std::io::WriteExt;
+use futures::AsyncWrite;
 use tracing::{debug, error, warn};
 
 use super::{err, write};
@@ -14,49 +14,56 @@ pub struct SpinOption {
     pub range: RangeInclusive<i32>,
 }
 
+// The words that start a field of an option line.  Everything up to the next one belongs to the
+// field, so names can have spaces in them.
+const OPTION_FIELDS: [&str; 5] = ["name", "type", "default", "min", "max"];
+
+// Parses a line like "option name Hash Size type spin default 64 min 1 max 1024".  The fields
+// can come in any order, and without a min or max, the range is open on that side.
 impl FromStr for SpinOption {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut option = SpinOption {
-            name: String::new(),
-            default: 0,
-            range: 0..=0,
+        let mut fields: Vec<(&str, Vec<&str>)> = Vec::new();
+        for part in s.split_ascii_whitespace() {
+            if OPTION_FIELDS.contains(&part) {
+                fields.push((part, Vec::new()));
+            } else if let Some((_, values)) = fields.last_mut() {
+                values.push(part);
+            }
+        }
+
+        let field = |key: &str| {
+            fields
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, values)| values.join(" "))
+        };
+        let integer = |key: &str| {
+            field(key)
+                .map(|value| value.parse::<i32>().map_err(|_| "expected an integer"))
+                .transpose()
         };
 
-        let mut parts = s.split_ascii_whitespace();
-        while let Some(part) = parts.next() {
-            match part {
-                "name" => option.name = parts.next().ok_or("expected option name")?.to_owned(),
-                "type" => assert_eq!(parts.next(), Some("spin"), "expected type spin"),
-                "default" => {
-                    option.default = parts
-                        .next()
-                        .ok_or("expected option default")?
-                        .parse::<i32>()
-                        .map_err(|_| "expected an integer")?
-                }
-                "min" => {
-                    option.range = parts
-                        .next()
-                        .ok_or("expected option min")?
-                        .parse::<i32>()
-                        .map_err(|_| "expected an integer")?
-                        ..=*option.range.end()
-                }
-                "max" => {
-                    option.range = *option.range.start()
-                        ..=parts
-                            .next()
-                            .ok_or("expected option max")?
-                            .parse::<i32>()
-                            .map_err(|_| "expected an integer")?
-                }
-                _ => (),
-            }
+        if field("type").as_deref() != Some("spin") {
+            return Err("expected type spin");
         }
 
-        Ok(option)
+        let name = field("name")
+            .filter(|name| !name.is_empty())
+            .ok_or("expected option name")?;
+        let default = integer("default")?.ok_or("expected option default")?;
+        let min = integer("min")?.unwrap_or(i32::MIN);
+        let max = integer("max")?.unwrap_or(i32::MAX);
+        if min > max {
+            return Err("option min is greater than its max");
+        }
+
+        Ok(SpinOption {
+            name,
+            default,
+            range: min..=max,
+        })
     }
 }
 
@@ -74,15 +81,37 @@ impl SpinOption {
     }
 }
 
+// Whether a game option can take the value: it has to be in the engine's range, or at the
+// assumed default if the engine doesn't have the option.
+pub fn check_option(
+    options: &[SpinOption],
+    name: &str,
+    value: i32,
+    default: i32,
+) -> Result<(), String> {
+    match options.iter().find(|o| o.name == name) {
+        Some(option) if !option.valid_value(value) => Err(format!(
+            "{name} {value} is outside the engine's range of {}..={}",
+            option.range.start(),
+            option.range.end()
+        )),
+        None if value != default => Err(format!(
+            "{name} {value} needs the {name} option, which the engine doesn't have"
+        )),
+        _ => Ok(()),
+    }
+}
+
 pub async fn validate_and_set_option(
-    writer: impl WriteExt + Unpin,
+    writer: impl AsyncWrite + Unpin,
     options: &[SpinOption],
     name: &str,
     value: i32,
     default: i32, // A global default to use if the engine doesn't provide its own.
+    force: bool,  // Set the option even if it's at the engine's default.
 ) -> io::Result<()> {
     if let Some(option) = options.iter().find(|o| o.name == name) {
-        if value != option.default {
+        if force || value != option.default {
             write(writer, option.to_tei_string(value)).await?;
         } else {
             debug!(
@@ -96,3 +125,53 @@ pub async fn validate_and_set_option(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_spin_options() {
+        let option = "option name Hash type spin default 64 min 1 max 1024"
+            .parse::<SpinOption>()
+            .unwrap();
+        assert_eq!(option.name, "Hash");
+        assert_eq!(option.default, 64);
+        assert_eq!(option.range, 1..=1024);
+
+        let option = "option max 8 default 2 type spin name Move Overhead"
+            .parse::<SpinOption>()
+            .unwrap();
+        assert_eq!(option.name, "Move Overhead");
+        assert_eq!(option.range, i32::MIN..=8);
+
+        assert!("option name Ponder type check default false"
+            .parse::<SpinOption>()
+            .is_err());
+        assert!("option name Threads type spin min 1 max 4"
+            .parse::<SpinOption>()
+            .is_err());
+        assert!("option name Threads type spin default x"
+            .parse::<SpinOption>()
+            .is_err());
+        assert!("option name Threads type spin default 1 min 4 max 2"
+            .parse::<SpinOption>()
+            .is_err());
+        assert!("option type spin default 1".parse::<SpinOption>().is_err());
+    }
+
+    #[test]
+    fn check_options() {
+        let options = ["option name HalfKomi type spin default 0 min -8 max 8"
+            .parse::<SpinOption>()
+            .unwrap()];
+
+        assert!(check_option(&options, "HalfKomi", 4, 0).is_ok());
+        assert!(check_option(&options, "HalfKomi", 9, 0).is_err());
+        assert!(check_option(&options, "Capstones", 1, 1).is_ok());
+        assert_eq!(
+            check_option(&options, "Capstones", 2, 1).unwrap_err(),
+            "Capstones 2 needs the Capstones option, which the engine doesn't have"
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use std::io;
+
+use super::game::{Game, GameMove};
+use super::runtime::{stdin_lines, StdinReader};
+use super::{err, read};
+
+// The side of a game that decides our moves.
+pub trait MoveProvider {
+    // Called once before the first move of a game.
+    async fn start(&mut self, game: &Game) -> io::Result<()>;
+
+    // Asks for a move in the game's current position, to be returned later by next_move.
+    async fn request_move(&mut self, game: &Game) -> io::Result<()>;
+
+    // Waits for the provider's next output, which may or may not be a move.  This must be
+    // safe to cancel, since it's raced against input from the server.
+    async fn next_move(&mut self) -> io::Result<Option<GameMove>>;
+
+    // Called when the provider's move isn't legal.  Returns false if the provider should
+    // give up on the game.
+    async fn reject_move(
+        &mut self,
+        game: &Game,
+        game_move: &GameMove,
+        error: &io::Error,
+    ) -> io::Result<bool>;
+
+    // Thinks about the position while the opponent decides their move, for providers that do.
+    // Whatever comes of it is given up by the next request_move, stop, or finish.
+    async fn ponder(&mut self, _game: &Game) -> io::Result<()> {
+        Ok(())
+    }
+
+    // Gives up on a move that's still being decided.  Once this returns, next_move won't
+    // return that move.
+    async fn stop(&mut self) -> io::Result<()>;
+
+    // Called once the game is over, whether or not a move is still being decided.
+    async fn finish(&mut self) -> io::Result<()>;
+
+    // Changes one of the provider's options.  This is only called while no move is being
+    // decided.
+    async fn set_option(&mut self, name: &str, value: i32) -> io::Result<()>;
+
+    // A comment on the move next_move last returned, like the engine's evaluation of it.
+    fn take_comment(&mut self) -> Option<String> {
+        None
+    }
+
+    // The reply the provider expects to the move next_move last returned.
+    fn take_ponder_move(&mut self) -> Option<GameMove> {
+        None
+    }
+
+    // Whether the provider resigned instead of returning a move from next_move.
+    fn take_resignation(&mut self) -> bool {
+        false
+    }
+
+    // How many milliseconds the server takes off our clock for each move beyond the time
+    // spent deciding it, to be left out of the time the provider thinks it has.
+    fn set_overhead(&mut self, _overhead: u64) {}
+}
+
+// Relays moves typed in PTN on stdin.
+pub struct Human {
+    input: StdinReader,
+    to_move: bool,
+}
+
+impl Human {
+    pub fn new() -> Self {
+        Self {
+            input: stdin_lines(),
+            to_move: false,
+        }
+    }
+}
+
+impl MoveProvider for Human {
+    async fn start(&mut self, game: &Game) -> io::Result<()> {
+        println!(
+            "Playing {0}x{0} as {1} against {2}. Enter moves in PTN.",
+            game.size, game.color, game.opponent
+        );
+        Ok(())
+    }
+
+    async fn request_move(&mut self, game: &Game) -> io::Result<()> {
+        if let Some(last_move) = game.ptn_moves().last() {
+            println!("{} played {last_move}.", game.opponent);
+        }
+
+        if let Ok(board) = game.board() {
+            println!("\n{board}\n");
+        }
+
+        let time = game.our_time();
+
+        println!("Your move ({}:{:02} remaining):", time / 60, time % 60);
+
+        self.to_move = true;
+        Ok(())
+    }
+
+    async fn next_move(&mut self) -> io::Result<Option<GameMove>> {
+        let line = read(&mut self.input).await?;
+        let line = line.trim();
+
+        if line.is_empty() {
+            return Ok(None);
+        }
+
+        if !self.to_move {
+            println!("It's not your turn.");
+            return Ok(None);
+        }
+
+        match GameMove::from_ptn(line) {
+            Ok(game_move) => {
+                self.to_move = false;
+                Ok(Some(game_move))
+            }
+            Err(error) => {
+                println!("Invalid move: {error}");
+                Ok(None)
+            }
+        }
+    }
+
+    async fn reject_move(
+        &mut self,
+        _game: &Game,
+        game_move: &GameMove,
+        error: &io::Error,
+    ) -> io::Result<bool> {
+        println!("{} is not legal: {error}", game_move.to_ptn());
+        self.to_move = true;
+        Ok(true)
+    }
+
+    async fn stop(&mut self) -> io::Result<()> {
+        self.to_move = false;
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> io::Result<()> {
+        self.stop().await
+    }
+
+    async fn set_option(&mut self, _name: &str, _value: i32) -> io::Result<()> {
+        Err(err!("there are no options when playing by hand"))
+    }
+}
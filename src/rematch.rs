@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use tracing::info;
+
+use super::game::Game;
+use super::seek::{Seek, SeekColor};
+
+// Offering a finished game's opponent another one, so they can play a set against the bot.
+#[derive(Args, Clone, Debug)]
+pub struct RematchSettings {
+    #[arg(
+        long,
+        value_name = "GAMES",
+        default_value_t = 0,
+        requires = "continuous"
+    )]
+    pub rematches: u32,
+    #[arg(long, value_name = "SECONDS", default_value_t = 60)]
+    pub rematch_timeout: u64,
+}
+
+impl Default for RematchSettings {
+    fn default() -> Self {
+        Self {
+            rematches: 0,
+            rematch_timeout: 60,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Rematches {
+    settings: RematchSettings,
+    // How many rematches each opponent has played.
+    played: HashMap<String, u32>,
+    // The directed seek offering a rematch, and when the offer was made.
+    offer: Option<(Seek, Instant)>,
+}
+
+impl Rematches {
+    pub fn new(settings: RematchSettings) -> Self {
+        Self {
+            settings,
+            ..Default::default()
+        }
+    }
+
+    // Offers the opponent of a finished game a rematch with the colors swapped, unless they've
+    // had all of theirs.
+    pub fn offer(&mut self, game: &Game, seek: &Seek) {
+        let played = self
+            .played
+            .get(&game.opponent.to_lowercase())
+            .copied()
+            .unwrap_or_default();
+        if game.result.is_none() || played >= self.settings.rematches {
+            return;
+        }
+
+        info!(
+            rematch = played + 1,
+            "Offering {} a rematch.", game.opponent
+        );
+        let mut rematch = seek.clone();
+        rematch.color = SeekColor::from(game.color.opposite());
+        rematch.opponent = Some(game.opponent.clone());
+        self.offer = Some((rematch, Instant::now()));
+    }
+
+    // The seek to post instead of the usual one while a rematch is on offer.
+    pub fn seek(&self) -> Option<&Seek> {
+        self.offer.as_ref().map(|(seek, _)| seek)
+    }
+
+    // Withdraws an offer that's gone unanswered too long, returning true if there was one.
+    pub fn expire(&mut self) -> bool {
+        let timeout = Duration::from_secs(self.settings.rematch_timeout);
+        if self
+            .offer
+            .as_ref()
+            .is_none_or(|(_, made)| made.elapsed() < timeout)
+        {
+            return false;
+        }
+
+        info!("The rematch offer expired.");
+        self.offer = None;
+        true
+    }
+
+    // Whether a seek is the opponent asking for the rematch themselves, with the same game.
+    pub fn is_reseek(&self, seek: &Seek, name: &str) -> bool {
+        let Some((rematch, _)) = &self.offer else {
+            return false;
+        };
+        let from_opponent = seek
+            .player
+            .as_ref()
+            .zip(rematch.opponent.as_ref())
+            .is_some_and(|(player, opponent)| player.eq_ignore_ascii_case(opponent));
+        let open = seek
+            .opponent
+            .as_ref()
+            .is_none_or(|o| o.eq_ignore_ascii_case(name));
+        let game = |s: &Seek| (s.size, s.time, s.increment, s.half_komi);
+
+        from_opponent && open && game(seek) == game(rematch)
+    }
+
+    // Counts the game against the opponent's rematches if it's the one on offer.
+    pub fn started(&mut self, game: &Game) {
+        let Some((rematch, _)) = self.offer.take() else {
+            return;
+        };
+
+        if rematch
+            .opponent
+            .is_some_and(|o| o.eq_ignore_ascii_case(&game.opponent))
+        {
+            *self.played.entry(game.opponent.to_lowercase()).or_default() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::board::Color;
+
+    #[test]
+    fn offer_rematches() {
+        let mut rematches = Rematches::new(RematchSettings {
+            rematches: 1,
+            ..Default::default()
+        });
+        let seek = "Seek new 1 Bot 6 900 10 W 4 30 1 0 0 0 0"
+            .parse::<Seek>()
+            .unwrap();
+        let mut game = "Game Start 7 6 Bot vs Alice white 900 0 30 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+
+        rematches.offer(&game, &seek);
+        assert!(rematches.seek().is_none());
+
+        game.result = Some("R-0".to_owned());
+        rematches.offer(&game, &seek);
+        let rematch = rematches.seek().unwrap();
+        assert_eq!(rematch.color.color(), Some(Color::Black));
+        assert_eq!(rematch.opponent.as_deref(), Some("Alice"));
+
+        let reseek = "Seek new 2 alice 6 900 10 B 4 30 1 0 0 0 0 Bot"
+            .parse::<Seek>()
+            .unwrap();
+        assert!(rematches.is_reseek(&reseek, "Bot"));
+        assert!(!rematches.is_reseek(&reseek, "Other"));
+        assert!(!rematches.expire());
+
+        rematches.started(&game);
+        assert!(rematches.seek().is_none());
+        rematches.offer(&game, &seek);
+        assert!(rematches.seek().is_none());
+    }
+}
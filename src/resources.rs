@@ -0,0 +1,141 @@
+use std::fmt;
+use std::process::Command;
+use std::str::FromStr;
+
+use clap::Args;
+use tracing::warn;
+
+// Limits on the engine process, so that a bot doesn't starve everything else on a shared machine.
+#[derive(Args, Clone, Debug, Default)]
+pub struct ResourceSettings {
+    #[arg(long, value_name = "LIST")]
+    pub cpus: Option<CpuList>,
+    #[arg(long, value_name = "N", allow_negative_numbers = true, value_parser = clap::value_parser!(i32).range(-20..=19))]
+    pub nice: Option<i32>,
+    #[arg(long, value_name = "MB")]
+    pub memory_limit: Option<u64>,
+}
+
+// CPU numbers, given like "0,2,4-7".
+#[derive(Clone, Debug, PartialEq)]
+pub struct CpuList(Vec<usize>);
+
+// The most CPUs an affinity mask can hold.
+const MAX_CPUS: usize = 1024;
+
+impl FromStr for CpuList {
+    type Err = &'static str;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut cpus = Vec::new();
+
+        for part in value.split(',') {
+            let cpu = |c: &str| c.trim().parse::<usize>().map_err(|_| "invalid CPU number");
+
+            let (first, last) = match part.split_once('-') {
+                Some((first, last)) => (cpu(first)?, cpu(last)?),
+                None => (cpu(part)?, cpu(part)?),
+            };
+
+            if first > last {
+                return Err("invalid CPU range");
+            }
+            if last >= MAX_CPUS {
+                return Err("CPU numbers must be below 1024");
+            }
+
+            cpus.extend(first..=last);
+        }
+
+        Ok(Self(cpus))
+    }
+}
+
+impl fmt::Display for CpuList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cpus = self.0.iter().map(|c| c.to_string()).collect::<Vec<_>>();
+        write!(f, "{}", cpus.join(","))
+    }
+}
+
+impl ResourceSettings {
+    // Has the process started by the command run under the limits.
+    pub fn apply(&self, command: &mut Command) {
+        if self.cpus.is_some() && !cfg!(target_os = "linux") {
+            warn!("CPU affinity can only be set on Linux.");
+        }
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+
+            if self.cpus.is_some() || self.nice.is_some() || self.memory_limit.is_some() {
+                let settings = self.clone();
+
+                // The limits are set in the child between fork and exec, using only system calls.
+                unsafe {
+                    command.pre_exec(move || settings.restrict_current_process());
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        if self.nice.is_some() || self.memory_limit.is_some() {
+            let _ = command;
+            warn!("Engine resource limits are only supported on Unix.");
+        }
+    }
+
+    #[cfg(unix)]
+    fn restrict_current_process(&self) -> std::io::Result<()> {
+        use std::io::Error;
+
+        if let Some(nice) = self.nice {
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } != 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        if let Some(megabytes) = self.memory_limit {
+            let bytes = (megabytes * 1024 * 1024) as libc::rlim_t;
+            let limit = libc::rlimit {
+                rlim_cur: bytes,
+                rlim_max: bytes,
+            };
+
+            if unsafe { libc::setrlimit(libc::RLIMIT_AS, &limit) } != 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Some(CpuList(cpus)) = &self.cpus {
+            let mut set = unsafe { std::mem::zeroed::<libc::cpu_set_t>() };
+            for &cpu in cpus {
+                unsafe { libc::CPU_SET(cpu, &mut set) };
+            }
+
+            let size = std::mem::size_of::<libc::cpu_set_t>();
+            if unsafe { libc::sched_setaffinity(0, size, &set) } != 0 {
+                return Err(Error::last_os_error());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cpu_list() {
+        assert_eq!("3".parse(), Ok(CpuList(vec![3])));
+        assert_eq!("0,2,4-6".parse(), Ok(CpuList(vec![0, 2, 4, 5, 6])));
+        assert!("".parse::<CpuList>().is_err());
+        assert!("4-2".parse::<CpuList>().is_err());
+        assert!("0,x".parse::<CpuList>().is_err());
+        assert!("1024".parse::<CpuList>().is_err());
+    }
+}
@@ -0,0 +1,332 @@
+// The async runtime the client runs on.  async-std is the default; building with
+// `--no-default-features --features tokio` switches to Tokio.  The rest of the crate only
+// sees the futures IO traits and the handful of functions re-exported here.
+
+use std::future::Future;
+use std::io;
+use std::pin::pin;
+use std::time::Duration;
+
+use futures::future::{self, Either};
+use futures::io::{AsyncBufReadExt, AsyncRead, BufReader, Lines};
+use futures::stream::{Fuse, StreamExt};
+
+pub use self::backend::*;
+
+#[cfg(not(any(feature = "async-std", feature = "tokio")))]
+compile_error!("either the \"async-std\" or the \"tokio\" feature must be enabled");
+
+pub type LineReader<R> = Fuse<Lines<BufReader<R>>>;
+
+pub type PlaytakReader = LineReader<TcpStream>;
+pub type ProcessReader = LineReader<ProcessOutput>;
+pub type StdinReader = LineReader<Stdin>;
+
+pub fn line_reader<R: AsyncRead>(reader: R) -> LineReader<R> {
+    BufReader::new(reader).lines().fuse()
+}
+
+pub fn stdin_lines() -> StdinReader {
+    line_reader(stdin())
+}
+
+// Starts a child process with piped stdin and stdout.  The process is killed if its Child
+// is dropped before it exits.
+pub fn spawn_process(
+    command: std::process::Command,
+    stderr: std::process::Stdio,
+) -> io::Result<(Child, ProcessWriter, ProcessReader)> {
+    let (child, writer, output) = spawn_piped(command, stderr)?;
+
+    Ok((child, writer, line_reader(output)))
+}
+
+// Returns None if the future doesn't finish within the duration.
+pub async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    match future::select(pin!(future), pin!(sleep(duration))).await {
+        Either::Left((output, _)) => Some(output),
+        Either::Right(_) => None,
+    }
+}
+
+#[cfg(not(feature = "tokio"))]
+mod backend {
+    use std::env;
+    use std::future::Future;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::process::{ExitStatus, Stdio};
+    use std::time::Duration;
+
+    use async_std::process::Command;
+    use async_std::task;
+
+    pub use async_std::io::Stdin;
+    pub use async_std::net::TcpStream;
+    pub use async_std::process::{ChildStdin as ProcessWriter, ChildStdout as ProcessOutput};
+    pub use async_std::task::JoinHandle;
+
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        // Limit the number of threads async-std tries to spawn; we don't need that many.
+        if env::var("ASYNC_STD_THREAD_COUNT").is_err() {
+            env::set_var("ASYNC_STD_THREAD_COUNT", "1");
+        }
+
+        task::block_on(future)
+    }
+
+    pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        task::spawn(future)
+    }
+
+    pub fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        task::spawn_blocking(f)
+    }
+
+    pub async fn sleep(duration: Duration) {
+        task::sleep(duration).await
+    }
+
+    pub async fn connect(address: &str) -> io::Result<TcpStream> {
+        TcpStream::connect(address).await
+    }
+
+    pub struct TcpListener(async_std::net::TcpListener);
+
+    impl TcpListener {
+        pub async fn accept(&self) -> io::Result<TcpStream> {
+            self.0.accept().await.map(|(stream, _)| stream)
+        }
+
+        pub fn local_addr(&self) -> io::Result<SocketAddr> {
+            self.0.local_addr()
+        }
+    }
+
+    pub async fn listen(address: &str) -> io::Result<TcpListener> {
+        async_std::net::TcpListener::bind(address)
+            .await
+            .map(TcpListener)
+    }
+
+    pub fn stdin() -> Stdin {
+        async_std::io::stdin()
+    }
+
+    pub struct Child(async_std::process::Child);
+
+    impl Child {
+        pub fn kill(&mut self) -> io::Result<()> {
+            self.0.kill()
+        }
+
+        pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+            self.0.status().await
+        }
+
+        pub fn has_exited(&mut self) -> bool {
+            !matches!(self.0.try_status(), Ok(None))
+        }
+    }
+
+    pub(super) fn spawn_piped(
+        command: std::process::Command,
+        stderr: Stdio,
+    ) -> io::Result<(Child, ProcessWriter, ProcessOutput)> {
+        let mut child = Command::from(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let (writer, output) = (child.stdin.take().unwrap(), child.stdout.take().unwrap());
+
+        Ok((Child(child), writer, output))
+    }
+}
+
+#[cfg(feature = "tokio")]
+mod backend {
+    use std::future::Future;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::pin::Pin;
+    use std::process::{ExitStatus, Stdio};
+    use std::sync::Arc;
+    use std::task::{ready, Context, Poll};
+    use std::time::Duration;
+
+    use futures::io::{AsyncRead, AsyncWrite};
+    use tokio::process::Command;
+    use tokio_util::compat::{Compat, TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+    pub type Stdin = Compat<tokio::io::Stdin>;
+    pub type ProcessWriter = Compat<tokio::process::ChildStdin>;
+    pub type ProcessOutput = Compat<tokio::process::ChildStdout>;
+
+    // Tokio streams can't be cloned, but they can be read and written through a shared
+    // reference, which is enough to hand the same connection to several tasks.
+    #[derive(Clone, Debug)]
+    pub struct TcpStream(Arc<tokio::net::TcpStream>);
+
+    impl AsyncRead for TcpStream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            loop {
+                ready!(self.0.poll_read_ready(cx))?;
+                match self.0.try_read(buf) {
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+                    result => return Poll::Ready(result),
+                }
+            }
+        }
+    }
+
+    impl AsyncWrite for TcpStream {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            loop {
+                ready!(self.0.poll_write_ready(cx))?;
+                match self.0.try_write(buf) {
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => continue,
+                    result => return Poll::Ready(result),
+                }
+            }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[cfg(unix)]
+    impl std::os::fd::AsRawFd for TcpStream {
+        fn as_raw_fd(&self) -> std::os::fd::RawFd {
+            self.0.as_raw_fd()
+        }
+    }
+
+    // Resolves to the task's output like async-std's handles do.  Dropping it detaches the task.
+    #[derive(Debug)]
+    pub struct JoinHandle<T>(tokio::task::JoinHandle<T>);
+
+    impl<T> Future for JoinHandle<T> {
+        type Output = T;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            Pin::new(&mut self.0)
+                .poll(cx)
+                .map(|result| result.expect("task panicked"))
+        }
+    }
+
+    pub fn block_on<F: Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("could not start the tokio runtime")
+            .block_on(future)
+    }
+
+    pub fn spawn<F>(future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        JoinHandle(tokio::spawn(future))
+    }
+
+    pub fn spawn_blocking<F, T>(f: F) -> JoinHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        JoinHandle(tokio::task::spawn_blocking(f))
+    }
+
+    pub async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    pub async fn connect(address: &str) -> io::Result<TcpStream> {
+        let stream = tokio::net::TcpStream::connect(address).await?;
+        Ok(TcpStream(Arc::new(stream)))
+    }
+
+    pub struct TcpListener(tokio::net::TcpListener);
+
+    impl TcpListener {
+        pub async fn accept(&self) -> io::Result<TcpStream> {
+            let (stream, _) = self.0.accept().await?;
+            Ok(TcpStream(Arc::new(stream)))
+        }
+
+        pub fn local_addr(&self) -> io::Result<SocketAddr> {
+            self.0.local_addr()
+        }
+    }
+
+    pub async fn listen(address: &str) -> io::Result<TcpListener> {
+        tokio::net::TcpListener::bind(address)
+            .await
+            .map(TcpListener)
+    }
+
+    pub fn stdin() -> Stdin {
+        tokio::io::stdin().compat()
+    }
+
+    pub struct Child(tokio::process::Child);
+
+    impl Child {
+        pub fn kill(&mut self) -> io::Result<()> {
+            self.0.start_kill()
+        }
+
+        pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+            self.0.wait().await
+        }
+
+        pub fn has_exited(&mut self) -> bool {
+            !matches!(self.0.try_wait(), Ok(None))
+        }
+    }
+
+    pub(super) fn spawn_piped(
+        command: std::process::Command,
+        stderr: Stdio,
+    ) -> io::Result<(Child, ProcessWriter, ProcessOutput)> {
+        let mut child = Command::from(command)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(stderr)
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let (writer, output) = (
+            child.stdin.take().unwrap().compat_write(),
+            child.stdout.take().unwrap().compat(),
+        );
+
+        Ok((Child(child), writer, output))
+    }
+}
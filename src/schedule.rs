@@ -0,0 +1,159 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Deserialize;
+
+use super::config::SeekConfig;
+
+// A stretch of the week, in UTC, during which the bot seeks.  A window that ends before it
+// starts runs past midnight into the next day.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Window {
+    // Every day if left out.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    pub start: TimeOfDay,
+    pub end: TimeOfDay,
+    // Layered on top of all the other seek settings while the window is open.
+    #[serde(default)]
+    pub seek: SeekConfig,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl Weekday {
+    const ALL: [Self; 7] = [
+        Self::Mon,
+        Self::Tue,
+        Self::Wed,
+        Self::Thu,
+        Self::Fri,
+        Self::Sat,
+        Self::Sun,
+    ];
+
+    fn previous(self) -> Self {
+        Self::ALL[(self as usize + 6) % 7]
+    }
+}
+
+// Minutes since midnight.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, PartialOrd)]
+#[serde(try_from = "String")]
+pub struct TimeOfDay(u32);
+
+impl FromStr for TimeOfDay {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (hours, minutes) = s.split_once(':').ok_or("times must be given as HH:MM")?;
+        let hours = hours.parse::<u32>().map_err(|_| "invalid hour")?;
+        let minutes = minutes.parse::<u32>().map_err(|_| "invalid minute")?;
+
+        // 24:00 is allowed, for windows that run to the end of the day.
+        if minutes >= 60 || hours * 60 + minutes > 24 * 60 {
+            return Err("times must be between 00:00 and 24:00");
+        }
+
+        Ok(Self(hours * 60 + minutes))
+    }
+}
+
+impl TryFrom<String> for TimeOfDay {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.0 / 60, self.0 % 60)
+    }
+}
+
+impl Window {
+    fn is_open(&self, day: Weekday, time: TimeOfDay) -> bool {
+        let on = |day| self.days.is_empty() || self.days.contains(&day);
+
+        if self.start <= self.end {
+            on(day) && self.start <= time && time < self.end
+        } else {
+            (on(day) && self.start <= time) || (on(day.previous()) && time < self.end)
+        }
+    }
+}
+
+// The first of the windows that's open at the given time, if any.
+pub fn open_window(windows: &[Window], now: SystemTime) -> Option<usize> {
+    let seconds = now
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    // The epoch was a Thursday.
+    let day = Weekday::ALL[((seconds / 86400 + 3) % 7) as usize];
+    let time = TimeOfDay((seconds % 86400 / 60) as u32);
+
+    windows.iter().position(|w| w.is_open(day, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use std::time::Duration;
+
+    #[test]
+    fn open_windows() {
+        let windows = toml::from_str::<Config>(
+            r#"
+            [[schedule]]
+            days = ["mon", "tue", "wed", "thu", "fri"]
+            start = "18:00"
+            end = "23:00"
+
+            [[schedule]]
+            days = ["sat"]
+            start = "22:00"
+            end = "02:00"
+            "#,
+        )
+        .unwrap()
+        .schedule;
+
+        // Monday, January 5th, 1970.
+        let monday = UNIX_EPOCH + Duration::from_secs(4 * 86400);
+        let at = |days: u64, hours: u64, minutes: u64| {
+            open_window(
+                &windows,
+                monday + Duration::from_secs(days * 86400 + hours * 3600 + minutes * 60),
+            )
+        };
+
+        assert_eq!(at(0, 17, 59), None);
+        assert_eq!(at(0, 18, 0), Some(0));
+        assert_eq!(at(4, 22, 59), Some(0));
+        assert_eq!(at(0, 23, 0), None);
+        assert_eq!(at(5, 18, 30), None);
+        assert_eq!(at(5, 23, 0), Some(1));
+        assert_eq!(at(6, 1, 59), Some(1));
+        assert_eq!(at(6, 2, 0), None);
+        assert_eq!(at(0, 1, 0), None);
+
+        assert!("25:00".parse::<TimeOfDay>().is_err());
+        assert!("18".parse::<TimeOfDay>().is_err());
+    }
+}
@@ -0,0 +1,381 @@
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use futures::channel::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use futures::{future, AsyncWrite};
+
+use super::chat::Moderation;
+use super::game::{Game, GameMove};
+use super::provider::MoveProvider;
+use super::{err, is_desync, run_game, runtime};
+
+// What the scripted player does when it's asked for a move.
+#[derive(Clone, Debug)]
+pub enum Step {
+    // Answers with the move in PTN once the delay is up, whether or not it's legal.
+    Move(&'static str, Duration),
+    // Fails, as if the engine had crashed.
+    Crash,
+    Resign,
+}
+
+impl Step {
+    pub fn play(ptn: &'static str) -> Self {
+        Step::Move(ptn, Duration::ZERO)
+    }
+}
+
+// A move provider that follows a script, for testing how games go when the engine misbehaves.
+// Like the engine, it gets one more try at a move that isn't legal.
+#[derive(Debug, Default)]
+pub struct ScriptedPlayer {
+    script: VecDeque<Step>,
+    // The step answering the current request, and when it's due.
+    pending: Option<(Step, Instant)>,
+    retried: bool,
+    resigned: bool,
+    pub requests: usize,
+    pub rejected: Vec<GameMove>,
+    pub stops: usize,
+    // The reply it claims to expect to each of its moves.
+    pub reply: Option<&'static str>,
+    // How many plies had been played each time it was asked to ponder, and the expected reply.
+    pub ponders: Vec<(usize, Option<String>)>,
+    pub finished: bool,
+}
+
+impl ScriptedPlayer {
+    pub fn new(script: impl IntoIterator<Item = Step>) -> Self {
+        Self {
+            script: script.into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    fn next_step(&mut self) -> io::Result<()> {
+        let step = self
+            .script
+            .pop_front()
+            .ok_or_else(|| err!("the script ran out"))?;
+        let delay = match step {
+            Step::Move(_, delay) => delay,
+            _ => Duration::ZERO,
+        };
+
+        self.requests += 1;
+        self.pending = Some((step, Instant::now() + delay));
+        Ok(())
+    }
+}
+
+impl MoveProvider for ScriptedPlayer {
+    async fn start(&mut self, _game: &Game) -> io::Result<()> {
+        Ok(())
+    }
+
+    async fn request_move(&mut self, _game: &Game) -> io::Result<()> {
+        self.retried = false;
+        self.next_step()
+    }
+
+    // The step is only used up once it's due, so that this is safe to cancel.
+    async fn next_move(&mut self) -> io::Result<Option<GameMove>> {
+        let Some((_, due)) = &self.pending else {
+            return future::pending().await;
+        };
+        runtime::sleep(due.saturating_duration_since(Instant::now())).await;
+
+        match self.pending.take().map(|(step, _)| step) {
+            Some(Step::Move(ptn, _)) => GameMove::from_ptn(ptn).map(Some),
+            Some(Step::Crash) => Err(err!("the engine crashed")),
+            Some(Step::Resign) => {
+                self.resigned = true;
+                Ok(None)
+            }
+            None => unreachable!(),
+        }
+    }
+
+    async fn reject_move(
+        &mut self,
+        _game: &Game,
+        game_move: &GameMove,
+        _error: &io::Error,
+    ) -> io::Result<bool> {
+        self.rejected.push(game_move.clone());
+        if self.retried {
+            return Ok(false);
+        }
+
+        self.retried = true;
+        self.next_step()?;
+        Ok(true)
+    }
+
+    async fn ponder(&mut self, game: &Game) -> io::Result<()> {
+        self.ponders
+            .push((game.moves.len(), game.ponder.as_ref().map(GameMove::to_ptn)));
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> io::Result<()> {
+        self.stops += 1;
+        self.pending = None;
+        Ok(())
+    }
+
+    async fn finish(&mut self) -> io::Result<()> {
+        self.finished = true;
+        self.pending = None;
+        Ok(())
+    }
+
+    async fn set_option(&mut self, _name: &str, _value: i32) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn take_ponder_move(&mut self) -> Option<GameMove> {
+        self.reply.and_then(|reply| GameMove::from_ptn(reply).ok())
+    }
+
+    fn take_resignation(&mut self) -> bool {
+        std::mem::take(&mut self.resigned)
+    }
+}
+
+// PlayTak.com's side of a game for tests.  Each line sent to it is answered with the next of its
+// replies, and the lines are kept for checking afterwards.  Nothing arrives unless it's answering
+// something, so the order things happen in doesn't depend on timing.
+#[derive(Debug)]
+pub struct FakeServer {
+    replies: VecDeque<Vec<String>>,
+    sender: UnboundedSender<io::Result<String>>,
+    partial: Vec<u8>,
+    pub sent: Vec<String>,
+}
+
+impl FakeServer {
+    // Returns the server with the lines to read from it, starting with the opening lines.
+    pub fn new(
+        opening: &[&str],
+        replies: &[&[&str]],
+    ) -> (Self, UnboundedReceiver<io::Result<String>>) {
+        let (sender, receiver) = mpsc::unbounded();
+        for line in opening {
+            sender.unbounded_send(Ok(line.to_string())).unwrap();
+        }
+
+        let server = Self {
+            replies: replies
+                .iter()
+                .map(|lines| lines.iter().map(|l| l.to_string()).collect())
+                .collect(),
+            sender,
+            partial: Vec::new(),
+            sent: Vec::new(),
+        };
+        (server, receiver)
+    }
+}
+
+impl AsyncWrite for FakeServer {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.partial.extend_from_slice(buf);
+        while let Some(end) = self.partial.iter().position(|&b| b == b'\n') {
+            let line = self.partial.drain(..=end).collect::<Vec<_>>();
+            self.sent
+                .push(String::from_utf8_lossy(&line).trim_end().to_owned());
+
+            for reply in self.replies.pop_front().unwrap_or_default() {
+                self.sender.unbounded_send(Ok(reply)).unwrap();
+            }
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+// A game that goes on this long is waiting on something the scripts don't have.
+const GAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Plays a game to the end against the fake server.
+pub fn play(
+    start: &str,
+    player: &mut ScriptedPlayer,
+    (server, receiver): (&mut FakeServer, UnboundedReceiver<io::Result<String>>),
+) -> io::Result<Game> {
+    let game = start.parse::<Game>().map_err(|error| err!(error))?;
+
+    runtime::block_on(runtime::timeout(
+        GAME_TIMEOUT,
+        run_game(
+            game,
+            player,
+            (server, receiver),
+            (&mut None, &mut Moderation::default()),
+            (None, None),
+        ),
+    ))
+    .unwrap_or_else(|| Err(io::Error::from(io::ErrorKind::TimedOut)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: &str = "Game Start 1 5 Bot vs Opp white 600 0 21 1 0 0";
+    const BLACK: &str = "Game Start 1 5 Opp vs Bot black 600 0 21 1 0 0";
+
+    #[test]
+    fn play_scripted_game() {
+        let mut player = ScriptedPlayer::new([Step::play("a1"), Step::play("b1")]);
+        let (mut server, receiver) = FakeServer::new(
+            &[],
+            &[
+                &["Game#1 Time 600 600", "Game#1 P E5"],
+                &["Game#1 Time 600 600", "Game#1 Over R-0"],
+            ],
+        );
+
+        let game = play(WHITE, &mut player, (&mut server, receiver)).unwrap();
+        assert_eq!(server.sent, ["Game#1 P A1", "Game#1 P B1"]);
+        assert_eq!(game.ptn_moves(), ["a1", "e5", "b1"]);
+        assert_eq!(game.result.as_deref(), Some("R-0"));
+        assert!(player.finished);
+    }
+
+    #[test]
+    fn ponder_on_opponents_time() {
+        let mut player = ScriptedPlayer::new([Step::play("e5"), Step::play("b1")]);
+        let (mut server, receiver) = FakeServer::new(
+            &["Game#1 P A1"],
+            &[
+                &["Game#1 Time 600 600", "Game#1 P A2"],
+                &["Game#1 Time 600 600", "Game#1 Over R-0"],
+            ],
+        );
+
+        play(BLACK, &mut player, (&mut server, receiver)).unwrap();
+        assert_eq!(player.ponders, [(0, None), (2, None), (4, None)]);
+        assert!(player.finished);
+    }
+
+    #[test]
+    fn keep_legal_ponder_moves() {
+        let mut player = ScriptedPlayer::new([Step::play("a1"), Step::play("b1")]);
+        player.reply = Some("e5");
+        let (mut server, receiver) = FakeServer::new(
+            &[],
+            &[
+                &["Game#1 Time 600 600", "Game#1 P E5"],
+                &["Game#1 Time 600 600", "Game#1 Over R-0"],
+            ],
+        );
+
+        let game = play(WHITE, &mut player, (&mut server, receiver)).unwrap();
+        // By the second move, e5 is taken.
+        assert_eq!(player.ponders, [(1, Some("e5".to_owned())), (3, None)]);
+        assert_eq!(game.ponder, None);
+    }
+
+    #[test]
+    fn resync_after_malformed_move() {
+        let mut player = ScriptedPlayer::new([Step::play("a1"), Step::play("b1")]);
+        let (mut server, receiver) =
+            FakeServer::new(&[], &[&["Game#1 Time 600 600", "Game#1 M E5 E7 1 1 1"]]);
+
+        let error = play(WHITE, &mut player, (&mut server, receiver)).unwrap_err();
+        assert!(is_desync(&error));
+        assert_eq!(server.sent, ["Game#1 P A1"]);
+    }
+
+    #[test]
+    fn replace_illegal_moves() {
+        let mut player =
+            ScriptedPlayer::new([Step::play("a1"), Step::play("a1"), Step::play("e5")]);
+        let (mut server, receiver) = FakeServer::new(
+            &[],
+            &[
+                &["Game#1 Time 600 600", "Game#1 P E5"],
+                &["Game#1 Time 600 600", "Game#1 Over 0-R"],
+            ],
+        );
+
+        let game = play(WHITE, &mut player, (&mut server, receiver)).unwrap();
+        assert_eq!(player.rejected.len(), 2);
+        assert_eq!(server.sent.len(), 2);
+        assert!(game.board().is_ok());
+        assert!(player.finished);
+    }
+
+    #[test]
+    fn fall_back_after_crash() {
+        let mut player = ScriptedPlayer::new([Step::play("a1"), Step::Crash]);
+        let (mut server, receiver) = FakeServer::new(
+            &[],
+            &[
+                &["Game#1 Time 600 600", "Game#1 P E5"],
+                // The fallback move is random, so the opponent answers with one that works wherever it went.
+                &["Game#1 Time 600 600", "Game#1 M A1 A2 1"],
+                &["Game#1 Time 600 600", "Game#1 Over 0-R"],
+            ],
+        );
+
+        let game = play(WHITE, &mut player, (&mut server, receiver)).unwrap();
+        assert_eq!(player.requests, 2);
+        assert_eq!(server.sent.len(), 3);
+        assert_eq!(game.moves.len(), 5);
+        assert!(game.board().is_ok());
+        assert!(!player.finished);
+    }
+
+    #[test]
+    fn drop_slow_move_when_game_ends() {
+        let mut player = ScriptedPlayer::new([Step::Move("a1", Duration::from_secs(60))]);
+        let (mut server, receiver) =
+            FakeServer::new(&["Game#1 P E5", "Game#1 Abandoned. Opp quit"], &[]);
+
+        let game = play(BLACK, &mut player, (&mut server, receiver)).unwrap();
+        assert!(server.sent.is_empty());
+        assert_eq!(game.moves.len(), 1);
+        assert_eq!(game.result, None);
+        assert!(player.finished);
+    }
+
+    #[test]
+    fn stop_when_out_of_time() {
+        let mut player = ScriptedPlayer::new([Step::Move("a1", Duration::from_secs(60))]);
+        let (mut server, receiver) =
+            FakeServer::new(&["Game#1 Time 0 600", "Game#1 Over 0-F"], &[]);
+
+        let game = play(WHITE, &mut player, (&mut server, receiver)).unwrap();
+        assert_eq!(player.stops, 1);
+        assert!(server.sent.is_empty());
+        assert_eq!(game.result.as_deref(), Some("0-F"));
+    }
+
+    #[test]
+    fn resign_on_request() {
+        let mut player = ScriptedPlayer::new([Step::Resign]);
+        let (mut server, receiver) = FakeServer::new(&[], &[&["Game#1 Over 0-R"]]);
+
+        let game = play(WHITE, &mut player, (&mut server, receiver)).unwrap();
+        assert_eq!(server.sent, ["Game#1 Resign"]);
+        assert_eq!(game.result.as_deref(), Some("0-R"));
+    }
+}
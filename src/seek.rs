@@ -1,8 +1,15 @@
 use std::fmt;
 use std::str::FromStr;
 
-use clap::ValueEnum;
-use clap::{arg, Args};
+use clap::{Args, ValueEnum};
+use serde::Deserialize;
+
+use super::api::ApiSeek;
+use super::board::Color;
+use super::config::SeekConfig;
+
+// Withdraws the seek that's been posted, if any.
+pub const REMOVE_SEEK: &str = "Seek 0 0 0 A 0 0 0 0 0 0 0 \n";
 
 #[derive(Args, Clone, Debug)]
 pub struct Seek {
@@ -10,7 +17,15 @@ pub struct Seek {
     pub id: Option<u32>,
     #[arg(skip)]
     pub player: Option<String>,
-    #[arg(short, long, value_parser = clap::value_parser!(u32).range(3..=8))]
+    // A preset is required to give a size, so the default only keeps clap happy.
+    #[arg(
+        short,
+        long,
+        value_parser = clap::value_parser!(u32).range(3..=8),
+        required_unless_present = "preset",
+        default_value_t = 6,
+        hide_default_value = true
+    )]
     pub size: u32,
     #[arg(short = 'm', long, default_value_t = 1200)]
     pub time: u32,
@@ -18,8 +33,8 @@ pub struct Seek {
     pub increment: u32,
     #[arg(short, long, value_enum, default_value_t = SeekColor::Random)]
     pub color: SeekColor,
-    #[arg(short = 'k', long, default_value_t = 0)]
-    pub half_komi: u32,
+    #[arg(short = 'k', long, default_value_t = 0, allow_negative_numbers = true)]
+    pub half_komi: i32,
     #[arg(long)]
     flatstones: Option<u32>,
     #[arg(long)]
@@ -34,6 +49,8 @@ pub struct Seek {
     pub extra_time_amount: Option<u32>,
     #[arg(short, long)]
     pub opponent: Option<String>,
+    #[arg(long, value_name = "NAME")]
+    pub preset: Option<String>,
 }
 
 impl Seek {
@@ -46,6 +63,43 @@ impl Seek {
         self.capstones
             .unwrap_or_else(|| capstones_for_size(self.size))
     }
+
+    pub fn configure(&mut self, config: &SeekConfig) {
+        self.size = config.size.unwrap_or(self.size);
+        self.time = config.time.unwrap_or(self.time);
+        self.increment = config.increment.unwrap_or(self.increment);
+        self.color = config.color.clone().unwrap_or(self.color.clone());
+        self.half_komi = config.half_komi.unwrap_or(self.half_komi);
+        self.flatstones = config.flatstones.or(self.flatstones);
+        self.capstones = config.capstones.or(self.capstones);
+        self.unrated = config.unrated.unwrap_or(self.unrated);
+        self.tournament = config.tournament.unwrap_or(self.tournament);
+        self.extra_time_move = config.extra_time_move.or(self.extra_time_move);
+        self.extra_time_amount = config.extra_time_amount.or(self.extra_time_amount);
+        self.opponent = config.opponent.clone().or(self.opponent.take());
+    }
+}
+
+impl Seek {
+    // The seek on one line, like "6x6 15:00+20, komi 2, random color".
+    pub fn summary(&self) -> String {
+        format!(
+            "{size}x{size} {}:{:02}+{}, komi {}, {} color",
+            self.time / 60,
+            self.time % 60,
+            self.increment,
+            f64::from(self.half_komi) / 2.0,
+            format!("{:?}", self.color).to_lowercase(),
+            size = self.size,
+        )
+    }
+
+    // Whether only the given player can accept the seek.
+    pub fn is_directed_at(&self, name: &str) -> bool {
+        self.opponent
+            .as_ref()
+            .is_some_and(|o| o.eq_ignore_ascii_case(name))
+    }
 }
 
 impl FromStr for Seek {
@@ -54,6 +108,10 @@ impl FromStr for Seek {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let parts = s.split_ascii_whitespace().collect::<Vec<_>>();
 
+        if parts.len() < 15 {
+            return Err("seek is missing fields");
+        }
+
         Ok(Self {
             id: Some(
                 parts[2]
@@ -74,10 +132,10 @@ impl FromStr for Seek {
                 "W" => SeekColor::White,
                 "B" => SeekColor::Black,
                 "A" => SeekColor::Random,
-                _ => panic!("invalid seeker color"),
+                _ => return Err("invalid seeker color"),
             },
             half_komi: parts[8]
-                .parse::<u32>()
+                .parse::<i32>()
                 .map_err(|_| "could not parse half komi")?,
             flatstones: Some(
                 parts[9]
@@ -92,12 +150,12 @@ impl FromStr for Seek {
             unrated: match parts[11] {
                 "0" => false,
                 "1" => true,
-                _ => panic!("invalid unrated value"),
+                _ => return Err("invalid unrated value"),
             },
             tournament: match parts[12] {
                 "0" => false,
                 "1" => true,
-                _ => panic!("invalid tournament value"),
+                _ => return Err("invalid tournament value"),
             },
             extra_time_move: Some(
                 parts[13]
@@ -112,6 +170,40 @@ impl FromStr for Seek {
             )
             .filter(|&v| v > 0),
             opponent: parts.get(15).map(|&o| o.to_owned()),
+            preset: None,
+        })
+    }
+}
+
+impl TryFrom<ApiSeek> for Seek {
+    type Error = &'static str;
+
+    fn try_from(seek: ApiSeek) -> Result<Self, Self::Error> {
+        if !(3..=8).contains(&seek.size) {
+            return Err("invalid board size");
+        }
+
+        Ok(Self {
+            id: Some(seek.id),
+            player: Some(seek.creator),
+            size: seek.size,
+            time: seek.time,
+            increment: seek.increment,
+            color: match seek.color.as_str() {
+                "W" => SeekColor::White,
+                "B" => SeekColor::Black,
+                "A" => SeekColor::Random,
+                _ => return Err("invalid seeker color"),
+            },
+            half_komi: seek.komi,
+            flatstones: Some(seek.pieces),
+            capstones: Some(seek.capstones),
+            unrated: seek.unrated,
+            tournament: seek.tournament,
+            extra_time_move: Some(seek.extra_time_move).filter(|&v| v > 0),
+            extra_time_amount: Some(seek.extra_time_amount).filter(|&v| v > 0),
+            opponent: seek.opponent.filter(|o| !o.is_empty()),
+            preset: None,
         })
     }
 }
@@ -197,13 +289,39 @@ impl fmt::Display for Seek {
     }
 }
 
-#[derive(Clone, Debug, ValueEnum)]
+#[derive(Clone, Debug, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
 pub enum SeekColor {
     White,
     Black,
     Random,
 }
 
+impl SeekColor {
+    // The color the seeker plays, unless it's left to chance.
+    pub fn color(&self) -> Option<Color> {
+        match self {
+            Self::White => Some(Color::White),
+            Self::Black => Some(Color::Black),
+            Self::Random => None,
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        self.color()
+            .map_or(Self::Random, |color| color.opposite().into())
+    }
+}
+
+impl From<Color> for SeekColor {
+    fn from(color: Color) -> Self {
+        match color {
+            Color::White => Self::White,
+            Color::Black => Self::Black,
+        }
+    }
+}
+
 pub fn flatstones_for_size(size: u32) -> u32 {
     match size {
         3 => 10,
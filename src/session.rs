@@ -0,0 +1,512 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll, Waker};
+use std::time::Duration;
+
+use clap::Args;
+use futures::channel::mpsc::{self, Receiver, Sender, UnboundedReceiver, UnboundedSender};
+use futures::future::{self, AbortHandle};
+use futures::task::AtomicWaker;
+use futures::{AsyncWrite, AsyncWriteExt, Stream, StreamExt};
+use tracing::{debug, error, info, warn};
+
+use super::runtime::{self, line_reader, PlaytakReader, TcpStream};
+use super::settle;
+use super::transcript::Transcribed;
+use super::{err, live, read, write, Login};
+
+#[derive(Args, Clone, Debug)]
+pub struct ConnectionSettings {
+    #[arg(long, value_name = "SECONDS", default_value_t = 30)]
+    pub ping_interval: u64,
+    #[arg(long, value_name = "SECONDS", default_value_t = 75)]
+    pub read_timeout: u64,
+    #[arg(long, value_name = "URL", default_value = "https://api.playtak.com/v1")]
+    pub api_url: String,
+    #[arg(long, value_name = "COUNT", default_value_t = 5)]
+    pub login_retries: u32,
+}
+
+// A logged-in connection to PlayTak.com.
+pub struct Session {
+    pub writer: SessionWriter,
+    pub reader: SessionReader,
+    // The name the server knows us by, which is made up for guests.
+    pub name: String,
+    ping_interval: Duration,
+    keep_alive: Arc<KeepAlive>,
+}
+
+// Shared between the pinging task and the reader, which answers the pings.
+#[derive(Default)]
+struct KeepAlive {
+    unanswered: AtomicU32,
+    dead: AtomicBool,
+    waker: AtomicWaker,
+}
+
+// Outgoing lines that haven't been sent yet.
+const QUEUE_LENGTH: usize = 32;
+// How many times a transient write failure is retried before the connection is given up on.
+const WRITE_RETRIES: u32 = 3;
+const WRITE_RETRY_DELAY: Duration = Duration::from_millis(200);
+// How long the server has to get through the greeting and login.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+// How long to wait before trying to log in again, doubling with each retry up to the maximum.
+const LOGIN_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_LOGIN_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+// The server's lines, minus the replies to our pings.  Once the server stops answering pings,
+// or sends nothing at all for the read timeout, reading fails so that the connection can be
+// given up on.  The lines are read by a task of its own, so that ping replies are counted
+// even while nothing is reading the session, like when an engine is starting.
+pub struct SessionReader {
+    lines: UnboundedReceiver<io::Result<String>>,
+    keep_alive: Arc<KeepAlive>,
+    reading: AbortHandle,
+}
+
+impl Stream for SessionReader {
+    type Item = io::Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.keep_alive.waker.register(cx.waker());
+
+        if self.keep_alive.dead.load(Ordering::Relaxed) {
+            return Poll::Ready(Some(Err(err!("PlayTak.com stopped answering pings"))));
+        }
+
+        self.lines.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for SessionReader {
+    fn drop(&mut self) {
+        self.reading.abort();
+    }
+}
+
+// Reads the server's lines for the session reader, answering ping replies itself.  Reading
+// stops after the read timeout, or once the server closes the connection.
+async fn read_lines(
+    mut lines: Transcribed<PlaytakReader>,
+    keep_alive: Arc<KeepAlive>,
+    read_timeout: Duration,
+    sender: UnboundedSender<io::Result<String>>,
+) {
+    loop {
+        let item = if read_timeout.is_zero() {
+            lines.next().await
+        } else {
+            match runtime::timeout(read_timeout, lines.next()).await {
+                Some(item) => item,
+                None => {
+                    let message = format!("nothing received in {} seconds", read_timeout.as_secs());
+                    let error = io::Error::new(io::ErrorKind::TimedOut, message);
+                    sender.unbounded_send(Err(error)).ok();
+                    return;
+                }
+            }
+        };
+
+        match item {
+            Some(Ok(line)) if line == "OK" && keep_alive.answer() => {
+                debug!("Received ping reply.");
+            }
+            Some(item) => {
+                if let Ok(line) = &item {
+                    live::received(line);
+                }
+                if sender.unbounded_send(item).is_err() {
+                    return;
+                }
+            }
+            None => return,
+        }
+    }
+}
+
+// Queues lines for the task that writes them to the server, which is the only thing that
+// writes to the connection once the session is logged in.  Writes complete once the line is
+// queued; flushing waits for everything queued to be sent.
+#[derive(Clone)]
+pub struct SessionWriter {
+    sender: Sender<Vec<u8>>,
+    queue: Arc<Queue>,
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: AtomicUsize,
+    flushing: Mutex<Vec<Waker>>,
+}
+
+impl AsyncWrite for SessionWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        ready!(self.sender.poll_ready(cx)).map_err(|_| closed())?;
+
+        self.queue.pending.fetch_add(1, Ordering::SeqCst);
+        if self.sender.start_send(buf.to_vec()).is_err() {
+            self.queue.pending.fetch_sub(1, Ordering::SeqCst);
+            return Poll::Ready(Err(closed()));
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.queue.pending.load(Ordering::SeqCst) > 0 {
+            self.queue.flushing.lock().unwrap().push(cx.waker().clone());
+        }
+
+        // Checked after registering, so that a line sent in between isn't missed.
+        if self.queue.pending.load(Ordering::SeqCst) == 0 {
+            Poll::Ready(Ok(()))
+        } else if self.sender.is_closed() {
+            Poll::Ready(Err(closed()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+impl Queue {
+    fn wake(&self) {
+        for waker in self.flushing.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+fn closed() -> io::Error {
+    err!("the connection to PlayTak.com is closed")
+}
+
+async fn send_lines(
+    mut stream: Transcribed<TcpStream>,
+    mut lines: Receiver<Vec<u8>>,
+    queue: Arc<Queue>,
+    // Held for as long as lines are sent, so that a panic can close the connection.
+    _settled: settle::Registration,
+) {
+    while let Some(line) = lines.next().await {
+        live::sent(&String::from_utf8_lossy(&line));
+        settle::sending(true);
+        let result = send_line(&mut stream, &line).await;
+        if result.is_ok() {
+            settle::sending(false);
+        }
+
+        queue.pending.fetch_sub(1, Ordering::SeqCst);
+        queue.wake();
+
+        if let Err(error) = result {
+            error!(%error, "Could not write to PlayTak.com.");
+            break;
+        }
+    }
+
+    // Anything still queued will never be sent.
+    drop(lines);
+    queue.wake();
+}
+
+async fn send_line(stream: &mut Transcribed<TcpStream>, line: &[u8]) -> io::Result<()> {
+    let (mut written, mut failures) = (0, 0);
+
+    while written < line.len() {
+        match stream.write(&line[written..]).await {
+            Ok(0) => return Err(io::Error::from(io::ErrorKind::WriteZero)),
+            Ok(n) => written += n,
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    io::ErrorKind::Interrupted
+                        | io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                ) && failures < WRITE_RETRIES =>
+            {
+                failures += 1;
+                warn!(%error, "Could not write to PlayTak.com. Retrying.");
+                runtime::sleep(WRITE_RETRY_DELAY).await;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+
+    Ok(())
+}
+
+// How far logging in has gotten.  The server's greeting has changed over the years and other
+// lines can arrive in between, so each step waits for the line it needs and skips the rest.
+#[derive(Debug, PartialEq)]
+enum Handshake {
+    Greeting,
+    ClientName,
+    Login,
+    LoggedIn(String),
+}
+
+impl Handshake {
+    // The next step, if this line finishes the current one.
+    fn advance(&self, line: &str) -> io::Result<Option<Self>> {
+        let next = match (self, line) {
+            (Handshake::Greeting, line) if line.starts_with("Login or Register") => {
+                Handshake::ClientName
+            }
+            (Handshake::ClientName, "OK") => {
+                debug!("Client acknowledged.");
+                Handshake::Login
+            }
+            // The client name is only informational, so logging in can go ahead without it.
+            (Handshake::ClientName, "NOK") => {
+                warn!("PlayTak.com did not accept the client name.");
+                Handshake::Login
+            }
+            (Handshake::Login, "Authentication failure") => {
+                error!("Could not authenticate. Are the username and password correct?");
+                return Err(io::Error::from(io::ErrorKind::PermissionDenied));
+            }
+            (Handshake::Login, line) => match line
+                .strip_prefix("Welcome ")
+                .and_then(|n| n.strip_suffix('!'))
+            {
+                Some(name) if !name.is_empty() && !name.contains(' ') => {
+                    Handshake::LoggedIn(name.to_owned())
+                }
+                _ => return Ok(None),
+            },
+            _ => return Ok(None),
+        };
+
+        Ok(Some(next))
+    }
+}
+
+// Logs in and returns the name the server gave us.
+async fn handshake(
+    mut writer: impl AsyncWrite + Unpin,
+    mut reader: impl Stream<Item = io::Result<String>> + Unpin,
+    login: &Login,
+) -> io::Result<String> {
+    let mut step = Handshake::Greeting;
+
+    loop {
+        let line = read(&mut reader).await?;
+
+        let Some(next) = step.advance(&line)? else {
+            debug!(?line, ?step, "Skipping a line while logging in.");
+            continue;
+        };
+
+        match &next {
+            Handshake::ClientName => write(&mut writer, "Client playtak-tei\n").await?,
+            Handshake::Login => write(&mut writer, login.to_login_string()).await?,
+            Handshake::LoggedIn(name) => return Ok(name.clone()),
+            Handshake::Greeting => unreachable!(),
+        }
+
+        step = next;
+    }
+}
+
+impl KeepAlive {
+    // Returns true if there was a ping waiting for this reply.
+    fn answer(&self) -> bool {
+        self.unanswered
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1))
+            .is_ok()
+    }
+}
+
+impl Session {
+    pub async fn connect(login: &Login) -> io::Result<Self> {
+        let (mut writer, mut reader, settled) = match runtime::connect("playtak.com:10000").await {
+            Ok(stream) => {
+                info!("Connected to PlayTak.com.");
+                (
+                    Transcribed::new(stream.clone(), None).traced("playtak"),
+                    Transcribed::new(line_reader(stream.clone()), None).traced("playtak"),
+                    settle::server(&stream),
+                )
+            }
+            Err(error) => {
+                error!(%error, "Could not connect to PlayTak.com.");
+                return Err(error);
+            }
+        };
+
+        let login_name = match runtime::timeout(
+            HANDSHAKE_TIMEOUT,
+            handshake(&mut writer, &mut reader, login),
+        )
+        .await
+        {
+            Some(result) => result?,
+            None => {
+                error!("PlayTak.com did not finish logging in.");
+                return Err(io::Error::from(io::ErrorKind::TimedOut));
+            }
+        };
+
+        info!("Logged in as {login_name}.");
+
+        let (ping_interval, read_timeout) = (
+            login.connection.ping_interval,
+            login.connection.read_timeout,
+        );
+
+        // Ping replies are what keep a quiet connection from timing out.
+        if read_timeout > 0 && (ping_interval == 0 || read_timeout <= ping_interval) {
+            warn!("The read timeout should be longer than the ping interval.");
+        }
+
+        let keep_alive = Arc::new(KeepAlive::default());
+
+        let (sender, receiver) = mpsc::channel(QUEUE_LENGTH);
+        let queue = Arc::new(Queue::default());
+        runtime::spawn(send_lines(writer, receiver, queue.clone(), settled));
+
+        let (line_sender, lines) = mpsc::unbounded();
+        let (reading, abort) = future::abortable(read_lines(
+            reader,
+            keep_alive.clone(),
+            Duration::from_secs(read_timeout),
+            line_sender,
+        ));
+        runtime::spawn(reading);
+
+        Ok(Self {
+            writer: SessionWriter { sender, queue },
+            reader: SessionReader {
+                lines,
+                keep_alive: keep_alive.clone(),
+                reading: abort,
+            },
+            name: login_name,
+            ping_interval: Duration::from_secs(ping_interval),
+            keep_alive,
+        })
+    }
+
+    // Connects and logs in, trying again after failures that could be temporary, like the
+    // server not answering or dropping the connection.  A rejected login won't fix itself.
+    pub async fn connect_retrying(login: &Login) -> io::Result<Self> {
+        let mut retry = 0;
+
+        loop {
+            match Self::connect(login).await {
+                Err(error)
+                    if error.kind() != io::ErrorKind::PermissionDenied
+                        && retry < login.connection.login_retries =>
+                {
+                    let delay = login_retry_delay(retry);
+                    warn!(
+                        %error,
+                        "Could not log in. Retrying in {} seconds.",
+                        delay.as_secs()
+                    );
+                    runtime::sleep(delay).await;
+                    retry += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Pings the server to keep the connection open, until the connection turns out to be dead.
+    // A ping interval of zero turns this off.
+    pub fn keep_alive(&self) -> impl Future<Output = io::Result<()>> + Send + 'static {
+        let (mut writer, keep_alive, interval) = (
+            self.writer.clone(),
+            self.keep_alive.clone(),
+            self.ping_interval,
+        );
+
+        async move {
+            if interval.is_zero() {
+                return Ok(());
+            }
+
+            loop {
+                runtime::sleep(interval).await;
+
+                if keep_alive.unanswered.load(Ordering::Relaxed) > 0 {
+                    warn!("PlayTak.com did not answer the last ping.");
+                    keep_alive.dead.store(true, Ordering::Relaxed);
+                    keep_alive.waker.wake();
+                    return Err(io::Error::from(io::ErrorKind::TimedOut));
+                }
+
+                keep_alive.unanswered.fetch_add(1, Ordering::Relaxed);
+                write(&mut writer, "PING\n").await?;
+            }
+        }
+    }
+}
+
+fn login_retry_delay(retry: u32) -> Duration {
+    LOGIN_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(retry))
+        .min(MAX_LOGIN_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_off_login_retries() {
+        let delays = (0..7).map(|retry| login_retry_delay(retry).as_secs());
+        assert_eq!(delays.collect::<Vec<_>>(), [2, 4, 8, 16, 32, 60, 60]);
+        assert_eq!(login_retry_delay(u32::MAX), MAX_LOGIN_RETRY_DELAY);
+    }
+
+    #[test]
+    fn handshake_steps() {
+        let mut step = Handshake::Greeting;
+        let mut advances = Vec::new();
+
+        for line in [
+            "Welcome!",
+            "Message of the day",
+            "Login or Register",
+            "Online 42",
+            "OK",
+            "Welcome!",
+            "Seek new 7 Alice 5 600 10 W 0 21 1 0 0 0 0",
+            "Welcome Guest123!",
+        ] {
+            if let Some(next) = step.advance(line).unwrap() {
+                advances.push(line);
+                step = next;
+            }
+        }
+
+        assert_eq!(step, Handshake::LoggedIn("Guest123".to_owned()));
+        assert_eq!(advances, ["Login or Register", "OK", "Welcome Guest123!"]);
+
+        assert_eq!(
+            Handshake::ClientName.advance("NOK").unwrap(),
+            Some(Handshake::Login)
+        );
+        assert_eq!(
+            Handshake::Login
+                .advance("Authentication failure")
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::PermissionDenied
+        );
+    }
+}
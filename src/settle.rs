@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpStream};
+use std::panic;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use super::{crash, err, trace};
+
+// What a panic has to tidy up: the engines still running, the connection to PlayTak.com, and
+// the game being played on it.  The handles are duplicates, written to directly from the panic
+// hook, since nothing async can run there.  Everything is registered with a guard that takes
+// it back out when it's dropped.
+#[derive(Default)]
+struct Settle {
+    engines: HashMap<u64, File>,
+    server: Option<(u64, TcpStream)>,
+    game: Option<(u64, u32)>,
+}
+
+static SETTLE: Mutex<Option<Settle>> = Mutex::new(None);
+static NEXT_KEY: AtomicU64 = AtomicU64::new(0);
+static RESIGN: AtomicBool = AtomicBool::new(false);
+// Whether a line to the server has been started and not finished, so that a resignation
+// written now would be garbled.
+static SENDING: AtomicBool = AtomicBool::new(false);
+
+#[must_use]
+pub struct Registration(u64);
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let key = self.0;
+        update(|settle| {
+            settle.engines.remove(&key);
+            if settle.server.as_ref().is_some_and(|(k, _)| *k == key) {
+                settle.server = None;
+            }
+            if settle.game.is_some_and(|(k, _)| k == key) {
+                settle.game = None;
+            }
+        });
+    }
+}
+
+fn update(f: impl FnOnce(&mut Settle)) {
+    f(SETTLE
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get_or_insert_with(Default::default));
+}
+
+fn register(f: impl FnOnce(&mut Settle, u64)) -> Registration {
+    let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+    update(|settle| f(settle, key));
+    Registration(key)
+}
+
+pub fn engine(input: &impl AsRawFd) -> Registration {
+    register(|settle, key| {
+        if let Some(file) = duplicate(input) {
+            settle.engines.insert(key, file);
+        }
+    })
+}
+
+pub fn server(stream: &impl AsRawFd) -> Registration {
+    register(|settle, key| settle.server = duplicate(stream).map(|stream| (key, stream)))
+}
+
+pub fn game(id: u32) -> Registration {
+    register(|settle, key| settle.game = Some((key, id)))
+}
+
+pub fn sending(sending: bool) {
+    SENDING.store(sending, Ordering::SeqCst);
+}
+
+// Installs the hook, which settles things before the usual panic output.  The game is left to
+// be resumed unless resigning was asked for.
+pub fn install(resign: bool) {
+    RESIGN.store(resign, Ordering::Relaxed);
+
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        settle();
+
+        let report = crash::write_report(&err!(format!("panic: {info}")));
+        previous(info);
+        if let Ok(path) = report {
+            eprintln!("A crash report was written to {}.", path.display());
+        }
+
+        trace::flush();
+        io::stdout().flush().ok();
+        io::stderr().flush().ok();
+    }));
+}
+
+// Nothing here can be reported if it fails, since the process is already going down.
+fn settle() {
+    let Some(mut settle) = SETTLE.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+
+    for input in settle.engines.values_mut() {
+        input.write_all(b"quit\n").ok();
+    }
+
+    if let Some((_, mut stream)) = settle.server {
+        if let Some((_, id)) = settle.game {
+            if RESIGN.load(Ordering::Relaxed) && !SENDING.load(Ordering::SeqCst) {
+                writeln!(stream, "Game#{id} Resign").ok();
+            }
+        }
+
+        // Nothing else goes out as the stack unwinds, so the game can be resumed from where
+        // the server has it.
+        stream.shutdown(Shutdown::Both).ok();
+    }
+}
+
+#[cfg(unix)]
+pub use std::os::fd::AsRawFd;
+
+#[cfg(unix)]
+fn duplicate<T: From<std::os::fd::OwnedFd>>(handle: &impl AsRawFd) -> Option<T> {
+    use std::os::fd::BorrowedFd;
+
+    // The handle is borrowed for as long as the duplicate takes to make.
+    let fd = unsafe { BorrowedFd::borrow_raw(handle.as_raw_fd()) };
+    fd.try_clone_to_owned().ok().map(T::from)
+}
+
+// There are no duplicates to be had elsewhere, so a panic just lets the engine be killed.
+#[cfg(not(unix))]
+pub trait AsRawFd {}
+
+#[cfg(not(unix))]
+impl<T> AsRawFd for T {}
+
+#[cfg(not(unix))]
+fn duplicate<T>(_handle: &impl AsRawFd) -> Option<T> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn release_registrations() {
+        let engine = engine(&io::stdout());
+        let key = engine.0;
+        update(|settle| assert!(settle.engines.contains_key(&key)));
+
+        drop(engine);
+        update(|settle| assert!(!settle.engines.contains_key(&key)));
+    }
+}
@@ -0,0 +1,85 @@
+use std::fs;
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+use tracing::warn;
+
+use super::game::Game;
+use super::limits::SessionStats;
+use super::seek::Seek;
+
+pub enum Status<'a> {
+    Connecting,
+    Seeking(&'a Seek),
+    Idle,
+    Playing(&'a Game),
+    Reconnecting,
+    Finished,
+}
+
+impl Status<'_> {
+    pub fn to_json(&self, stats: &SessionStats) -> Value {
+        let mut value = match self {
+            Status::Connecting => json!({ "state": "connecting" }),
+            Status::Seeking(seek) => json!({
+                "state": "seeking",
+                "seek": {
+                    "size": seek.size,
+                    "time": seek.time,
+                    "increment": seek.increment,
+                    "color": format!("{:?}", seek.color).to_lowercase(),
+                    "half_komi": seek.half_komi,
+                    "opponent": seek.opponent,
+                    "rated": !seek.unrated,
+                    "tournament": seek.tournament,
+                },
+            }),
+            Status::Idle => json!({ "state": "idle" }),
+            Status::Playing(game) => json!({
+                "state": "playing",
+                "game": {
+                    "id": game.id,
+                    "size": game.size,
+                    "opponent": game.opponent,
+                    "color": game.color.to_string(),
+                    "moves": game.moves.len(),
+                    "rated": !game.unrated,
+                    "tournament": game.tournament,
+                },
+            }),
+            Status::Reconnecting => json!({ "state": "reconnecting" }),
+            Status::Finished => json!({ "state": "finished" }),
+        };
+
+        if let Value::Object(map) = &mut value {
+            map.insert("games_played".to_owned(), stats.games().into());
+            map.insert("record".to_owned(), stats.record.to_json());
+        }
+
+        value
+    }
+}
+
+#[derive(Debug)]
+pub struct StatusFile {
+    path: PathBuf,
+}
+
+impl StatusFile {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn update(&self, status: Status, stats: &SessionStats) {
+        let value = status.to_json(stats);
+
+        // Write to a temporary file first so readers never see a partial status.
+        let temporary = self.path.with_extension("tmp");
+        let result = fs::write(&temporary, format!("{value:#}\n"))
+            .and_then(|_| fs::rename(&temporary, &self.path));
+
+        if let Err(error) = result {
+            warn!(%error, path = ?self.path, "Could not write status file.");
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use tracing::error;
+
+use super::board::Board;
+use super::engine::Engine;
+use super::game::{Game, GameMove};
+use super::seek::{capstones_for_size, flatstones_for_size};
+use super::{err, SuiteCommand};
+
+// A test position and the moves that solve it, from a line like
+//   x5/x5/x2,1,x2/x5/2,x4 1 2; bm c2 b3; id "cap threat"; komi 2
+// Either bm (best moves) or am (moves to avoid) has to be given.
+#[derive(Debug)]
+struct TestPosition {
+    id: String,
+    game: Game,
+    best: Vec<GameMove>,
+    avoid: Vec<GameMove>,
+}
+
+impl TestPosition {
+    fn parse(line: &str, number: usize) -> io::Result<Self> {
+        let mut fields = line.split(';').map(str::trim);
+        let tps = fields.next().unwrap_or_default();
+        let size = Board::from_tps(tps)?.size();
+
+        let mut position = Self {
+            id: format!("#{number}"),
+            game: Game {
+                size,
+                flatstones: flatstones_for_size(size),
+                capstones: capstones_for_size(size),
+                tps: Some(tps.to_owned()),
+                ..Default::default()
+            },
+            best: Vec::new(),
+            avoid: Vec::new(),
+        };
+
+        let moves = |list: &str| -> io::Result<Vec<GameMove>> {
+            list.split_ascii_whitespace()
+                .map(GameMove::from_ptn)
+                .collect()
+        };
+
+        for field in fields.filter(|f| !f.is_empty()) {
+            let (operation, operand) = field.split_once(' ').unwrap_or((field, ""));
+            match operation {
+                "bm" => position.best = moves(operand)?,
+                "am" => position.avoid = moves(operand)?,
+                "id" => position.id = operand.trim_matches('"').to_owned(),
+                "komi" => {
+                    let komi = operand.parse::<f32>().map_err(|_| err!("invalid komi"))?;
+                    position.game.half_komi = (komi * 2.0) as i32;
+                }
+                _ => return Err(err!(format!("unknown operation \"{operation}\""))),
+            }
+        }
+
+        if position.best.is_empty() && position.avoid.is_empty() {
+            return Err(err!("a position needs a bm or am operation"));
+        }
+
+        Ok(position)
+    }
+
+    fn is_solved_by(&self, game_move: &GameMove) -> bool {
+        (self.best.is_empty() || self.best.contains(game_move)) && !self.avoid.contains(game_move)
+    }
+}
+
+fn load_suite(path: &Path) -> io::Result<Vec<TestPosition>> {
+    fs::read_to_string(path)?
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| {
+            TestPosition::parse(line, index + 1)
+                .map_err(|error| err!(format!("line {}: {error}", index + 1)))
+        })
+        .collect()
+}
+
+// Has the engine search each of a file's test positions at a fixed time or depth, and reports
+// how many it solved and how quickly.
+pub async fn run_suite(command: &SuiteCommand) -> io::Result<()> {
+    let positions = load_suite(&command.suite).map_err(|error| {
+        error!(%error, "Could not load the test suite.");
+        error
+    })?;
+    let Some(first) = positions.first() else {
+        error!("The test suite has no positions.");
+        return Err(err!());
+    };
+
+    let limit = match command.depth {
+        Some(depth) => format!("depth {depth}"),
+        None => format!("movetime {}", command.movetime),
+    };
+
+    let mut engine = Engine::initialize(
+        &command.engine_arguments,
+        &command.engine_settings,
+        &first.game,
+    )
+    .await?;
+    engine
+        .warm_up(&command.engine_settings, first.game.size)
+        .await?;
+
+    let mut solved = Vec::new();
+
+    for position in &positions {
+        engine.configure(&position.game).await?;
+
+        // The solution is found once the principal variation starts with a solving move and
+        // doesn't change its mind afterwards.
+        let started = Instant::now();
+        let mut found = None;
+        let best_move = engine
+            .analyze(&position.game, &limit, |line| {
+                let first_move = line
+                    .strip_prefix("info ")
+                    .and_then(|info| info.split_once(" pv "))
+                    .and_then(|(_, pv)| pv.split_ascii_whitespace().next())
+                    .and_then(|m| GameMove::from_ptn(m).ok());
+
+                match first_move {
+                    Some(m) if position.is_solved_by(&m) => {
+                        found.get_or_insert_with(|| started.elapsed());
+                    }
+                    Some(_) => found = None,
+                    None => (),
+                }
+            })
+            .await?;
+        let elapsed = started.elapsed();
+
+        if position.is_solved_by(&best_move) {
+            let time = found.unwrap_or(elapsed);
+            println!(
+                "{}: solved with {} in {:.2}s",
+                position.id,
+                best_move.to_ptn(),
+                time.as_secs_f64()
+            );
+            solved.push(time);
+        } else {
+            println!("{}: not solved, played {}", position.id, best_move.to_ptn());
+        }
+    }
+
+    engine.quit().await?;
+
+    let average = match solved.len() {
+        0 => Duration::ZERO,
+        count => solved.iter().sum::<Duration>() / count as u32,
+    };
+    println!(
+        "\nSolved {} of {} ({:.0}%), in {:.2}s on average.",
+        solved.len(),
+        positions.len(),
+        100.0 * solved.len() as f64 / positions.len() as f64,
+        average.as_secs_f64()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test_positions() {
+        let position = TestPosition::parse(
+            "x5/x5/x2,1,x2/x5/2,x4 1 2; bm c2 b3; id \"cap threat\"; komi 2.5",
+            1,
+        )
+        .unwrap();
+        assert_eq!(position.id, "cap threat");
+        assert_eq!(position.game.size, 5);
+        assert_eq!(position.game.half_komi, 5);
+        assert!(position.is_solved_by(&GameMove::from_ptn("b3").unwrap()));
+        assert!(!position.is_solved_by(&GameMove::from_ptn("a1").unwrap()));
+
+        let position = TestPosition::parse("x3/x3/x3 1 1; am a1", 4).unwrap();
+        assert_eq!(position.id, "#4");
+        assert!(position.is_solved_by(&GameMove::from_ptn("b2").unwrap()));
+        assert!(!position.is_solved_by(&GameMove::from_ptn("a1").unwrap()));
+
+        assert!(TestPosition::parse("x3/x3/x3 1 1", 1).is_err());
+        assert!(TestPosition::parse("x3/x3/x3 1 1; bm a1; pv a1", 1).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+// A dump of every line exchanged with PlayTak.com and the engine, for bug reports.  Each line is
+// marked with the seconds since the trace started, who it was exchanged with, and > for sent or
+// < for received.  It's written whatever the log filter, and nothing is written until it's
+// enabled.
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STARTED: OnceLock<Instant> = OnceLock::new();
+static OUTPUT: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+// Traces to the file, or to stderr without one.
+pub fn enable(path: Option<&Path>) -> io::Result<()> {
+    let output: Box<dyn Write + Send> = match path {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stderr()),
+    };
+
+    *OUTPUT.lock().unwrap_or_else(|e| e.into_inner()) = Some(output);
+    STARTED.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn record(party: &str, marker: char, line: &str) {
+    if !is_enabled() {
+        return;
+    }
+
+    let elapsed = STARTED.get_or_init(Instant::now).elapsed().as_secs_f64();
+    let mut output = OUTPUT.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(output) = output.as_mut() {
+        // There's nowhere left to report a failure to write the trace.
+        writeln!(
+            output,
+            "{elapsed:12.6} {party:<7} {marker} {}",
+            redact(marker, line)
+        )
+        .ok();
+        output.flush().ok();
+    }
+}
+
+pub fn flush() {
+    if let Some(output) = OUTPUT.lock().unwrap_or_else(|e| e.into_inner()).as_mut() {
+        output.flush().ok();
+    }
+}
+
+// The trace is meant to be shared, so passwords are left out of it.
+pub fn redact(marker: char, line: &str) -> Cow<'_, str> {
+    match line.split(' ').collect::<Vec<_>>()[..] {
+        ["Login", name, _] if marker == '>' => Cow::Owned(format!("Login {name} ********")),
+        _ => Cow::Borrowed(line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_passwords() {
+        assert_eq!(redact('>', "Login Alice hunter2"), "Login Alice ********");
+        assert_eq!(redact('>', "Login Guest"), "Login Guest");
+        assert_eq!(redact('<', "Login or Register"), "Login or Register");
+        assert_eq!(redact('>', "Game#7 P A1"), "Game#7 P A1");
+    }
+}
@@ -0,0 +1,205 @@
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::{Instant, SystemTime};
+
+use futures::{AsyncWrite, Stream};
+use tracing::warn;
+
+use super::game::{utc_date_time, Game};
+use super::{crash, trace};
+
+// A raw record of every line exchanged with one party during a game, each marked with the
+// seconds since the game started and > for sent or < for received.  It's written whatever the
+// log level, for when what was actually said is in question.
+#[derive(Clone, Debug)]
+pub struct Transcript {
+    file: Arc<Mutex<File>>,
+    started: Instant,
+}
+
+impl Transcript {
+    // Creates the game's two transcripts: one for PlayTak.com, one for the engine.
+    pub fn create(directory: &Path, game: &Game) -> io::Result<(Self, Self)> {
+        fs::create_dir_all(directory)?;
+
+        let (date, time) = utc_date_time(SystemTime::now());
+        let stem = format!("{}-{date}-{}", game.id, time.replace(':', ""));
+        let started = Instant::now();
+
+        let open = |name: &str| -> io::Result<Self> {
+            Ok(Self {
+                file: Arc::new(Mutex::new(File::create(
+                    directory.join(format!("{stem}-{name}.txt")),
+                )?)),
+                started,
+            })
+        };
+
+        Ok((open("playtak")?, open("tei")?))
+    }
+
+    fn record(&self, marker: char, line: &str) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Err(error) = writeln!(file, "{elapsed:10.3} {marker} {line}") {
+            warn!(%error, "Could not write to a transcript.");
+        }
+    }
+}
+
+// Passes a reader or writer's lines through, copying them to a transcript while one is set, and
+// to the trace and crash report if it's named for one.
+pub struct Transcribed<T> {
+    inner: T,
+    transcript: Option<Transcript>,
+    party: Option<&'static str>,
+    // What's been written of a line that isn't finished yet.
+    partial: Vec<u8>,
+}
+
+impl<T> Transcribed<T> {
+    pub fn new(inner: T, transcript: Option<Transcript>) -> Self {
+        Self {
+            inner,
+            transcript,
+            party: None,
+            partial: Vec::new(),
+        }
+    }
+
+    // Names who's on the other end in the trace.
+    pub fn traced(mut self, party: &'static str) -> Self {
+        self.party = Some(party);
+        self
+    }
+
+    fn record(&self, marker: char, line: &str) {
+        if let Some(transcript) = &self.transcript {
+            transcript.record(marker, line);
+        }
+        if let Some(party) = self.party {
+            trace::record(party, marker, line);
+            crash::record(party, marker, line);
+        }
+    }
+
+    fn is_recording(&self) -> bool {
+        self.transcript.is_some() || self.party.is_some()
+    }
+
+    pub fn set_transcript(&mut self, transcript: Option<Transcript>) {
+        self.transcript = transcript;
+        self.partial.clear();
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for Transcribed<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        let written = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+
+        if this.is_recording() {
+            this.partial.extend_from_slice(&buf[..written]);
+            while let Some(end) = this.partial.iter().position(|&b| b == b'\n') {
+                let line = this.partial.drain(..=end).collect::<Vec<_>>();
+                this.record('>', String::from_utf8_lossy(&line).trim_end());
+            }
+        }
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+impl<T: Stream<Item = io::Result<String>> + Unpin> Stream for Transcribed<T> {
+    type Item = io::Result<String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = ready!(Pin::new(&mut self.inner).poll_next(cx));
+
+        if let Some(Ok(line)) = &item {
+            self.record('<', line);
+        }
+
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::{stream, AsyncWriteExt, StreamExt};
+
+    #[test]
+    fn transcribe_lines() {
+        let directory = std::env::temp_dir().join(format!("transcript-{}", std::process::id()));
+        let game = "Game Start 7 5 Alice vs Bob white 600 0 21 1 0 0"
+            .parse::<Game>()
+            .unwrap();
+        let (transcript, _) = Transcript::create(&directory, &game).unwrap();
+
+        let mut writer = Transcribed::new(Vec::new(), Some(transcript.clone()));
+        block_on(writer.write_all(b"Game#7 P A")).unwrap();
+        block_on(writer.write_all(b"1\n")).unwrap();
+
+        let lines = stream::iter(vec![Ok("Game#7 Time 600 600".to_owned())]);
+        let mut reader = Transcribed::new(lines, Some(transcript));
+        block_on(reader.next());
+
+        let path = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .find(|path| path.to_string_lossy().ends_with("-playtak.txt"))
+            .unwrap();
+        let contents = fs::read_to_string(path).unwrap();
+        fs::remove_dir_all(&directory).ok();
+
+        let lines = contents
+            .lines()
+            .map(|line| line.trim_start().split_once(' ').unwrap().1)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, ["> Game#7 P A1", "< Game#7 Time 600 600"]);
+    }
+
+    #[test]
+    fn trace_lines() {
+        let path = std::env::temp_dir().join(format!("trace-{}.txt", std::process::id()));
+        trace::enable(Some(&path)).unwrap();
+
+        let mut writer = Transcribed::new(Vec::new(), None).traced("tei");
+        block_on(writer.write_all(b"go movetime 1000\n")).unwrap();
+        let lines = stream::iter(vec![Ok("bestmove a1".to_owned())]);
+        block_on(Transcribed::new(lines, None).traced("tei").next());
+
+        let contents = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).ok();
+
+        let lines = contents
+            .lines()
+            .map(|line| {
+                line.split_whitespace()
+                    .skip(1)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(lines, ["tei > go movetime 1000", "tei < bestmove a1"]);
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+use std::io;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use tracing::error;
+
+use super::engine;
+use super::live::{self, LiveState};
+
+// How often the screen is redrawn, which is also how long a key press can go unnoticed.
+const REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+// A terminal dashboard with the board, clocks, the engine's thinking, chat, and the traffic
+// with the server, shown in place of the usual log output.  The log goes to a pane of its own.
+// The terminal is restored when this is dropped.
+pub struct Dashboard {
+    done: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Dashboard {
+    pub fn start() -> io::Result<Self> {
+        live::enable();
+        engine::discard_stderr();
+
+        tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(|| LogWriter)
+            .init();
+
+        let mut terminal = ratatui::try_init()?;
+        let done = Arc::new(AtomicBool::new(false));
+
+        let thread = thread::spawn({
+            let done = done.clone();
+            move || {
+                let result = run(&mut terminal, &done);
+                ratatui::restore();
+
+                match result {
+                    Ok(true) => (),
+                    // Quitting from the dashboard ends the whole program, like an interrupt would.
+                    Ok(false) => process::exit(0),
+                    Err(error) => error!(%error, "The dashboard failed."),
+                }
+            }
+        });
+
+        Ok(Self {
+            done,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+// Sends log output to the dashboard instead of the terminal.
+struct LogWriter;
+
+impl io::Write for LogWriter {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        live::log(&String::from_utf8_lossy(buffer));
+        Ok(buffer.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Returns true if the dashboard was closed by the program, or false if the user quit.
+fn run(terminal: &mut DefaultTerminal, done: &AtomicBool) -> io::Result<bool> {
+    while !done.load(Ordering::Relaxed) {
+        let state = live::snapshot();
+        terminal.draw(|frame| draw(frame, &state))?;
+
+        if event::poll(REFRESH_INTERVAL)? {
+            if let Event::Key(key) = event::read()? {
+                let interrupt =
+                    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+
+                if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || interrupt)
+                {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+fn draw(frame: &mut Frame, state: &LiveState) {
+    let [left, right] =
+        Layout::horizontal([Constraint::Length(40), Constraint::Min(20)]).areas(frame.area());
+    let [board_area, clock_area, engine_area] = Layout::vertical([
+        Constraint::Length(12),
+        Constraint::Length(4),
+        Constraint::Min(4),
+    ])
+    .areas(left);
+    let [chat_area, protocol_area, log_area] = Layout::vertical([
+        Constraint::Percentage(25),
+        Constraint::Percentage(35),
+        Constraint::Percentage(40),
+    ])
+    .areas(right);
+
+    match &state.game {
+        Some(game) => {
+            let board = game
+                .board
+                .as_ref()
+                .map(|b| b.to_string())
+                .unwrap_or_default();
+            frame.render_widget(
+                Paragraph::new(board)
+                    .block(Block::bordered().title(format!(" Game #{} ", game.id))),
+                board_area,
+            );
+
+            let clock = |seconds: u32| format!("{}:{:02}", seconds / 60, seconds % 60);
+            let mut clocks = vec![
+                Line::from(format!("White  {}  {}", clock(game.time.0), game.white)),
+                Line::from(format!("Black  {}  {}", clock(game.time.1), game.black)),
+            ];
+            if let Some(result) = &game.result {
+                clocks[0] = Line::from(format!("Result {result}"));
+            }
+            frame.render_widget(
+                Paragraph::new(clocks).block(Block::bordered().title(" Clocks ")),
+                clock_area,
+            );
+
+            let engine = vec![
+                Line::from(format!("Score  {}", game.score.as_deref().unwrap_or("-"))),
+                Line::from(format!("PV     {}", game.pv.as_deref().unwrap_or("-"))),
+                Line::from(""),
+                Line::from(game.moves.join(" ")),
+            ];
+            frame.render_widget(
+                Paragraph::new(engine)
+                    .wrap(Wrap { trim: false })
+                    .block(Block::bordered().title(" Engine ")),
+                engine_area,
+            );
+        }
+        None => {
+            frame.render_widget(
+                Paragraph::new("No game in progress.").block(Block::bordered().title(" Game ")),
+                left,
+            );
+        }
+    }
+
+    draw_lines(frame, chat_area, " Chat ", &state.chat);
+    draw_lines(frame, protocol_area, " PlayTak.com ", &state.protocol);
+    draw_lines(frame, log_area, " Log ", &state.log);
+}
+
+// Shows as many of the most recent lines as fit.
+fn draw_lines(frame: &mut Frame, area: Rect, title: &str, lines: &VecDeque<String>) {
+    let height = area.height.saturating_sub(2) as usize;
+    let lines = lines
+        .iter()
+        .skip(lines.len().saturating_sub(height))
+        .map(|line| Line::from(line.as_str()))
+        .collect::<Vec<_>>();
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(title)),
+        area,
+    );
+}
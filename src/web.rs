@@ -0,0 +1,216 @@
+use std::time::Duration;
+
+use futures::{stream, Stream, StreamExt};
+use serde_json::{json, Value};
+
+use super::http::{self, Request, Response};
+use super::live::{self, LiveState};
+use super::runtime::{self, TcpListener};
+
+// How often event streams check for something new to send.
+const EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+// How many chat lines the page is sent.
+const CHAT_LINES: usize = 20;
+
+// A page for watching the current game from a browser: the board, clocks, the engine's
+// evaluation over the game, and the moves, kept up to date with server-sent events.
+pub async fn run_dashboard(listener: TcpListener) {
+    live::enable();
+    http::serve(listener, handle).await
+}
+
+async fn handle(request: Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => Response::html(PAGE),
+        ("GET", "/state") => Response::json(state_json(&live::snapshot())),
+        ("GET", "/events") => Response::Events {
+            events: events().boxed(),
+            shared: false,
+        },
+        // Without the chat, which can be private, other sites' pages like stream overlays can
+        // follow the game.
+        ("GET", "/game/events") => Response::Events {
+            events: game_events().boxed(),
+            shared: true,
+        },
+        ("GET", _) => Response::error(404, "not found"),
+        _ => Response::error(405, "method not allowed"),
+    }
+}
+
+// The state as JSON whenever it changes, starting with how it is now.
+fn events() -> impl Stream<Item = String> {
+    stream::unfold(None, |last| async move {
+        loop {
+            let state = live::snapshot();
+            if Some(state.version) != last {
+                return Some((state_json(&state).to_string(), Some(state.version)));
+            }
+
+            runtime::sleep(EVENT_INTERVAL).await;
+        }
+    })
+}
+
+// The game as JSON whenever it changes, leaving out chat and everything else.
+fn game_events() -> impl Stream<Item = String> {
+    stream::unfold(None, |last: Option<String>| async move {
+        loop {
+            let game = game_json(&live::snapshot()).to_string();
+            if last.as_ref() != Some(&game) {
+                return Some((game.clone(), Some(game)));
+            }
+
+            runtime::sleep(EVENT_INTERVAL).await;
+        }
+    })
+}
+
+fn game_json(state: &LiveState) -> Value {
+    let Some(game) = &state.game else {
+        return Value::Null;
+    };
+
+    json!({
+        "id": game.id,
+        "white": game.white,
+        "black": game.black,
+        "tps": game.board.as_ref().map(|b| b.to_tps()),
+        "time": [game.time.0, game.time.1],
+        "moves": game.moves,
+        "result": game.result,
+        "pv": game.pv,
+        "score": game.score,
+        "evals": game.evals,
+        "ponder": game.ponder,
+    })
+}
+
+fn state_json(state: &LiveState) -> Value {
+    let chat = state
+        .chat
+        .iter()
+        .skip(state.chat.len().saturating_sub(CHAT_LINES))
+        .collect::<Vec<_>>();
+
+    json!({ "game": game_json(state), "chat": chat })
+}
+
+const PAGE: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>playtak-tei</title>
+<style>
+  body { font-family: sans-serif; background: #222; color: #ddd; display: flex; gap: 2em; padding: 1em; }
+  svg { background: #333; }
+  .clock { font-size: 1.4em; font-family: monospace; }
+  #moves { font-family: monospace; max-width: 30em; }
+  #chat { font-family: monospace; font-size: 0.9em; white-space: pre-wrap; max-width: 40em; }
+</style>
+</head>
+<body>
+<div>
+  <h2 id="title">No game in progress</h2>
+  <svg id="board" width="480" height="480"></svg>
+</div>
+<div>
+  <p class="clock" id="white"></p>
+  <p class="clock" id="black"></p>
+  <p id="result"></p>
+  <p>Score: <span id="score">-</span><br>PV: <span id="pv">-</span></p>
+  <svg id="evals" width="400" height="120"></svg>
+  <p id="moves"></p>
+  <div id="chat"></div>
+</div>
+<script>
+const NS = "http://www.w3.org/2000/svg";
+
+function element(name, attributes, parent) {
+  const e = document.createElementNS(NS, name);
+  for (const [k, v] of Object.entries(attributes)) e.setAttribute(k, v);
+  parent.appendChild(e);
+  return e;
+}
+
+function clock(seconds) {
+  return Math.floor(seconds / 60) + ":" + String(seconds % 60).padStart(2, "0");
+}
+
+function drawBoard(tps) {
+  const svg = document.getElementById("board");
+  svg.innerHTML = "";
+  if (!tps) return;
+
+  const rows = tps.split(" ")[0].split("/");
+  const size = rows.length, cell = 480 / size;
+  rows.forEach((row, r) => {
+    let x = 0;
+    for (const square of row.split(",")) {
+      if (square.startsWith("x")) {
+        for (let i = 0; i < (parseInt(square.slice(1)) || 1); i++) {
+          element("rect", { x: x * cell + 1, y: r * cell + 1, width: cell - 2, height: cell - 2, fill: "#555" }, svg);
+          x++;
+        }
+        continue;
+      }
+
+      element("rect", { x: x * cell + 1, y: r * cell + 1, width: cell - 2, height: cell - 2, fill: "#555" }, svg);
+      const kind = /[SC]$/.test(square) ? square.slice(-1) : "";
+      const stack = kind ? square.slice(0, -1) : square;
+      const color = stack.slice(-1) === "1" ? "#eee" : "#111";
+      const cx = x * cell + cell / 2, cy = r * cell + cell / 2, s = cell * 0.5;
+      if (kind === "C") {
+        element("circle", { cx, cy, r: s / 2, fill: color, stroke: "#888" }, svg);
+      } else if (kind === "S") {
+        element("rect", { x: cx - s / 8, y: cy - s / 2, width: s / 4, height: s, fill: color, stroke: "#888" }, svg);
+      } else {
+        element("rect", { x: cx - s / 2, y: cy - s / 2, width: s, height: s, fill: color, stroke: "#888" }, svg);
+      }
+      if (stack.length > 1) {
+        const label = element("text", { x: x * cell + 4, y: r * cell + 16, fill: "#fc6", "font-size": 14 }, svg);
+        label.textContent = stack;
+      }
+      x++;
+    }
+  });
+}
+
+function drawEvals(evals) {
+  const svg = document.getElementById("evals");
+  svg.innerHTML = "";
+  element("line", { x1: 0, y1: 60, x2: 400, y2: 60, stroke: "#666" }, svg);
+  if (evals.length < 2) return;
+
+  const last = evals[evals.length - 1][0];
+  const points = evals.map(([ply, cp]) => {
+    const clamped = Math.max(-1000, Math.min(1000, cp));
+    return (ply / last) * 400 + "," + (60 - clamped * 0.055);
+  });
+  element("polyline", { points: points.join(" "), fill: "none", stroke: "#fc6", "stroke-width": 2 }, svg);
+}
+
+function update(state) {
+  const game = state.game;
+  document.getElementById("chat").textContent = state.chat.join("\n");
+  if (!game) return;
+
+  document.getElementById("title").textContent = "Game #" + game.id;
+  document.getElementById("white").textContent = "White " + clock(game.time[0]) + " " + game.white;
+  document.getElementById("black").textContent = "Black " + clock(game.time[1]) + " " + game.black;
+  document.getElementById("result").textContent = game.result ? "Result: " + game.result : "";
+  document.getElementById("score").textContent = game.score || "-";
+  document.getElementById("pv").textContent = game.pv || "-";
+  document.getElementById("moves").textContent = game.moves
+    .map((m, i) => (i % 2 === 0 ? (i / 2 + 1) + ". " : "") + m)
+    .join(" ");
+  drawBoard(game.tps);
+  drawEvals(game.evals);
+}
+
+new EventSource("/events").onmessage = (event) => update(JSON.parse(event.data));
+</script>
+</body>
+</html>
+"##;